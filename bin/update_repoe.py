@@ -1,21 +1,55 @@
 #!/usr/bin/env python3
 
 import requests
+import json
 import os
+import subprocess
 
 DATA = {
-    "rust/domain/dist/base_items.min.json",
-    "rust/domain/dist/stat_translations.min.json",
-    "rust/domain/dist/stats.min.json",
-    "rust/domain/dist/mods.min.json",
+    "rust/domain/dist/base_items.min.json": "base_items.min.json",
+    "rust/domain/dist/stat_translations.min.json": "stat_translations.min.json",
+    "rust/domain/dist/stats.min.json": "stats.min.json",
+    "rust/domain/dist/mods.min.json": "mods.min.json",
+    "rust/domain/dist/poe2/base_items.min.json": "poe2/base_items.min.json",
+    "rust/domain/dist/poe2/mods.min.json": "poe2/mods.min.json",
+}
+
+# mods are embedded as prepared binary data
+MOD_DATA = {
+    "rust/domain/dist/mods.min.json": "rust/domain/dist/mods.data",
+    "rust/domain/dist/poe2/mods.min.json": "rust/domain/dist/poe2/mods.data",
 }
 
 BASE_URL = "https://repoe-fork.github.io/"
 
+# socket color of a gem is decided by its primary attribute tag
+GEM_COLORS = {"strength": "R", "dexterity": "G", "intelligence": "B"}
+
+
+def gem_colors():
+    r = requests.get(BASE_URL+"gems.min.json")
+    r.raise_for_status()
+    colors = {}
+    for gem_id, gem in r.json().items():
+        color = next((GEM_COLORS[t] for t in gem.get("tags", []) if t in GEM_COLORS), None)
+        if color:
+            colors[gem_id] = color
+    with open("rust/domain/dist/gem_colors.min.json", "w+") as f:
+        json.dump(dict(sorted(colors.items())), f, separators=(",", ":"))
+
 if __name__ == '__main__':
-    for data in DATA:
-        basename = os.path.basename(data)
-        r = requests.get(BASE_URL+basename)
+    for data, remote in DATA.items():
+        os.makedirs(os.path.dirname(data), exist_ok=True)
+        r = requests.get(BASE_URL+remote)
+        r.raise_for_status()
         with open(data, "w+") as f:
             f.write(r.text)
 
+    gem_colors()
+
+    for mods, output in MOD_DATA.items():
+        subprocess.run(
+            ["cargo", "run", "-p", "domain", "--bin", "binary_mod_data", "--",
+             os.path.abspath(mods), os.path.abspath(output)],
+            cwd="rust", check=True)
+
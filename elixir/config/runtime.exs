@@ -38,6 +38,11 @@ if config_env() != :test do
     interval: env!("POE_STASH_API_INTERVAL", :integer!),
     long_interval: env!("POE_STASH_API_LONG_INTERVAL", :integer!)
 
+  # e.g. local replay server for recorded stashes
+  if url = System.get_env("POE_STASH_API_URL") do
+    config :poe_system, PoeSystem.StashReceiver, url: url
+  end
+
   config :logger, level: env!("LOG_LEVEL", :atom!, :info)
 end
 
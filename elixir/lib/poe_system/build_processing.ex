@@ -87,8 +87,9 @@ defmodule PoeSystem.BuildProcessing do
     find_similar(item, Native.get_stored_item_type(item.item))
   end
 
-  def find_similar(%NativeItem{item: %Item{rarity: "unique"} = item}, {:ok, _}),
-    do: fetch_from_poeninja(item)
+  def find_similar(%NativeItem{item: %Item{rarity: rarity} = item}, {:ok, _})
+      when rarity in ["unique", "relic"],
+      do: fetch_from_poeninja(item)
 
   def find_similar(%NativeItem{item: %Item{subcategory: :gem} = item}, {:ok, _}) do
     case PoeNinja.get_item(item.name) do
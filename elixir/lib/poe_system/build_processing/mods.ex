@@ -7,6 +7,7 @@ defmodule PoeSystem.BuildProcessing.Mods do
     |> Enum.filter(fn
       {_, :exist} -> true
       {_, :exact} -> true
+      {_, {:min, _}} -> true
       _ -> false
     end)
     |> Enum.map(fn {%ModStatId{value: value}, _} -> value end)
@@ -24,6 +25,18 @@ defmodule PoeSystem.BuildProcessing.Mods do
     |> Items.append_name(item.name)
   end
 
+  defp append_query_option(q, %{option: {:unique_variant, %{mods: mods}}}, %{item: item}) do
+    q
+    |> Items.append_name(item.name)
+    |> Items.append_mods(Enum.map(mods, fn %ModStatId{value: value} -> value end))
+  end
+
+  defp append_query_option(q, %{option: {:unique_rolls, %{mods: mods}}}, %{item: item}) do
+    q
+    |> Items.append_name(item.name)
+    |> Items.append_mods(extract_mods_for_search(mods))
+  end
+
   defp append_query_basetype(q, %{basetype: false}, _), do: q
 
   defp append_query_basetype(q, %{basetype: true}, %{item: item}) do
@@ -47,6 +60,7 @@ defmodule PoeSystem.BuildProcessing.Mods do
   end
 
   def unique?(%Item{rarity: "unique"}), do: true
+  def unique?(%Item{rarity: "relic"}), do: true
   def unique?(%Item{}), do: false
 
   def gem?(%Item{category: :gems}), do: true
@@ -12,7 +12,7 @@ defmodule PoeSystem.EctoTypes.Info do
   def load(%{"type" => type} = info) do
     t =
       type
-      |> String.downcase()
+      |> Macro.underscore()
       |> String.to_atom()
 
     values =
@@ -32,7 +32,7 @@ defmodule PoeSystem.EctoTypes.Info do
     t =
       a
       |> Atom.to_string()
-      |> String.capitalize()
+      |> Macro.camelize()
 
     v =
       (Enum.into(b, []) ++ [{"type", t}])
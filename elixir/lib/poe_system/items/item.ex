@@ -38,12 +38,16 @@ defmodule PoeSystem.Items.Item do
         :helmets,
         :belt,
         :ring,
-        :quiver
+        :quiver,
+        :focus,
+        :charm
       ]
 
     field :name, :string
     field :price, PoeSystem.EctoTypes.Price
     field :rarity, :string
+    field :sockets, :string
+    field :game_version, Ecto.Enum, values: [:poe1, :poe2], default: :poe1
   end
 
   Protocol.derive(Jason.Encoder, __MODULE__, except: [:__meta__])
@@ -58,7 +62,19 @@ defmodule PoeSystem.Items.Item do
     item
     |> cast(
       attrs,
-      [:id, :item_id, :basetype, :category, :subcategory, :name, :price, :rarity, :info],
+      [
+        :id,
+        :item_id,
+        :basetype,
+        :category,
+        :subcategory,
+        :name,
+        :price,
+        :rarity,
+        :info,
+        :sockets,
+        :game_version
+      ],
       empty_values: []
     )
     |> validate_required([
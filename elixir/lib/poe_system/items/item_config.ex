@@ -7,12 +7,13 @@ defmodule PoeSystem.Items.ItemConfig do
   embedded_schema do
     field :basetype, :boolean
     field :option, :map
+    field :sockets, :map
   end
 
   Protocol.derive(Jason.Encoder, __MODULE__)
 
   def changeset(struct, data) do
     struct
-    |> cast(data, [:basetype, :option])
+    |> cast(data, [:basetype, :option, :sockets])
   end
 end
@@ -14,6 +14,7 @@ defmodule PoeSystem.StashReceiver do
              interval: [type: :pos_integer, default: :timer.seconds(1)],
              long_interval: [type: :pos_integer, default: :timer.seconds(60)],
              plug: [type: :any, default: nil],
+             url: [type: :string],
              access_token: [required: true, type: :string],
              disabled: [type: :boolean],
              league: [type: {:list, :string}, default: []]
@@ -1,10 +1,12 @@
 defmodule PoeSystem.StashReceiver.Client do
+  @default_url "https://api.pathofexile.com/public-stash-tabs"
+
   def get_stash_data(next_stash_id, opts) do
     plug = Map.get(opts, :plug)
 
     req =
       Req.new(
-        url: "https://api.pathofexile.com/public-stash-tabs",
+        url: Map.get(opts, :url, @default_url),
         headers: [
           user_agent: "OAuth somepoetools/0.1.0 (contact: bladoff@gmail.com)"
         ],
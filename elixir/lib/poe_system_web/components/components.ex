@@ -1,6 +1,7 @@
 defmodule PoeSystemWeb.Components do
   use PoeSystemWeb, :html
-  alias PoeSystem.Items.{Item, ItemConfig}
+  alias PoeSystem.Items.{Item, ItemConfig, NativeItem}
+  alias RustPoe.Native
 
   attr :type, :atom, required: true
   attr :data, :any, required: true
@@ -17,7 +18,7 @@ defmodule PoeSystemWeb.Components do
 
   attr :name, :string
   attr :basetype, :string, required: true
-  attr :rarity, :string, required: true, values: ~w(normal magic rare unique)
+  attr :rarity, :string, required: true, values: ~w(normal magic rare unique relic)
   # tuple {:atom, :list}
   attr :info, :any, required: true
   slot :mods_block
@@ -30,7 +31,8 @@ defmodule PoeSystemWeb.Components do
         "normal" => "border-neutral-500",
         "magic" => "border-blue-500",
         "rare" => "border-yellow-500",
-        "unique" => "border-orange-500"
+        "unique" => "border-orange-500",
+        "relic" => "border-green-500"
       })
 
     ~H"""
@@ -78,6 +80,9 @@ defmodule PoeSystemWeb.Components do
             <.label position="end" text="unique" type="label">
               <.checkbox checked={@config.option && @config.option == :unique} />
             </.label>
+            <.link href={trade_url(@item, @config)} target="_blank" class="underline">
+              open on trade
+            </.link>
           </div>
         </div>
       </:name_block>
@@ -92,6 +97,18 @@ defmodule PoeSystemWeb.Components do
     """
   end
 
+  defp trade_url(item, config) do
+    league =
+      Application.get_env(:poe_system, PoeSystem.StashReceiver, [])
+      |> Keyword.get(:league, [])
+      |> List.first("Standard")
+
+    case Native.trade_search_url(%NativeItem{item: item, config: config}, league) do
+      {:ok, url} -> url
+      _ -> nil
+    end
+  end
+
   attr :item, Item, required: true
 
   def item_simple(assigns) do
@@ -152,6 +169,12 @@ defmodule PoeSystemWeb.Components do
     """
   end
 
+  def mod_config_opt(%{opt: {_, {:min, _}}} = assigns) do
+    ~H"""
+    Min
+    """
+  end
+
   def mod_config_opt(%{opt: nil} = assigns) do
     ~H""
   end
@@ -11,7 +11,7 @@ defmodule PoeSystemWeb.LiveComponents.PobReceive do
           field={f[:pob]}
           id="pobdata"
           label="Path of Building data"
-          placeholder="base64-encoded string"
+          placeholder="base64-encoded string or pobb.in, poe.ninja, pastebin link"
           type="textarea"
         />
         <.button phx-disable-with="Loading items...">Save</.button>
@@ -21,7 +21,8 @@ defmodule PoeSystemWeb.LiveComponents.PobReceive do
   end
 
   @impl true
-  def handle_event("submit", %{"pob" => %{"pob" => pobdata}}, socket) do
+  def handle_event("submit", %{"pob" => %{"pob" => pob_or_link}}, socket) do
+    {:ok, pobdata} = Native.fetch_pob(pob_or_link)
     {:ok, itemsets, skillsets} = Native.get_itemsets_skillsets(pobdata)
     send(self(), {:new_pob, {pobdata, itemsets, skillsets}})
     {:noreply, socket}
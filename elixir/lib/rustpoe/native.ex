@@ -26,6 +26,9 @@ defmodule RustPoe.Native do
   @spec closest_item(NativeItem.t(), [Item.t()]) :: {:ok, Item.t() | nil} | nif_err()
   def closest_item(_item, _items), do: error()
 
+  @spec trade_search_url(NativeItem.t(), String.t()) :: {:ok, String.t()} | nif_err()
+  def trade_search_url(_item, _league), do: error()
+
   @spec get_items_from_stash_data(String.t()) :: {:ok, [Item.t()]} | nif_err()
   def get_items_from_stash_data(_data), do: error()
 
@@ -46,5 +49,8 @@ defmodule RustPoe.Native do
 
   def get_itemsets_skillsets(_pobdata), do: error()
 
+  @spec fetch_pob(String.t()) :: {:ok, String.t()} | nif_err()
+  def fetch_pob(_pob_or_link), do: error()
+
   defp error, do: :erlang.nif_error(:nif_not_loaded)
 end
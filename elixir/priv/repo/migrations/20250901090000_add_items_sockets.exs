@@ -0,0 +1,9 @@
+defmodule PoeSystem.Repo.Migrations.AddItemsSockets do
+  use Ecto.Migration
+
+  def change do
+    alter table(:items) do
+      add :sockets, :string
+    end
+  end
+end
@@ -0,0 +1,9 @@
+defmodule PoeSystem.Repo.Migrations.AddItemsGameVersion do
+  use Ecto.Migration
+
+  def change do
+    alter table(:items) do
+      add :game_version, :string, null: false, default: "poe1"
+    end
+  end
+end
@@ -0,0 +1,34 @@
+use std::env::args;
+
+use utils::stream_stashes::{open_stashes, validate_chain};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = args().collect::<Vec<String>>();
+    if args.len() < 2 {
+        eprintln!("Usage: {} <archive or dir>", args[0]);
+        return Ok(());
+    }
+
+    let report = validate_chain(open_stashes(&args[1])?)?;
+    println!("entries: {}", report.entries);
+    if !report.verifiable {
+        println!("entries don't store change id they were fetched with, gaps can't be detected");
+    }
+    for gap in &report.gaps {
+        match &gap.found {
+            Some(found) => println!("missing from {} to {}", gap.expected, found),
+            None => println!("missing from {}, chain ends", gap.expected),
+        }
+    }
+    for id in &report.forks {
+        println!("fork at {}", id);
+    }
+    for id in &report.invalid {
+        println!("no next_change_id in {}", id);
+    }
+    if !report.is_valid() {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
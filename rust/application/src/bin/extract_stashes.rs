@@ -9,7 +9,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
 
-    let stashes = open_stashes(&args[1]);
+    let stashes = open_stashes(&args[1])?;
 
     let dir = PathBuf::from(&args[2]);
     if !dir.exists() {
@@ -18,7 +18,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let max_count = (&args[3]).parse().expect("cannot parse count");
     let mut count = 0;
-    for (filename, content) in stashes {
+    for entry in stashes {
+        let (filename, content) = entry?;
         count += 1;
         std::fs::write(dir.join(filename), content)?;
         if count >= max_count {
@@ -1,10 +1,12 @@
-use std::{collections::HashMap, time::Duration};
+use std::{collections::HashMap, path::PathBuf, time::Duration};
 
 use application::pipe_stashes::parse_mods;
 use async_channel::bounded;
 use clap::{Parser, Subcommand};
+use domain::{build_calculation::stored_item::StoredItem, item::Item as DomainItem};
 use public_stash::models::PublicStashData;
 use redis::AsyncCommands;
+use storage::ItemIndexBuilder;
 use tokio::io::{self, AsyncBufReadExt, BufReader, Lines, Stdin};
 
 #[derive(Parser)]
@@ -19,6 +21,8 @@ struct Cli {
 #[derive(Subcommand)]
 enum Command {
     Redis,
+    // builds embedded index in given directory
+    Index { dir: PathBuf },
 }
 
 type SharedResult<T> = Result<T, Box<dyn std::error::Error>>;
@@ -81,19 +85,36 @@ async fn insert_into_redis(
                 let rx = rx_cloned;
                 let mut conn = rx.recv().await.unwrap();
                 let stash_info: PublicStashData = serde_json::from_str(&line).unwrap();
-                let mut affixes: HashMap<String, Vec<&str>> = HashMap::new();
+                // sorted set per stat with mod value as score, like the file index
+                // mods without parsed value go to a plain set so they only match by existence
+                let mut affixes: HashMap<String, Vec<(i32, &str)>> = HashMap::new();
+                let mut unvalued: HashMap<String, Vec<&str>> = HashMap::new();
                 for stash in &stash_info.stashes {
                     for item in &stash.items {
                         let mods = parse_mods(item);
+                        let id = item.id.as_ref().unwrap();
                         for m in mods {
-                            let lst = affixes.entry(format!("affix:{}", m.stat_id)).or_default();
-                            lst.push(item.id.as_ref().unwrap());
+                            match m.numeric_value.first_int() {
+                                Some(v) => affixes
+                                    .entry(format!("affix:{}", m.stat_id))
+                                    .or_default()
+                                    .push((v, id)),
+                                None => unvalued
+                                    .entry(format!("unvalued_affix:{}", m.stat_id))
+                                    .or_default()
+                                    .push(id),
+                            }
                         }
                     }
                 }
-                for (k, lst) in affixes {
-                    let _: usize = conn.sadd(k, lst).await.unwrap();
+                let mut pipe = redis::pipe();
+                for (k, lst) in &affixes {
+                    pipe.zadd_multiple(k, lst).ignore();
                 }
+                for (k, lst) in &unvalued {
+                    pipe.sadd(k, lst).ignore();
+                }
+                let _: () = pipe.query_async(&mut conn).await.unwrap();
                 tx.send(conn).await.unwrap();
             })
             .await?;
@@ -107,6 +128,27 @@ async fn insert_into_redis(
     Ok(())
 }
 
+async fn insert_into_index(dir: PathBuf, mut lines: Lines<BufReader<Stdin>>) -> SharedResult<()> {
+    let mut builder = ItemIndexBuilder::new();
+    let mut skipped = 0;
+    while let Ok(Some(line)) = lines.next_line().await {
+        let stash_info: PublicStashData = serde_json::from_str(&line)?;
+        for stash in stash_info.stashes {
+            for item in stash.items {
+                match DomainItem::try_from(item).map(StoredItem::try_from) {
+                    Ok(Ok(item)) => builder.add(item),
+                    _ => skipped += 1,
+                }
+            }
+        }
+    }
+
+    let index = builder.build();
+    index.save(&dir)?;
+    println!("indexed {} items, skipped {}", index.len(), skipped);
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
@@ -116,6 +158,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     match cli.command {
         Command::Redis => insert_into_redis(cli.task_num, lines).await?,
+        Command::Index { dir } => insert_into_index(dir, lines).await?,
     };
 
     Ok(())
@@ -10,7 +10,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
 
-    let stashes = open_stashes(&args[1]);
+    let stashes = open_stashes(&args[1])?;
 
     let mut count = 0usize;
     let mut size = 0usize;
@@ -18,7 +18,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut zero_stashes = 0usize;
     let mut items_count = 0usize;
     let max = stashes
-        .filter_map(|s| serde_json::from_str::<PublicStashData>(&s.1).ok())
+        .filter_map(|s| match s {
+            Ok(s) => Some(s),
+            Err(e) => {
+                eprintln!("error reading stashes: {}", e);
+                None
+            }
+        })
+        .filter_map(|(name, data)| {
+            serde_json::from_str::<PublicStashData>(&data)
+                .inspect_err(|e| eprintln!("skipping {}: {}", name, e))
+                .ok()
+        })
         .map(|s| s.stashes)
         .flat_map(|s| {
             stashes_in_file = stashes_in_file.max(s.len());
@@ -0,0 +1,63 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use application::price_history::PriceHistory;
+use clap::Parser;
+use public_stash::{models::PublicStashData, tracker::StashTracker};
+use utils::stream_stashes::open_stashes;
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    // stashes archive or directory
+    src: PathBuf,
+    #[arg(long, default_value = "10")]
+    min_items: usize,
+    #[arg(long, default_value = "30")]
+    top: usize,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+
+    let mut tracker = StashTracker::new();
+    let mut history = PriceHistory::new();
+    // archives don't store fetch time, time is counted in processed changes,
+    // change n is at UNIX_EPOCH + n seconds
+    let mut changes = 0u64;
+    for entry in open_stashes(&cli.src)? {
+        let (filename, data) = entry?;
+        let data: PublicStashData = match serde_json::from_str(&data) {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("skipping {}: {}", filename, e);
+                continue;
+            }
+        };
+        let at = SystemTime::UNIX_EPOCH + Duration::from_secs(changes);
+        for event in tracker.apply_data(&data, at) {
+            history.apply(&event);
+        }
+        changes += 1;
+    }
+    println!("processed {} changes, {} items", changes, history.len());
+
+    for stats in history.aggregate(cli.min_items).into_iter().take(cli.top) {
+        println!(
+            "{} ({}): items {}, median price {}, price drops {}, sold {:.1}%, median changes to sell {}",
+            stats.key,
+            stats.currency,
+            stats.items,
+            stats.median_price,
+            stats.price_drops,
+            stats.sell_through() * 100.0,
+            stats
+                .median_time_to_sell
+                .map_or("-".to_string(), |d| d.as_secs().to_string()),
+        );
+    }
+
+    Ok(())
+}
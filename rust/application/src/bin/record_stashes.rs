@@ -0,0 +1,82 @@
+use std::{env, path::PathBuf, time::Duration};
+
+use clap::{Parser, ValueEnum};
+use public_stash::{
+    client::Client,
+    recorder::{record, RecordFormat, RecordOptions, Recorder, RecorderConfig},
+};
+use utils::{FileLimitStore, LimitMiddleware, DEFAULT_USER_AGENT};
+
+const LIMITS_STATE_FILE: &str = ".limits.json";
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Dir,
+    TarZstd,
+}
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    // directory for recorded segments and checkpoint
+    dir: PathBuf,
+    #[arg(long, value_enum, default_value = "tar-zstd")]
+    format: Format,
+    // start from this change id instead of checkpoint
+    #[arg(long)]
+    from: Option<String>,
+    #[arg(long)]
+    max_changes: Option<usize>,
+    #[arg(long, default_value = "512")]
+    max_segment_mb: u64,
+    #[arg(long, default_value = "60")]
+    max_segment_minutes: u64,
+    #[arg(long, default_value = "5")]
+    poll_seconds: u64,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt::init();
+    let cli = Cli::parse();
+
+    let limits = LimitMiddleware::shared();
+    let limits_store = FileLimitStore::new(LIMITS_STATE_FILE);
+    limits.load(&limits_store)?;
+
+    let mut client = Client::new(DEFAULT_USER_AGENT);
+    match env::var("POE_TOKEN") {
+        Ok(token) if !token.is_empty() => client.set_token(&token),
+        _ => eprintln!("POE_TOKEN env variable is empty, requests will be unauthorized"),
+    }
+
+    let mut recorder = Recorder::new(RecorderConfig {
+        dir: cli.dir,
+        format: match cli.format {
+            Format::Dir => RecordFormat::Dir,
+            Format::TarZstd => RecordFormat::TarZstd,
+        },
+        max_segment_bytes: cli.max_segment_mb * 1024 * 1024,
+        max_segment_age: Duration::from_secs(cli.max_segment_minutes * 60),
+    })?;
+    let options = RecordOptions {
+        from: cli.from,
+        max_changes: cli.max_changes,
+        poll_interval: Duration::from_secs(cli.poll_seconds),
+    };
+
+    let recorded = tokio::select! {
+        r = record(&mut client, &mut recorder, options) => r,
+        _ = tokio::signal::ctrl_c() => {
+            eprintln!("interrupted, finishing current segment");
+            Ok(0)
+        }
+    };
+    // limits are saved on errors too, next run must respect penalties
+    let finished = recorder.finish();
+    limits.save(&limits_store)?;
+    finished?;
+    eprintln!("recorded: {}", recorded?);
+
+    Ok(())
+}
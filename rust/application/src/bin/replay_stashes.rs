@@ -0,0 +1,55 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+use application::replay::{serve, ReplayConfig, ReplayData, DEFAULT_POLICY};
+use clap::Parser;
+use utils::{Limit, LimitHeaders};
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    // stash directories, archives or jsonl dumps, chained in given order.
+    // everything is loaded into memory before serving
+    #[arg(required = true)]
+    src: Vec<PathBuf>,
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    addr: SocketAddr,
+    // comma separated hits:period:penalty rules
+    #[arg(long, default_value = "1:1:60")]
+    rules: String,
+    // ip, account or client
+    #[arg(long, default_value = "client")]
+    kind: String,
+    #[arg(long, default_value = DEFAULT_POLICY)]
+    policy: String,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt::init();
+    let cli = Cli::parse();
+
+    let config = ReplayConfig {
+        policy: cli.policy,
+        kind: LimitHeaders::try_from(cli.kind.as_str())?,
+        rules: Limit::parse_list(&cli.rules, ",")?,
+    };
+    let data = ReplayData::load(&cli.src)?;
+    eprintln!(
+        "loaded {} entries, first {}, tip {}",
+        data.len(),
+        data.first(),
+        data.tip()
+    );
+
+    let listener = tokio::net::TcpListener::bind(cli.addr).await?;
+    eprintln!(
+        "serving on http://{}/public-stash-tabs",
+        listener.local_addr()?
+    );
+    tokio::select! {
+        r = serve(listener, data, config) => r?,
+        _ = tokio::signal::ctrl_c() => {}
+    };
+
+    Ok(())
+}
@@ -1,12 +1,9 @@
 use application::pipe_stashes::parse_mods;
 use clap::{Parser, Subcommand};
-use domain::item::types::Mod;
 use public_stash::models::PublicStashData;
 use redis::AsyncCommands;
-use std::{
-    collections::{HashMap, HashSet},
-    path::PathBuf,
-};
+use std::{collections::HashMap, path::PathBuf};
+use storage::{ItemIndex, ItemQuery, StatRange};
 use tokio::time::Instant;
 use utils::stream_stashes::open_stashes;
 
@@ -14,6 +11,9 @@ use utils::stream_stashes::open_stashes;
 #[command(version, about)]
 struct Cli {
     src: PathBuf,
+    // found items should have mod values at least this fraction of original values
+    #[arg(long, default_value = "0.8")]
+    min_ratio: f32,
     #[command(subcommand)]
     command: Command,
 }
@@ -21,6 +21,7 @@ struct Cli {
 #[derive(Subcommand)]
 enum Command {
     Redis,
+    Index { dir: PathBuf },
 }
 
 struct FoundItem {
@@ -31,7 +32,15 @@ struct FoundItem {
 
 type SearchResult = Result<Vec<FoundItem>, Box<dyn std::error::Error>>;
 
-async fn search_in_redis(mods: &Vec<Mod>) -> SearchResult {
+// redis score bounds of filter
+fn score_bounds(filter: &StatRange) -> (String, String) {
+    (
+        filter.min.map_or("-inf".to_string(), |v| v.to_string()),
+        filter.max.map_or("+inf".to_string(), |v| v.to_string()),
+    )
+}
+
+async fn search_in_redis(filters: &[StatRange]) -> SearchResult {
     let cwd = std::env::current_dir()?;
     let client = redis::Client::open(format!(
         "redis+unix:{}",
@@ -42,22 +51,48 @@ async fn search_in_redis(mods: &Vec<Mod>) -> SearchResult {
             .unwrap()
     ))?;
     let mut conn = client.get_multiplexed_async_connection().await?;
-    let mut ids: HashSet<String> = HashSet::new();
-    let mut first_loaded = false;
-    for m in mods {
-        let k = format!("affix:{}", m.stat_id);
-        let new_ids: HashSet<String> = conn.smembers(&k).await?;
-
-        if !first_loaded {
-            first_loaded = true;
-            ids = new_ids.clone();
-        } else {
-            ids = ids.intersection(&new_ids).cloned().collect();
+
+    // start from the most selective stat, others only check its candidates.
+    // unbounded filters also match mods without parsed value, like the index
+    let mut plan = vec![];
+    for f in filters {
+        let k = format!("affix:{}", f.stat_id);
+        let unvalued = format!("unvalued_affix:{}", f.stat_id);
+        let (min, max) = score_bounds(f);
+        let mut count: usize = conn.zcount(&k, &min, &max).await?;
+        if !f.is_bounded() {
+            count += conn.scard::<_, usize>(&unvalued).await?;
         }
+        plan.push((count, k, unvalued, f));
+    }
+    plan.sort_by_key(|(count, _, _, _)| *count);
 
-        if ids.len() < 10 {
+    let Some(((_, k, unvalued, f), rest)) = plan.split_first() else {
+        return Ok(vec![]);
+    };
+    let (min, max) = score_bounds(f);
+    let mut ids: Vec<String> = conn.zrangebyscore(k, &min, &max).await?;
+    if !f.is_bounded() {
+        ids.extend(conn.smembers::<_, Vec<String>>(unvalued).await?);
+    }
+    for (_, k, unvalued, f) in rest {
+        if ids.is_empty() {
             break;
         }
+        let scores: Vec<Option<f64>> = conn.zscore_multiple(k, &ids).await?;
+        let in_unvalued: Vec<bool> = if f.is_bounded() {
+            vec![false; ids.len()]
+        } else {
+            conn.smismember(unvalued, &ids).await?
+        };
+        ids = ids
+            .into_iter()
+            .zip(scores.into_iter().zip(in_unvalued))
+            .filter(|(_, (score, unvalued))| {
+                *unvalued || score.is_some_and(|s| f.contains(s as i32))
+            })
+            .map(|(id, _)| id)
+            .collect();
     }
 
     let results = ids
@@ -72,14 +107,35 @@ async fn search_in_redis(mods: &Vec<Mod>) -> SearchResult {
     Ok(results)
 }
 
+fn search_in_index(dir: &PathBuf, filters: &[StatRange]) -> SearchResult {
+    let index = ItemIndex::open(dir)?;
+    let query = ItemQuery {
+        stats: filters.to_vec(),
+        ..Default::default()
+    };
+
+    let results = index
+        .search(&query)
+        .into_iter()
+        .map(|it| FoundItem {
+            id: it.id.clone(),
+            basetype: it.basetype.clone(),
+            affixes: HashMap::new(),
+        })
+        .collect();
+
+    Ok(results)
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
-    let stashes = open_stashes(&cli.src);
+    let stashes = open_stashes(&cli.src)?;
     let mut max_item = None;
     let mut max_mods = vec![];
-    for (_, data) in stashes {
+    for entry in stashes {
+        let (_, data) = entry?;
         let stash_info: PublicStashData = serde_json::from_str(&data)?;
         for stash in stash_info.stashes {
             for item in stash.items {
@@ -96,11 +152,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         "trying to find similar item to {1} {0}",
         item.base_type, item.name
     );
-    let mods = max_mods;
+    let filters: Vec<_> = max_mods
+        .iter()
+        .map(|m| match m.numeric_value.first_int() {
+            Some(v) if v > 0 => StatRange::new(
+                m.stat_id.clone(),
+                Some((v as f32 * cli.min_ratio) as i32),
+                None,
+            ),
+            _ => StatRange::any(m.stat_id.clone()),
+        })
+        .collect();
 
     let start = Instant::now();
     let result = match cli.command {
-        Command::Redis => search_in_redis(&mods).await?,
+        Command::Redis => search_in_redis(&filters).await?,
+        Command::Index { dir } => search_in_index(&dir, &filters)?,
     };
     let end = Instant::now();
 
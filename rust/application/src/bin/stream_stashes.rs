@@ -1,17 +1,21 @@
-use utils::stream_stashes::open_stashes;
+use utils::stream_stashes::{open_stashes, open_stashes_from};
 use std::env::args;
 use std::io::{self, Write};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = args().into_iter().collect::<Vec<String>>();
     if args.len() < 2 {
-        eprintln!("Usage: {} <archive or dir>", args[0]);
+        eprintln!("Usage: {} <archive or dir> [change_id]", args[0]);
         return Ok(());
     }
 
-    let stashes = open_stashes(&args[1]);
+    let stashes = match args.get(2) {
+        Some(change_id) => open_stashes_from(&args[1], change_id)?,
+        None => open_stashes(&args[1])?,
+    };
     let mut count = 0;
-    for (_, content) in stashes {
+    for entry in stashes {
+        let (_, content) = entry?;
         count += 1;
         io::stdout()
             .write_all(content.as_bytes())
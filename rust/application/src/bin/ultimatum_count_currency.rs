@@ -1,11 +1,15 @@
 use application::ultimatum::*;
 use clap::Parser;
+use futures::{pin_mut, StreamExt};
+use std::collections::HashMap;
 use std::env;
-use std::{collections::HashMap, time::Duration};
 use tradeapi::poe1::{
     query::{Builder, StatusOption, TradeFilters},
-    Client as TradeClient, ClientError as TradeClientError,
+    Client as TradeClient, SEARCH_MAX_RESULTS,
 };
+use utils::{FileLimitStore, LimitMiddleware};
+
+const LIMITS_STATE_FILE: &str = ".limits.json";
 
 #[derive(Parser, Debug)]
 struct Args {
@@ -24,9 +28,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         unreachable!();
     }
 
+    let limits = LimitMiddleware::shared();
+    let limits_store = FileLimitStore::new(LIMITS_STATE_FILE);
+    limits.load(&limits_store)?;
+
+    // limits are saved on errors too, next run must respect penalties
+    let result = run(&cli, &poesessid).await;
+    limits.save(&limits_store)?;
+    result
+}
+
+async fn run(cli: &Args, poesessid: &str) -> Result<(), Box<dyn std::error::Error>> {
     let mut trade_client = TradeClient::new(
         "Mozilla/5.0 (X11; Linux x86_64; rv:127.0) Gecko/20100101 Firefox/127.0",
-        &poesessid,
+        poesessid,
         "Settlers",
     );
     let mut builder = Builder::new();
@@ -39,36 +54,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     );
 
     println!("fetching ultimatums from trade search");
-    let search_res = trade_client.get_search_id(&builder).await?;
-    println!(
-        "search res size: {} total: {}",
-        search_res.result.len(),
-        search_res.total
-    );
-    let it = EveryNElements::new(10, search_res.result.iter());
+    let stream = trade_client.search_stream(&builder, SEARCH_MAX_RESULTS);
+    pin_mut!(stream);
     let mut ultimatums = Vec::with_capacity(200);
-    for els in it {
-        println!("fetching next elements from trade");
-        loop {
-            let fetch_res = trade_client
-                .fetch_results(els.iter().map(|e| (*e).clone()).collect(), &search_res.id)
-                .await;
-            if matches!(fetch_res, Err(TradeClientError::TooManyRequestsOrSimilar)) {
-                println!("too many requests");
-                tokio::time::sleep(Duration::from_millis(500)).await;
-                continue;
-            }
-            let fetch_res = fetch_res?;
-            let m = fetch_res
-                .result
-                .into_iter()
-                .map(|e| map_ultimatum(e.item))
-                .filter(|e| e.currency);
-            ultimatums.extend(m);
-            break;
+    while let Some(entry) = stream.next().await {
+        let ulti = map_ultimatum(entry?.item);
+        if ulti.currency {
+            ultimatums.push(ulti);
         }
-        println!("ultimatums with currency: {}", ultimatums.len());
     }
+    println!("ultimatums with currency: {}", ultimatums.len());
 
     let collected_currency_sacrifice = ultimatums.into_iter().fold(HashMap::new(), |mut acc, x| {
         let v = acc.entry(x.sacrifice.clone()).or_insert(0);
@@ -1,2 +1,4 @@
 pub mod pipe_stashes;
+pub mod price_history;
+pub mod replay;
 pub mod ultimatum;
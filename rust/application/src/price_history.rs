@@ -0,0 +1,317 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    time::{Duration, SystemTime},
+};
+
+use domain::build_calculation::stored_item::Price;
+use public_stash::tracker::{StashEvent, TrackedItem};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum GroupKey {
+    Basetype(String),
+    Unique(String),
+    // basetype with sorted explicit stat ids
+    StatProfile(String, Vec<String>),
+}
+
+impl Display for GroupKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GroupKey::Basetype(b) => write!(f, "basetype {}", b),
+            GroupKey::Unique(n) => write!(f, "unique {}", n),
+            GroupKey::StatProfile(b, ids) => write!(f, "{} [{}]", b, ids.join(", ")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PricePoint {
+    pub at: SystemTime,
+    pub price: Option<Price>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemHistory {
+    pub item: TrackedItem,
+    pub prices: Vec<PricePoint>,
+    pub removed_at: Option<SystemTime>,
+    // item disappeared together with whole stash, probably not sold
+    pub emptied: bool,
+}
+
+impl ItemHistory {
+    pub fn last_price(&self) -> Option<&Price> {
+        self.prices.last()?.price.as_ref()
+    }
+
+    pub fn price_drops(&self) -> usize {
+        self.prices
+            .windows(2)
+            .filter(|w| match (&w[0].price, &w[1].price) {
+                (Some(old), Some(new)) => {
+                    let (old_currency, old) = old.parts();
+                    let (new_currency, new) = new.parts();
+                    old_currency == new_currency && new < old
+                }
+                _ => false,
+            })
+            .count()
+    }
+
+    // time from listing to removal if item was sold (removed from non-empty stash)
+    pub fn time_to_sell(&self) -> Option<Duration> {
+        if self.emptied {
+            return None;
+        }
+        self.removed_at?.duration_since(self.item.listed_at).ok()
+    }
+
+    fn groups(&self) -> Vec<GroupKey> {
+        let item = &self.item;
+        let mut keys = vec![GroupKey::Basetype(item.basetype.clone())];
+        if item.unique {
+            keys.push(GroupKey::Unique(item.name.clone()));
+        } else if !item.stat_ids.is_empty() {
+            keys.push(GroupKey::StatProfile(
+                item.basetype.clone(),
+                item.stat_ids.clone(),
+            ));
+        }
+        keys
+    }
+}
+
+fn median<T: Ord + Copy>(mut values: Vec<T>) -> Option<T> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort();
+    Some(values[values.len() / 2])
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupStats {
+    pub key: GroupKey,
+    pub currency: String,
+    pub items: usize,
+    pub sold: usize,
+    pub median_price: i32,
+    pub price_drops: usize,
+    pub median_time_to_sell: Option<Duration>,
+}
+
+impl GroupStats {
+    pub fn sell_through(&self) -> f64 {
+        self.sold as f64 / self.items as f64
+    }
+}
+
+// listing price and removal time of every item seen in stash events
+#[derive(Debug, Default)]
+pub struct PriceHistory {
+    items: HashMap<String, ItemHistory>,
+}
+
+impl PriceHistory {
+    pub fn new() -> PriceHistory {
+        PriceHistory::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn item(&self, id: &str) -> Option<&ItemHistory> {
+        self.items.get(id)
+    }
+
+    fn removed(&mut self, item: &TrackedItem, at: SystemTime, emptied: bool) {
+        if let Some(h) = self.items.get_mut(&item.id) {
+            h.removed_at = Some(at);
+            h.emptied = emptied;
+        }
+    }
+
+    pub fn apply(&mut self, event: &StashEvent) {
+        match event {
+            StashEvent::ItemAdded { item, .. } => {
+                let point = PricePoint {
+                    at: item.listed_at,
+                    price: item.price.clone(),
+                };
+                match self.items.get_mut(&item.id) {
+                    // listed again after it was removed in earlier change, keep original listing
+                    Some(h) => {
+                        h.removed_at = None;
+                        h.emptied = false;
+                        if h.prices.last().is_none_or(|p| p.price != point.price) {
+                            h.prices.push(point);
+                        }
+                    }
+                    None => {
+                        self.items.insert(
+                            item.id.clone(),
+                            ItemHistory {
+                                item: item.clone(),
+                                prices: vec![point],
+                                removed_at: None,
+                                emptied: false,
+                            },
+                        );
+                    }
+                }
+            }
+            // listing continues in another stash, price change is reported separately
+            StashEvent::ItemMoved { .. } => {}
+            StashEvent::PriceChanged {
+                item_id, new, at, ..
+            } => {
+                if let Some(h) = self.items.get_mut(item_id) {
+                    h.prices.push(PricePoint {
+                        at: *at,
+                        price: new.clone(),
+                    });
+                }
+            }
+            StashEvent::ItemRemoved { item, at, .. } => self.removed(item, *at, false),
+            StashEvent::StashEmptied { items, at, .. } => {
+                for item in items {
+                    self.removed(item, *at, true);
+                }
+            }
+        }
+    }
+
+    // stats for groups with at least `min_items` priced items, most listed first
+    pub fn aggregate(&self, min_items: usize) -> Vec<GroupStats> {
+        let mut groups: HashMap<(GroupKey, &str), Vec<&ItemHistory>> = HashMap::new();
+        for h in self.items.values() {
+            let Some(price) = h.last_price() else {
+                continue;
+            };
+            for key in h.groups() {
+                groups
+                    .entry((key, price.parts().0))
+                    .or_default()
+                    .push(h);
+            }
+        }
+
+        let mut stats: Vec<_> = groups
+            .into_iter()
+            .filter(|(_, items)| items.len() >= min_items.max(1))
+            .map(|((key, currency), items)| {
+                let sold: Vec<_> = items.iter().filter_map(|h| h.time_to_sell()).collect();
+                GroupStats {
+                    key,
+                    currency: currency.to_string(),
+                    items: items.len(),
+                    sold: sold.len(),
+                    median_price: median(
+                        items
+                            .iter()
+                            .filter_map(|h| h.last_price())
+                            .map(|p| p.parts().1)
+                            .collect(),
+                    )
+                    .unwrap_or_default(),
+                    price_drops: items.iter().map(|h| h.price_drops()).sum(),
+                    median_time_to_sell: median(sold),
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| {
+            b.items
+                .cmp(&a.items)
+                .then_with(|| a.key.cmp(&b.key))
+                .then_with(|| a.currency.cmp(&b.currency))
+        });
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use domain::build_calculation::stored_item::Price;
+    use public_stash::tracker::{StashEvent, TrackedItem};
+
+    use super::{GroupKey, PriceHistory};
+
+    fn item(id: &str, price: i32, at: SystemTime) -> TrackedItem {
+        TrackedItem {
+            id: id.to_string(),
+            basetype: "Coral Ring".to_string(),
+            name: String::new(),
+            unique: false,
+            stat_ids: vec!["base_maximum_life".to_string()],
+            price: Some(Price::Chaos(price)),
+            listed_at: at,
+        }
+    }
+
+    fn added(item: TrackedItem) -> StashEvent {
+        StashEvent::ItemAdded {
+            stash_id: "stash".to_string(),
+            item,
+        }
+    }
+
+    #[test]
+    fn sell_through() {
+        let t = |s| SystemTime::UNIX_EPOCH + Duration::from_secs(s);
+        let mut history = PriceHistory::new();
+        let events = [
+            added(item("a", 10, t(0))),
+            added(item("b", 20, t(0))),
+            added(item("c", 30, t(10))),
+            StashEvent::PriceChanged {
+                stash_id: "stash".to_string(),
+                item_id: "b".to_string(),
+                old: Some(Price::Chaos(20)),
+                new: Some(Price::Chaos(15)),
+                at: t(30),
+            },
+            StashEvent::ItemRemoved {
+                stash_id: "stash".to_string(),
+                item: item("a", 10, t(0)),
+                at: t(60),
+            },
+            StashEvent::ItemRemoved {
+                stash_id: "stash".to_string(),
+                item: item("b", 20, t(0)),
+                at: t(120),
+            },
+            StashEvent::StashEmptied {
+                stash_id: "stash".to_string(),
+                items: vec![item("c", 30, t(10))],
+                at: t(200),
+            },
+        ];
+        events.iter().for_each(|e| history.apply(e));
+
+        assert_eq!(history.len(), 3);
+        let b = history.item("b").unwrap();
+        assert_eq!(b.price_drops(), 1);
+        assert_eq!(b.last_price(), Some(&Price::Chaos(15)));
+        assert_eq!(history.item("c").unwrap().time_to_sell(), None);
+
+        let stats = history.aggregate(2);
+        assert_eq!(stats.len(), 2);
+        let basetype = &stats[0];
+        assert_eq!(basetype.key, GroupKey::Basetype("Coral Ring".to_string()));
+        assert_eq!(basetype.currency, "chaos");
+        assert_eq!(basetype.items, 3);
+        assert_eq!(basetype.sold, 2);
+        assert_eq!(basetype.median_price, 15);
+        assert_eq!(basetype.price_drops, 1);
+        assert_eq!(basetype.median_time_to_sell, Some(Duration::from_secs(120)));
+        assert!(matches!(stats[1].key, GroupKey::StatProfile(..)));
+    }
+}
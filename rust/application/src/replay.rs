@@ -0,0 +1,372 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::Bytes,
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{AppendHeaders, IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+use thiserror::Error;
+use tracing::debug;
+use utils::{
+    stream_stashes::{entry_change_id, extract_next_change_id, open_stashes, StashesError},
+    Limit, LimitHeaders,
+};
+
+pub const DEFAULT_POLICY: &str = "public-stash-request-limit";
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("stashes error: {0}")]
+    Stashes(#[from] StashesError),
+    #[error("entry without next_change_id: {0}")]
+    NoChangeId(String),
+    #[error("no stash entries to replay")]
+    Empty,
+}
+
+// recorded responses by change id they were fetched with. all responses are kept
+// in memory, so replayed sources must fit into RAM
+#[derive(Debug)]
+pub struct ReplayData {
+    pages: HashMap<String, Bytes>,
+    first: String,
+    // next_change_id of last entry, served as caught up river
+    tip: String,
+}
+
+impl ReplayData {
+    pub fn new<I>(entries: I) -> Result<ReplayData, ReplayError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut pages = HashMap::new();
+        let mut first = None;
+        let mut tip = None;
+        for (name, body) in entries {
+            let id = entry_change_id(name.rsplit('/').next().unwrap_or(&name)).to_string();
+            tip = Some(
+                extract_next_change_id(&body).ok_or_else(|| ReplayError::NoChangeId(id.clone()))?,
+            );
+            first.get_or_insert_with(|| id.clone());
+            pages.insert(id, Bytes::from(body));
+        }
+        Ok(ReplayData {
+            pages,
+            first: first.ok_or(ReplayError::Empty)?,
+            tip: tip.ok_or(ReplayError::Empty)?,
+        })
+    }
+
+    // reads all entries of all sources into memory before serving, sources are chained
+    // in given order
+    pub fn load<P: AsRef<Path>>(paths: &[P]) -> Result<ReplayData, ReplayError> {
+        let mut entries = vec![];
+        for p in paths {
+            for entry in open_stashes(p)? {
+                entries.push(entry?);
+            }
+        }
+        ReplayData::new(entries)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    pub fn first(&self) -> &str {
+        &self.first
+    }
+
+    pub fn tip(&self) -> &str {
+        &self.tip
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayConfig {
+    pub policy: String,
+    pub kind: LimitHeaders,
+    pub rules: Vec<Limit>,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        ReplayConfig {
+            policy: DEFAULT_POLICY.to_string(),
+            kind: LimitHeaders::Client,
+            rules: vec![Limit::new(
+                1,
+                Duration::from_secs(1),
+                Duration::from_secs(60),
+            )],
+        }
+    }
+}
+
+struct RuleState {
+    limit: Limit,
+    hits: VecDeque<Instant>,
+    restricted_until: Option<Instant>,
+}
+
+fn ceil_secs(d: Duration) -> u64 {
+    d.as_millis().div_ceil(1000) as u64
+}
+
+impl RuleState {
+    fn prune(&mut self, now: Instant) {
+        while self
+            .hits
+            .front()
+            .is_some_and(|h| *h + self.limit.watching_time <= now)
+        {
+            self.hits.pop_front();
+        }
+        if self.restricted_until.is_some_and(|r| r <= now) {
+            self.restricted_until = None;
+        }
+    }
+
+    fn state(&self, now: Instant) -> Limit {
+        let penalty = self
+            .restricted_until
+            .map(|r| Duration::from_secs(ceil_secs(r - now)))
+            .unwrap_or_default();
+        Limit::new(self.hits.len() as u32, self.limit.watching_time, penalty)
+    }
+}
+
+// server side of rate limiting: requests over a rule put it under penalty
+struct RateLimiter {
+    config: ReplayConfig,
+    rules: Vec<RuleState>,
+}
+
+impl RateLimiter {
+    fn new(config: ReplayConfig) -> RateLimiter {
+        let rules = config
+            .rules
+            .iter()
+            .map(|&limit| RuleState {
+                limit,
+                hits: VecDeque::new(),
+                restricted_until: None,
+            })
+            .collect();
+        RateLimiter { config, rules }
+    }
+
+    // counts request, returns time to wait if it's rejected
+    fn hit(&mut self, now: Instant) -> Option<Duration> {
+        self.rules.iter_mut().for_each(|r| r.prune(now));
+        if self.rules.iter().all(|r| r.restricted_until.is_none()) {
+            for r in &mut self.rules {
+                r.hits.push_back(now);
+                if r.hits.len() > r.limit.hit_count as usize {
+                    let penalty = if r.limit.penalty_time.is_zero() {
+                        r.limit.watching_time
+                    } else {
+                        r.limit.penalty_time
+                    };
+                    r.restricted_until = Some(now + penalty);
+                }
+            }
+        }
+        self.rules
+            .iter()
+            .filter_map(|r| r.restricted_until)
+            .max()
+            .map(|r| r - now)
+    }
+
+    fn headers(&self, now: Instant) -> Vec<(&'static str, String)> {
+        let join = |limits: Vec<Limit>| {
+            limits
+                .iter()
+                .map(|l| l.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let kind = self.config.kind;
+        vec![
+            ("x-rate-limit-policy", self.config.policy.clone()),
+            ("x-rate-limit-rules", kind.name().to_string()),
+            (
+                kind.rules_header(),
+                join(self.rules.iter().map(|r| r.limit).collect()),
+            ),
+            (
+                kind.state_header(),
+                join(self.rules.iter().map(|r| r.state(now)).collect()),
+            ),
+        ]
+    }
+}
+
+struct ReplayState {
+    data: ReplayData,
+    limiter: Mutex<RateLimiter>,
+}
+
+#[derive(Deserialize)]
+struct StashQuery {
+    id: Option<String>,
+}
+
+fn error_body(code: u32, message: &str) -> String {
+    json!({ "error": { "code": code, "message": message } }).to_string()
+}
+
+async fn public_stash_tabs(
+    State(state): State<Arc<ReplayState>>,
+    Query(query): Query<StashQuery>,
+) -> Response {
+    let now = Instant::now();
+    let (retry, headers) = {
+        let mut limiter = state.limiter.lock().unwrap();
+        let retry = limiter.hit(now);
+        (retry, limiter.headers(now))
+    };
+    let json = [(header::CONTENT_TYPE, "application/json")];
+    if let Some(retry) = retry {
+        debug!("rate limited {:?}, retry after {:?}", query.id, retry);
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            AppendHeaders(headers),
+            [(header::RETRY_AFTER, ceil_secs(retry).to_string())],
+            json,
+            error_body(3, "Rate limit exceeded"),
+        )
+            .into_response();
+    }
+
+    let data = &state.data;
+    let id = query.id.unwrap_or_else(|| data.first.clone());
+    let (status, body) = match data.pages.get(&id) {
+        Some(page) => (StatusCode::OK, page.clone()),
+        // client caught up with recorded river
+        None if id == data.tip => (
+            StatusCode::OK,
+            Bytes::from(json!({ "next_change_id": id, "stashes": [] }).to_string()),
+        ),
+        None => (
+            StatusCode::NOT_FOUND,
+            Bytes::from(error_body(1, "Resource not found")),
+        ),
+    };
+    debug!("{} {}", status, id);
+    (status, AppendHeaders(headers), json, body).into_response()
+}
+
+// serves `/public-stash-tabs?id=` from recorded data
+pub fn router(data: ReplayData, config: ReplayConfig) -> Router {
+    let state = Arc::new(ReplayState {
+        data,
+        limiter: Mutex::new(RateLimiter::new(config)),
+    });
+    Router::new()
+        .route("/public-stash-tabs", get(public_stash_tabs))
+        .with_state(state)
+}
+
+pub async fn serve(
+    listener: tokio::net::TcpListener,
+    data: ReplayData,
+    config: ReplayConfig,
+) -> std::io::Result<()> {
+    axum::serve(listener, router(data, config)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use public_stash::{
+        client::{Client, Error},
+        test_utils::page,
+    };
+    use utils::{reqwest::StatusCode, Limit, LimitHeaders};
+
+    use super::{serve, ReplayConfig, ReplayData, DEFAULT_POLICY};
+
+    // serves index -> 1-1 -> 2-2 -> 3-3 with 2 requests per second
+    async fn start() -> anyhow::Result<String> {
+        let dir = tempfile::tempdir()?;
+        for (id, next) in [("index", "1-1"), ("1-1", "2-2"), ("2-2", "3-3")] {
+            std::fs::write(dir.path().join(format!("{}.json", id)), page(next))?;
+        }
+        let data = ReplayData::load(&[dir.path()])?;
+        assert_eq!(data.len(), 3);
+        assert_eq!(data.tip(), "3-3");
+
+        let config = ReplayConfig {
+            policy: DEFAULT_POLICY.to_string(),
+            kind: LimitHeaders::Client,
+            rules: vec![Limit::new(
+                2,
+                Duration::from_secs(1),
+                Duration::from_secs(1),
+            )],
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(serve(listener, data, config));
+        Ok(url)
+    }
+
+    #[tokio::test]
+    async fn rejects_burst() -> anyhow::Result<()> {
+        let url = start().await?;
+        let client = utils::reqwest::Client::new();
+        let mut responses = vec![];
+        for _ in 0..3 {
+            responses.push(
+                client
+                    .get(format!("{}/public-stash-tabs", url))
+                    .send()
+                    .await?,
+            );
+        }
+        assert_eq!(responses[0].status(), StatusCode::OK);
+        assert_eq!(responses[1].headers()["x-rate-limit-client-state"], "2:1:0");
+        let limited = &responses[2];
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(limited.headers()["retry-after"], "1");
+        assert_eq!(limited.headers()["x-rate-limit-client"], "2:1:1");
+        assert_eq!(limited.headers()["x-rate-limit-client-state"], "3:1:1");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn client_follows_limits() -> anyhow::Result<()> {
+        let url = start().await?;
+        let mut client = Client::new("test");
+        client.set_base_url(&url);
+
+        let mut change_id = None;
+        for next in ["1-1", "2-2", "3-3", "3-3"] {
+            let data = client.get_latest_stash(change_id.as_deref()).await?;
+            assert_eq!(data.next_change_id, next);
+            change_id = Some(data.next_change_id);
+        }
+        assert!(matches!(
+            client.get_latest_stash(Some("unknown")).await,
+            Err(Error::StatusCode(404))
+        ));
+        Ok(())
+    }
+}
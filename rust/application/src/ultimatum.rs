@@ -11,27 +11,18 @@ pub struct Ultimatum {
     pub currency: bool,
 }
 
-#[derive(serde::Deserialize)]
-pub struct Property {
-    pub name: String,
-    pub values: Vec<Vec<serde_json::Value>>,
-}
-
 pub fn map_ultimatum(item: ClientFetchItem) -> Ultimatum {
-    let vv: Vec<Property> = serde_json::from_value(item.rest["properties"].clone()).unwrap();
-    let v: Vec<String> = vv
+    let v: Vec<String> = item
+        .item
+        .properties
+        .unwrap_or_default()
         .into_iter()
-        .map(|e| (e.name, e.values))
-        .filter(|e| e.0.contains("Requires Sacrifice") || e.0.contains("Reward"))
+        .filter(|e| e.name.contains("Requires Sacrifice") || e.name.contains("Reward"))
         .flat_map(|e| {
-            if e.1.len() == 2 {
-                vec![
-                    e.1[0][0].as_str().unwrap().to_string(),
-                    e.1[1][0].as_str().unwrap().to_string(),
-                ]
-            } else {
-                vec![e.1[0][0].as_str().unwrap().to_string()]
-            }
+            e.values
+                .into_iter()
+                .take(2)
+                .filter_map(|v| v.first().map(|v| v.value()))
         })
         .collect();
 
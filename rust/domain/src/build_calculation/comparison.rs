@@ -3,11 +3,50 @@ use std::ops::Deref;
 use tracing::{instrument, Level};
 
 use crate::{
-    build_calculation::item_config::{ItemConfigOption, ModOption},
-    data::MODS,
+    build_calculation::item_config::{ItemConfigOption, ModOption, ModStatId},
+    data::ModValue as DataModValue,
+    item::types::Sockets,
 };
 
-use super::{stored_item::StoredItem, ItemWithConfig};
+use super::{
+    stored_item::{ItemInfo, StoredItem},
+    ItemWithConfig,
+};
+
+type Predicate<'a> = Box<dyn Fn(&StoredItem) -> bool + 'a>;
+
+fn mod_values(it: &StoredItem, k: &ModStatId) -> Option<(DataModValue, Option<DataModValue>)> {
+    let m = it.info.mods().iter().find(|m| &m.stat_id == k.deref())?;
+    // mods without data can't be compared by values
+    let mod_data = it.game_version.get_mod_data(&m.text)?;
+    match mod_data.extract_values(&m.text) {
+        (Some(mv1), mv2) => Some((mv1, mv2)),
+        _ => None,
+    }
+}
+
+fn mod_predicate<'a>(k: &'a ModStatId, v: &'a ModOption) -> Option<Predicate<'a>> {
+    match v {
+        ModOption::Exist => Some(Box::new(|it| {
+            it.info.mods().iter().any(|m| &m.stat_id == k.deref())
+        })),
+        ModOption::Exact(val) => Some(Box::new(|it| match mod_values(it, k) {
+            Some((mv1, None)) => mv1 == *val,
+            Some((mv1, Some(mv2))) => mv1 <= *val && mv2 >= *val,
+            _ => false,
+        })),
+        ModOption::Range(range) => Some(Box::new(|it| match mod_values(it, k) {
+            Some((mv1, None)) => mv1 >= range.start && mv1 <= range.end,
+            Some((mv1, Some(mv2))) => mv1 >= range.start && mv2 <= range.end,
+            _ => false,
+        })),
+        ModOption::Min(val) => Some(Box::new(|it| match mod_values(it, k) {
+            Some((mv1, _)) => mv1 >= *val,
+            _ => false,
+        })),
+        ModOption::Ignore => None,
+    }
+}
 
 pub struct Comparator {}
 
@@ -17,7 +56,7 @@ impl Comparator {
         required_item: &'a ItemWithConfig,
         items: Vec<StoredItem>,
     ) -> Option<StoredItem> {
-        let mut preds: Vec<Box<dyn Fn(&StoredItem) -> bool>> = vec![];
+        let mut preds: Vec<Predicate> = vec![];
 
         if let Some(ic) = &required_item.config.option {
             match ic {
@@ -26,32 +65,96 @@ impl Comparator {
                         required_item.item.name == it.name
                     }));
                 }
-                ItemConfigOption::Mods(mods) => {
-                    mods.iter().for_each(|(k, v)| match v {
-                        ModOption::Exist => preds.push(Box::new(|it| {
-                            it.info.mods().iter().any(|m| &m.stat_id == k.deref())
-                        })),
-                        ModOption::Exact(val) => preds.push(Box::new(|it| {
-                            let m = it.info.mods().iter().find(|m| &m.stat_id == k.deref());
-                            if let Some(mm) = m {
-                                let mod_data =
-                                    MODS::get_mod_data(&mm.text).expect("mod should be found");
-                                let v = mod_data.extract_values(&mm.text);
-                                match v {
-                                    (Some(mv1), None) => mv1 == *val,
-                                    (Some(mv1), Some(mv2)) => mv1 <= *val && mv2 >= *val,
-                                    _ => false,
-                                }
-                            } else {
-                                false
+                ItemConfigOption::UniqueVariant {
+                    seed,
+                    conqueror,
+                    radius,
+                    mods,
+                } => {
+                    preds.push(Box::new(|it: &StoredItem| {
+                        required_item.item.name == it.name
+                    }));
+                    preds.push(Box::new(move |it| {
+                        mods.iter()
+                            .all(|k| it.info.mods().iter().any(|m| &m.stat_id == k.deref()))
+                    }));
+                    if seed.is_some() || conqueror.is_some() || radius.is_some() {
+                        preds.push(Box::new(move |it| match &it.info {
+                            ItemInfo::UniqueJewel {
+                                seed: it_seed,
+                                conqueror: it_conqueror,
+                                radius: it_radius,
+                                ..
+                            } => {
+                                (seed.is_none() || seed == it_seed)
+                                    && (conqueror.is_none() || conqueror == it_conqueror)
+                                    && (radius.is_none() || radius == it_radius)
                             }
-                        })),
-                        _ => {}
-                    });
+                            _ => false,
+                        }));
+                    }
+                }
+                ItemConfigOption::UniqueRolls { kind, legacy, mods } => {
+                    preds.push(Box::new(|it: &StoredItem| {
+                        required_item.item.name == it.name
+                    }));
+                    if let Some(kind) = kind {
+                        preds.push(Box::new(move |it| it.unique_kind().as_ref() == Some(kind)));
+                    }
+                    if *legacy {
+                        preds.push(Box::new(|it| {
+                            required_item.item.info.mods().iter().all(|req| {
+                                it.info.mods().iter().any(|m| m.stat_id == req.stat_id)
+                            })
+                        }));
+                    }
+                    preds.extend(mods.iter().filter_map(|(k, v)| mod_predicate(k, v)));
+                }
+                ItemConfigOption::Mods(mods) => {
+                    preds.extend(mods.iter().filter_map(|(k, v)| mod_predicate(k, v)));
                 }
             }
         }
 
+        if let Some(req) = &required_item.config.sockets {
+            preds.push(Box::new(move |it| {
+                it.sockets
+                    .as_deref()
+                    .and_then(|s| Sockets::try_from(s).ok())
+                    .is_some_and(|s| s.satisfies(req))
+            }));
+        }
+
+        if let ItemInfo::ClusterJewel {
+            size,
+            passives,
+            enchant,
+            notables,
+            ..
+        } = &required_item.item.info
+        {
+            if !matches!(
+                required_item.config.option,
+                Some(ItemConfigOption::Unique | ItemConfigOption::UniqueVariant { .. })
+            ) {
+                preds.push(Box::new(move |it| match &it.info {
+                    ItemInfo::ClusterJewel {
+                        size: it_size,
+                        passives: it_passives,
+                        enchant: it_enchant,
+                        notables: it_notables,
+                        ..
+                    } => {
+                        size == it_size
+                            && passives == it_passives
+                            && enchant == it_enchant
+                            && notables.iter().all(|n| it_notables.contains(n))
+                    }
+                    _ => false,
+                }));
+            }
+        }
+
         let candidates: Vec<_> = items
             .iter()
             .filter(|it| preds.iter().all(|pr| pr(it)))
@@ -64,3 +167,197 @@ impl Comparator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Comparator;
+    use crate::build_calculation::{
+        item_config::{
+            ItemConfig, ItemConfigOption, ModOption, ModStatId, RangeInclusiveI32Elixir, UniqueKind,
+        },
+        stored_item::{ClusterJewelSize, ItemInfo, StoredItem},
+        ItemWithConfig,
+    };
+    use crate::item::types::{Mod as DomainMod, ModType};
+
+    fn cluster(passives: u8, enchant: &str, notables: &[&str]) -> StoredItem {
+        StoredItem {
+            basetype: "Large Cluster Jewel".to_string(),
+            info: ItemInfo::ClusterJewel {
+                size: ClusterJewelSize::Large,
+                passives,
+                enchant: enchant.to_string(),
+                notables: notables.iter().map(|n| n.to_string()).collect(),
+                mods: vec![],
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn closest_cluster_jewel() {
+        let required = ItemWithConfig {
+            item: cluster(8, "12% increased Attack Damage", &["Feed the Fury"]),
+            config: ItemConfig::default(),
+        };
+        let items = vec![
+            cluster(12, "12% increased Attack Damage", &["Feed the Fury"]),
+            cluster(8, "10% increased Fire Damage", &["Feed the Fury"]),
+            cluster(8, "12% increased Attack Damage", &["Martial Prowess"]),
+            cluster(
+                8,
+                "12% increased Attack Damage",
+                &["Martial Prowess", "Feed the Fury"],
+            ),
+        ];
+        let found = Comparator::closest_item(&required, items.clone()).unwrap();
+        assert_eq!(found, items[3]);
+        assert!(Comparator::closest_item(&required, items[..3].to_vec()).is_none());
+    }
+
+    fn timeless(seed: u32, conqueror: &str) -> StoredItem {
+        StoredItem {
+            name: "Glorious Vanity".to_string(),
+            basetype: "Timeless Jewel".to_string(),
+            info: ItemInfo::UniqueJewel {
+                seed: Some(seed),
+                conqueror: Some(conqueror.to_string()),
+                radius: None,
+                mods: vec![],
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn closest_unique_variant() {
+        let required = ItemWithConfig {
+            item: timeless(8000, "Xibaqua"),
+            config: ItemConfig {
+                option: Some(ItemConfigOption::UniqueVariant {
+                    seed: Some(8000),
+                    conqueror: None,
+                    radius: None,
+                    mods: vec![],
+                }),
+                ..Default::default()
+            },
+        };
+        let items = vec![
+            timeless(1000, "Xibaqua"),
+            timeless(8000, "Ahuana"),
+            timeless(8000, "Xibaqua"),
+        ];
+        let found = Comparator::closest_item(&required, items.clone()).unwrap();
+        assert_eq!(found, items[1]);
+        assert!(Comparator::closest_item(&required, items[..1].to_vec()).is_none());
+    }
+
+    fn belt(name: &str, rarity: &str, life: i32) -> StoredItem {
+        let life = DomainMod::try_by_stat(&format!("+{} to maximum Life", life), ModType::Explicit)
+            .unwrap();
+        StoredItem {
+            name: name.to_string(),
+            basetype: "Heavy Belt".to_string(),
+            rarity: rarity.to_string(),
+            info: ItemInfo::Accessory {
+                quality: 0,
+                mods: vec![life.into()],
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn closest_unique_rolls() {
+        let life = belt("Headhunter", "unique", 0).info.mods()[0]
+            .stat_id
+            .clone();
+        let mut required = ItemWithConfig {
+            item: belt("Headhunter", "unique", 60),
+            config: ItemConfig {
+                option: Some(ItemConfigOption::UniqueRolls {
+                    kind: Some(UniqueKind::Regular),
+                    legacy: false,
+                    mods: [(ModStatId::from(&life), ModOption::Min(55))].into(),
+                }),
+                ..Default::default()
+            },
+        };
+        let items = vec![
+            belt("Headhunter", "unique", 50),
+            belt("Replica Headhunter", "unique", 60),
+            belt("Headhunter", "relic", 60),
+            belt("Headhunter", "unique", 57),
+        ];
+        let found = Comparator::closest_item(&required, items.clone()).unwrap();
+        assert_eq!(found, items[3]);
+        assert_eq!(items[1].unique_kind(), Some(UniqueKind::Replica));
+
+        required.config.option = Some(ItemConfigOption::UniqueRolls {
+            kind: Some(UniqueKind::Relic),
+            legacy: false,
+            mods: [(
+                ModStatId::from(&life),
+                ModOption::Range(RangeInclusiveI32Elixir { start: 55, end: 60 }),
+            )]
+            .into(),
+        });
+        let found = Comparator::closest_item(&required, items.clone()).unwrap();
+        assert_eq!(found, items[2]);
+    }
+
+    #[test]
+    fn closest_legacy_unique() {
+        let mut legacy = belt("Headhunter", "unique", 60);
+        let fire = DomainMod::try_by_stat("+30% to Fire Resistance", ModType::Explicit).unwrap();
+        legacy.info.mut_mods().unwrap().push(fire.into());
+        let required = ItemWithConfig {
+            item: legacy.clone(),
+            config: ItemConfig {
+                option: Some(ItemConfigOption::UniqueRolls {
+                    kind: None,
+                    legacy: true,
+                    mods: Default::default(),
+                }),
+                ..Default::default()
+            },
+        };
+        let items = vec![belt("Headhunter", "unique", 60), legacy];
+        let found = Comparator::closest_item(&required, items.clone()).unwrap();
+        assert_eq!(found, items[1]);
+    }
+
+    // ranges and minimums are checked for rare items too, not only existence
+    #[test]
+    fn closest_mods_by_values() {
+        let life = belt("", "rare", 0).info.mods()[0].stat_id.clone();
+        let mut required = ItemWithConfig {
+            item: belt("", "rare", 70),
+            config: ItemConfig {
+                option: Some(ItemConfigOption::Mods(
+                    [(ModStatId::from(&life), ModOption::Min(65))].into(),
+                )),
+                ..Default::default()
+            },
+        };
+        let items = vec![belt("", "rare", 60), belt("", "rare", 80)];
+        let found = Comparator::closest_item(&required, items.clone()).unwrap();
+        assert_eq!(found, items[1]);
+
+        required.config.option = Some(ItemConfigOption::Mods(
+            [(
+                ModStatId::from(&life),
+                ModOption::Range(RangeInclusiveI32Elixir { start: 55, end: 65 }),
+            )]
+            .into(),
+        ));
+        let found = Comparator::closest_item(&required, items.clone()).unwrap();
+        assert_eq!(found, items[0]);
+
+        // mod text without mod data doesn't match instead of panicking
+        let mut unknown = belt("", "rare", 60);
+        unknown.info.mut_mods().unwrap()[0].text = "unknown mod".to_string();
+        assert!(Comparator::closest_item(&required, vec![unknown]).is_none());
+    }
+}
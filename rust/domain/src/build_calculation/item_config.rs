@@ -3,10 +3,12 @@ use std::{
     ops::{Deref, RangeInclusive},
 };
 
-use rustler::{types::atom, Encoder, NifStruct, NifTaggedEnum, Term};
+use rustler::{types::atom, Encoder, NifStruct, NifTaggedEnum, NifUnitEnum, Term};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
+use crate::item::types::SocketRequirement;
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash, NifStruct)]
 #[module = "PoeSystem.Items.ModStatId"]
 pub struct ModStatId {
@@ -40,12 +42,39 @@ pub struct Quality(u32);
 pub struct ItemConfig {
     pub basetype: bool,
     pub option: Option<ItemConfigOption>,
+    // links and colors needed by skill gems socketed in the item
+    #[serde(default)]
+    pub sockets: Option<SocketRequirement>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, NifTaggedEnum)]
 pub enum ItemConfigOption {
     Mods(HashMap<ModStatId, ModOption>),
     Unique,
+    // unique with variants (timeless jewels, watcher's eye, thread of hope),
+    // empty fields are not checked
+    UniqueVariant {
+        seed: Option<u32>,
+        conqueror: Option<String>,
+        radius: Option<String>,
+        mods: Vec<ModStatId>,
+    },
+    // unique with roll constraints, e.g. Mageblood with 4 flasks.
+    // legacy variant must have every mod of required item, changed rolls
+    // are expressed by mod options
+    UniqueRolls {
+        kind: Option<UniqueKind>,
+        #[serde(default)]
+        legacy: bool,
+        mods: HashMap<ModStatId, ModOption>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, NifUnitEnum)]
+pub enum UniqueKind {
+    Regular,
+    Replica,
+    Relic,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, NifStruct)]
@@ -59,6 +88,7 @@ pub struct RangeInclusiveI32Elixir {
 pub enum ModOption {
     Exact(i32),
     Range(RangeInclusiveI32Elixir),
+    Min(i32),
     Exist,
     Ignore,
 }
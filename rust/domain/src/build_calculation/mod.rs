@@ -4,15 +4,13 @@ pub mod stored_item;
 
 use std::{collections::HashMap, str::FromStr};
 
-use item_config::{ItemConfig, ItemConfigOption, ModOption, ModStatId};
+use item_config::{ItemConfig, ItemConfigOption, ModOption, ModStatId, UniqueKind};
 use rustler::NifStruct;
 use serde::{Deserialize, Serialize};
 use stored_item::{ItemInfo, StoredItem};
 use strum::EnumString;
 use ts_rs::TS;
 
-use crate::data::MODS;
-
 #[derive(Serialize, Deserialize, Debug, Default, NifStruct)]
 #[module = "PoeSystem.Build.BuildInfo"]
 pub struct BuildInfo {
@@ -95,9 +93,26 @@ impl BuildItemsWithConfig {
         }
     }
 
+    fn unique_option(item: &ItemWithConfig) -> Option<ItemConfigOption> {
+        // legacy variant is known only at import, keep it
+        if let Some(option @ ItemConfigOption::UniqueRolls { legacy: true, .. }) =
+            &item.config.option
+        {
+            return Some(option.clone());
+        }
+        match item.item.unique_kind()? {
+            UniqueKind::Relic => Some(ItemConfigOption::UniqueRolls {
+                kind: Some(UniqueKind::Relic),
+                legacy: false,
+                mods: HashMap::new(),
+            }),
+            _ => Some(ItemConfigOption::Unique),
+        }
+    }
+
     fn simple_everything(item: &mut ItemWithConfig) {
-        if item.item.rarity == "unique" {
-            item.config.option = Some(ItemConfigOption::Unique);
+        if let Some(option) = BuildItemsWithConfig::unique_option(item) {
+            item.config.option = Some(option);
         } else if matches!(item.item.info, ItemInfo::Gem { .. }) {
             item.config.basetype = true;
         } else {
@@ -116,11 +131,12 @@ impl BuildItemsWithConfig {
     }
 
     fn simple_nores(item: &mut ItemWithConfig) {
-        if item.item.rarity == "unique" {
-            item.config.option = Some(ItemConfigOption::Unique);
+        if let Some(option) = BuildItemsWithConfig::unique_option(item) {
+            item.config.option = Some(option);
         } else if matches!(item.item.info, ItemInfo::Gem { .. }) {
             item.config.basetype = true;
         } else {
+            let game_version = item.item.game_version;
             let mods = item
                 .item
                 .info
@@ -128,7 +144,7 @@ impl BuildItemsWithConfig {
                 .iter_mut()
                 .flat_map(|x| x.iter_mut())
                 .fold(HashMap::new(), |mut acc, m| {
-                    if let Some(mt) = MODS::get_mod_data(&m.text) {
+                    if let Some(mt) = game_version.get_mod_data(&m.text) {
                         let tags = mt.mod_type().get_tags();
                         let elemental = tags.iter().any(|x| x == "elemental");
                         let resistance = tags.iter().any(|x| x == "resistance");
@@ -1,12 +1,15 @@
 use crate::{
-    data::ModValue as DataModValue,
+    build_calculation::item_config::UniqueKind,
+    data::{GameVersion, ModValue as DataModValue},
     item::{
-        types::{Category, Mod as DomainMod, ModValue, Subcategory, SubcategoryError, TypeError},
+        types::{
+            Category, Mod as DomainMod, ModValue, Rarity, Subcategory, SubcategoryError, TypeError,
+        },
         Item,
     },
 };
 use regex::bytes::Regex;
-use rustler::{NifStruct, NifTaggedEnum};
+use rustler::{NifStruct, NifTaggedEnum, NifUnitEnum};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -60,6 +63,24 @@ pub enum ItemInfo {
     Jewel {
         mods: Vec<Mod>,
     },
+    ClusterJewel {
+        size: ClusterJewelSize,
+        passives: u8,
+        enchant: String,
+        notables: Vec<String>,
+        mods: Vec<Mod>,
+    },
+    // abyss jewels have no sockets of their own, abyssal sockets they go into
+    // are part of host item sockets and required through SocketRequirement
+    AbyssJewel {
+        mods: Vec<Mod>,
+    },
+    UniqueJewel {
+        seed: Option<u32>,
+        conqueror: Option<String>,
+        radius: Option<String>,
+        mods: Vec<Mod>,
+    },
     Flask {
         quality: u8,
         mods: Vec<Mod>,
@@ -85,6 +106,11 @@ impl ItemInfo {
             ItemInfo::Armor { mods, .. } => mods.iter().map(|m| m.stat_id.as_str()).collect(),
             ItemInfo::Weapon { mods, .. } => mods.iter().map(|m| m.stat_id.as_str()).collect(),
             ItemInfo::Jewel { mods, .. } => mods.iter().map(|m| m.stat_id.as_str()).collect(),
+            ItemInfo::ClusterJewel { mods, .. } => {
+                mods.iter().map(|m| m.stat_id.as_str()).collect()
+            }
+            ItemInfo::AbyssJewel { mods, .. } => mods.iter().map(|m| m.stat_id.as_str()).collect(),
+            ItemInfo::UniqueJewel { mods, .. } => mods.iter().map(|m| m.stat_id.as_str()).collect(),
             ItemInfo::Flask { mods, .. } => mods.iter().map(|m| m.stat_id.as_str()).collect(),
             ItemInfo::Accessory { mods, .. } => mods.iter().map(|m| m.stat_id.as_str()).collect(),
             ItemInfo::Gem { .. } => panic!("gems have no mods"),
@@ -98,6 +124,9 @@ impl ItemInfo {
             ItemInfo::Gem { .. } => &[],
             ItemInfo::Flask { mods, .. } => &mods[..],
             ItemInfo::Jewel { mods, .. } => &mods[..],
+            ItemInfo::ClusterJewel { mods, .. } => &mods[..],
+            ItemInfo::AbyssJewel { mods, .. } => &mods[..],
+            ItemInfo::UniqueJewel { mods, .. } => &mods[..],
             ItemInfo::Accessory { mods, .. } => &mods[..],
         }
     }
@@ -109,11 +138,36 @@ impl ItemInfo {
             ItemInfo::Gem { .. } => return None,
             ItemInfo::Flask { mods, .. } => mods,
             ItemInfo::Jewel { mods, .. } => mods,
+            ItemInfo::ClusterJewel { mods, .. } => mods,
+            ItemInfo::AbyssJewel { mods, .. } => mods,
+            ItemInfo::UniqueJewel { mods, .. } => mods,
             ItemInfo::Accessory { mods, .. } => mods,
         })
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, NifUnitEnum)]
+pub enum ClusterJewelSize {
+    Small,
+    Medium,
+    Large,
+}
+
+impl ClusterJewelSize {
+    fn from_basetype(basetype: &str) -> Option<ClusterJewelSize> {
+        match basetype {
+            "Small Cluster Jewel" => Some(ClusterJewelSize::Small),
+            "Medium Cluster Jewel" => Some(ClusterJewelSize::Medium),
+            "Large Cluster Jewel" => Some(ClusterJewelSize::Large),
+            _ => None,
+        }
+    }
+}
+
+const CLUSTER_NOTABLE_STAT_PREFIX: &str = "local_affliction_notable_";
+const CLUSTER_NOTABLE_PREFIX: &str = "1 Added Passive Skill is ";
+const CLUSTER_ENCHANT_PREFIX: &str = "Added Small Passive Skills grant: ";
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, NifTaggedEnum)]
 pub enum Price {
     Chaos(i32),
@@ -128,6 +182,15 @@ impl Default for Price {
 }
 
 impl Price {
+    // currency name and amount, e.g. ("divine", 2)
+    pub fn parts(&self) -> (&str, i32) {
+        match self {
+            Self::Chaos(v) => ("chaos", *v),
+            Self::Divine(v) => ("divine", *v),
+            Self::Custom(c, v) => (c.as_str(), *v),
+        }
+    }
+
     pub fn is_zero(&self) -> bool {
         match self {
             Self::Chaos(i) => *i == 0,
@@ -148,14 +211,48 @@ pub struct StoredItem {
     pub name: String,
     pub price: Price,
     pub rarity: String,
+    // e.g. "R-G-B W", None for items without sockets
+    #[serde(default)]
+    pub sockets: Option<String>,
+    // selects mods data when comparing mod values
+    #[serde(default)]
+    pub game_version: GameVersion,
 }
 
 lazy_static::lazy_static! {
     static ref PRICE_REGEX: regex::bytes::Regex = Regex::new(r#"~(price|b/o) ([0-9\.]+) ([a-z]+)"#).unwrap();
+    // timeless jewels: "Bathed in the blood of 8000 sacrificed in the name of Xibaqua"
+    static ref TIMELESS_REGEX: regex::bytes::Regex = Regex::new(r#"^(?:Bathed in the blood of|Commanded leadership over|Denoted service of|Carved to glorify|Commissioned|Remembrancing) ([0-9]+) .* ([A-Za-z]+)$"#).unwrap();
+    // thread of hope: "Only affects Passives in Large Ring"
+    static ref RADIUS_REGEX: regex::bytes::Regex = Regex::new(r#"^Only affects Passives in (.+) Ring$"#).unwrap();
 }
 
 impl StoredItem {
-    fn extract_price(s: &str) -> Option<Price> {
+    pub fn unique_kind(&self) -> Option<UniqueKind> {
+        match self.rarity.as_str() {
+            "relic" => Some(UniqueKind::Relic),
+            "unique" if self.name.starts_with("Replica ") => Some(UniqueKind::Replica),
+            "unique" => Some(UniqueKind::Regular),
+            _ => None,
+        }
+    }
+
+    fn extract_timeless(s: &str) -> Option<(u32, String)> {
+        let c = TIMELESS_REGEX.captures(s.as_bytes())?;
+        let seed = std::str::from_utf8(c.get(1)?.as_bytes())
+            .ok()?
+            .parse()
+            .ok()?;
+        let conqueror = String::from_utf8_lossy(c.get(2)?.as_bytes()).to_string();
+        Some((seed, conqueror))
+    }
+
+    fn extract_radius(s: &str) -> Option<String> {
+        let c = RADIUS_REGEX.captures(s.as_bytes())?;
+        Some(String::from_utf8_lossy(c.get(1)?.as_bytes()).to_string())
+    }
+
+    pub fn extract_price(s: &str) -> Option<Price> {
         let c = PRICE_REGEX.captures(s.as_bytes())?;
         let count = c.get(2)?;
         let curr = c.get(3)?;
@@ -201,8 +298,49 @@ impl TryFrom<Item> for StoredItem {
         }
 
         let basetype = value.base_type;
-        let category = Category::get_from_basetype(&basetype)?;
-        let subcategory = Subcategory::get_from_basetype(&basetype)?;
+        let category = Category::get_from_versioned_basetype(value.game_version, &basetype)?;
+        let subcategory = Subcategory::get_from_versioned_basetype(value.game_version, &basetype)?;
+        let is_abyss_jewel = value
+            .game_version
+            .get_base_by_name(&basetype)
+            .is_some_and(|b| b.tags.iter().any(|t| t == "abyss_jewel"));
+        let cluster_size = ClusterJewelSize::from_basetype(&basetype);
+        let cluster_passives = value
+            .mods
+            .iter()
+            .find_map(|m| {
+                m.text
+                    .strip_prefix("Adds ")?
+                    .strip_suffix(" Passive Skills")?
+                    .parse()
+                    .ok()
+            })
+            .unwrap_or_default();
+        let cluster_notables = value
+            .mods
+            .iter()
+            .filter(|m| m.stat_id.starts_with(CLUSTER_NOTABLE_STAT_PREFIX))
+            .filter_map(|m| m.text.strip_prefix(CLUSTER_NOTABLE_PREFIX))
+            .map(|s| s.to_string())
+            .collect();
+        let (seed, conqueror) = value
+            .unknown_mods
+            .iter()
+            .find_map(|m| StoredItem::extract_timeless(m))
+            .unzip();
+        let radius = value
+            .unknown_mods
+            .iter()
+            .find_map(|m| StoredItem::extract_radius(m));
+        let is_unique = matches!(value.rarity, Rarity::Unique | Rarity::Relic);
+        // enchants are not present in mods data so they end up in unknown mods
+        let cluster_enchant = value
+            .unknown_mods
+            .iter()
+            .chain(value.mods.iter().map(|m| &m.text))
+            .find_map(|m| m.strip_prefix(CLUSTER_ENCHANT_PREFIX))
+            .map(|s| s.to_string())
+            .unwrap_or_default();
         let mods = value.mods.into_iter().map(|m| m.into()).collect();
         let props = value.properties;
         let quality = props
@@ -267,7 +405,23 @@ impl TryFrom<Item> for StoredItem {
                 Some(ItemInfo::Gem { level, quality })
             }
             Category::Flasks => Some(ItemInfo::Flask { quality, mods }),
-            Category::Jewels => Some(ItemInfo::Jewel { mods }),
+            Category::Jewels => Some(match cluster_size {
+                Some(size) => ItemInfo::ClusterJewel {
+                    size,
+                    passives: cluster_passives,
+                    enchant: cluster_enchant,
+                    notables: cluster_notables,
+                    mods,
+                },
+                None if is_abyss_jewel => ItemInfo::AbyssJewel { mods },
+                None if is_unique => ItemInfo::UniqueJewel {
+                    seed,
+                    conqueror,
+                    radius,
+                    mods,
+                },
+                None => ItemInfo::Jewel { mods },
+            }),
             Category::Accessories => Some(ItemInfo::Accessory { quality, mods }),
             _ => None,
         };
@@ -283,6 +437,8 @@ impl TryFrom<Item> for StoredItem {
             name: value.name,
             price,
             rarity: value.rarity.into(),
+            sockets: Some(value.sockets.to_string()).filter(|s| !s.is_empty()),
+            game_version: value.game_version,
         })
     }
 }
@@ -290,6 +446,7 @@ impl TryFrom<Item> for StoredItem {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::item::types::ModType;
 
     #[test]
     fn extract_price() {
@@ -318,4 +475,97 @@ mod tests {
             StoredItem::extract_price("~b/o 10.99 alt custom text")
         );
     }
+
+    #[test]
+    fn cluster_jewel_info() -> Result<(), anyhow::Error> {
+        let item = Item {
+            category: Category::Jewels,
+            base_type: "Large Cluster Jewel".to_string(),
+            mods: vec![
+                DomainMod::try_by_stat("Adds 8 Passive Skills", ModType::Enchant)?,
+                DomainMod::try_by_stat(
+                    "1 Added Passive Skill is Feed the Fury",
+                    ModType::Explicit,
+                )?,
+                DomainMod::try_by_stat("+40 to maximum Life", ModType::Explicit)?,
+            ],
+            unknown_mods: vec![
+                "Added Small Passive Skills grant: 12% increased Attack Damage".to_string(),
+            ],
+            ..Default::default()
+        };
+        let stored = StoredItem::try_from(item)?;
+        match stored.info {
+            ItemInfo::ClusterJewel {
+                size,
+                passives,
+                enchant,
+                notables,
+                mods,
+            } => {
+                assert_eq!(size, ClusterJewelSize::Large);
+                assert_eq!(passives, 8);
+                assert_eq!(enchant, "12% increased Attack Damage");
+                assert_eq!(notables, vec!["Feed the Fury".to_string()]);
+                assert_eq!(mods.len(), 3);
+            }
+            i => panic!("wrong item info: {:?}", i),
+        }
+
+        let item = Item {
+            category: Category::Jewels,
+            base_type: "Murderous Eye Jewel".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            StoredItem::try_from(item)?.info,
+            ItemInfo::AbyssJewel { .. }
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn unique_jewel_info() -> Result<(), anyhow::Error> {
+        let item = Item {
+            name: "Glorious Vanity".to_string(),
+            category: Category::Jewels,
+            base_type: "Timeless Jewel".to_string(),
+            rarity: Rarity::Unique,
+            unknown_mods: vec![
+                "Bathed in the blood of 8000 sacrificed in the name of Xibaqua".to_string(),
+                "Passives in radius are Conquered by the Vaal".to_string(),
+            ],
+            ..Default::default()
+        };
+        match StoredItem::try_from(item)?.info {
+            ItemInfo::UniqueJewel {
+                seed,
+                conqueror,
+                radius,
+                ..
+            } => {
+                assert_eq!(seed, Some(8000));
+                assert_eq!(conqueror, Some("Xibaqua".to_string()));
+                assert_eq!(radius, None);
+            }
+            i => panic!("wrong item info: {:?}", i),
+        }
+
+        let item = Item {
+            name: "Thread of Hope".to_string(),
+            category: Category::Jewels,
+            base_type: "Crimson Jewel".to_string(),
+            rarity: Rarity::Unique,
+            unknown_mods: vec!["Only affects Passives in Large Ring".to_string()],
+            ..Default::default()
+        };
+        match StoredItem::try_from(item)?.info {
+            ItemInfo::UniqueJewel { seed, radius, .. } => {
+                assert_eq!(seed, None);
+                assert_eq!(radius, Some("Large".to_string()));
+            }
+            i => panic!("wrong item info: {:?}", i),
+        }
+        Ok(())
+    }
 }
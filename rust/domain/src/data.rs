@@ -11,6 +11,10 @@ use std::{
 };
 use ts_rs::TS;
 
+use crate::item::types::SocketColor;
+
+pub mod poe2;
+
 pub fn cut_numbers(val: &str) -> String {
     val.replace(|el: char| el == '{' || el == '}' || el.is_numeric(), "")
 }
@@ -185,6 +189,10 @@ impl ModType {
     pub fn get_tags(&self) -> &[String] {
         &self.tags
     }
+
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.variants.iter().map(|v| v.0.as_str())
+    }
 }
 
 #[derive(Debug, serde::Deserialize, Clone)]
@@ -378,6 +386,10 @@ lazy_static! {
         hm
     };
     pub static ref BASE_TYPES: HashSet<String> = BASE_ITEMS_BY_NAME.keys().cloned().collect();
+    static ref GEM_COLORS: HashMap<String, SocketColor> = {
+        let gem_colors_file = include_bytes!("../dist/gem_colors.min.json");
+        serde_json::from_slice(gem_colors_file).unwrap()
+    };
     pub static ref MODS: SerializedModData = {
         let mods_file = include_bytes!("../dist/mods.data");
         bincode::deserialize(mods_file).unwrap()
@@ -393,6 +405,63 @@ impl LAZY_MODS_REGEX {
     }
 }
 
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    serde::Deserialize,
+    serde::Serialize,
+    strum::EnumString,
+    strum::AsRefStr,
+    rustler::NifUnitEnum,
+)]
+#[strum(ascii_case_insensitive)]
+pub enum GameVersion {
+    #[default]
+    Poe1,
+    Poe2,
+}
+
+impl GameVersion {
+    pub fn get_base_by_name(&self, name: &str) -> Option<BasetypeInfo> {
+        match self {
+            GameVersion::Poe1 => BaseItems::get_by_name(name),
+            GameVersion::Poe2 => poe2::BaseItems::get_by_name(name),
+        }
+    }
+
+    pub fn get_base_by_id(&self, id: &str) -> Option<BasetypeInfo> {
+        match self {
+            GameVersion::Poe1 => BaseItems::get_by_id(id),
+            GameVersion::Poe2 => poe2::BaseItems::get_by_id(id),
+        }
+    }
+
+    pub fn contains_base_name(&self, name: &str) -> bool {
+        match self {
+            GameVersion::Poe1 => BaseItems::contains_name(name),
+            GameVersion::Poe2 => poe2::BaseItems::contains_name(name),
+        }
+    }
+
+    pub fn base_types(&self) -> &'static HashSet<String> {
+        match self {
+            GameVersion::Poe1 => &BASE_TYPES,
+            GameVersion::Poe2 => &poe2::BASE_TYPES,
+        }
+    }
+
+    pub(crate) fn get_mod_data(&self, value: &str) -> Option<ModExtractor<'static>> {
+        match self {
+            GameVersion::Poe1 => MODS::get_mod_data(value),
+            GameVersion::Poe2 => poe2::MODS::get_mod_data(value),
+        }
+    }
+}
+
 pub struct BaseItems;
 
 impl BaseItems {
@@ -404,6 +473,18 @@ impl BaseItems {
         BASE_ITEMS_BY_NAME.contains_key(name)
     }
 
+    // socket color of poe1 gem by its metadata id
+    pub fn gem_color(id: &str) -> Option<SocketColor> {
+        GEM_COLORS
+            .get(id)
+            .or_else(|| {
+                BASE_ITEMS_MISSING_MAP_BY_ID
+                    .get(id)
+                    .and_then(|id| GEM_COLORS.get(id))
+            })
+            .cloned()
+    }
+
     pub fn get_by_id(id: &str) -> Option<BasetypeInfo> {
         let res = BASE_ITEMS_BY_ID.get(id).cloned();
         if res.is_none() {
@@ -461,12 +542,16 @@ impl ModExtractor<'_> {
     }
 }
 
+fn get_mod_data(mods: &'static SerializedModData, value: &str) -> Option<ModExtractor<'static>> {
+    let (k, _) = replace_for_regex(value);
+    let m = mods.get(&k)?;
+    let reg = LAZY_MODS_REGEX.get_regex(&k);
+    Some(ModExtractor { re: reg, m })
+}
+
 impl MODS {
     pub(crate) fn get_mod_data(value: &str) -> Option<ModExtractor<'static>> {
-        let (k, _) = replace_for_regex(value);
-        let mods = MODS.get(&k)?;
-        let reg = LAZY_MODS_REGEX.get_regex(&k);
-        Some(ModExtractor { re: reg, m: mods })
+        get_mod_data(&MODS, value)
     }
 }
 
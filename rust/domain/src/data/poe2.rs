@@ -0,0 +1,62 @@
+use lazy_static::lazy_static;
+use std::collections::{HashMap, HashSet};
+
+use super::{get_mod_data, BasetypeInfo, ModExtractor, SerializedModData};
+
+lazy_static! {
+    static ref BASE_ITEMS_BY_ID: HashMap<String, BasetypeInfo> = {
+        let base_items_file = include_bytes!("../../dist/poe2/base_items.min.json");
+        serde_json::from_slice(base_items_file).unwrap()
+    };
+    static ref BASE_ITEMS_BY_NAME: HashMap<String, BasetypeInfo> =
+        BASE_ITEMS_BY_ID
+            .values()
+            .fold(HashMap::new(), |mut acc, info| {
+                if info.name.is_empty() {
+                    return acc;
+                }
+                acc.entry(info.name.clone()).or_insert(info.clone());
+                acc
+            });
+    pub static ref BASE_TYPES: HashSet<String> = BASE_ITEMS_BY_NAME.keys().cloned().collect();
+    pub static ref MODS: SerializedModData = {
+        let mods_file = include_bytes!("../../dist/poe2/mods.data");
+        bincode::deserialize(mods_file).unwrap()
+    };
+}
+
+impl MODS {
+    pub(crate) fn get_mod_data(value: &str) -> Option<ModExtractor<'static>> {
+        get_mod_data(&MODS, value)
+    }
+}
+
+pub struct BaseItems;
+
+impl BaseItems {
+    pub fn get_by_name(name: &str) -> Option<BasetypeInfo> {
+        BASE_ITEMS_BY_NAME.get(name).cloned()
+    }
+
+    pub fn contains_name(name: &str) -> bool {
+        BASE_ITEMS_BY_NAME.contains_key(name)
+    }
+
+    pub fn get_by_id(id: &str) -> Option<BasetypeInfo> {
+        BASE_ITEMS_BY_ID.get(id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BaseItems;
+
+    #[test]
+    fn poe2_base_items() {
+        let focus = BaseItems::get_by_name("Twig Focus").unwrap();
+        assert_eq!(focus.item_class, "Focus");
+        assert!(!BaseItems::contains_name("Slink Boots"));
+        let gem = BaseItems::get_by_id("Metadata/Items/Gems/SupportGemSpellEcho").unwrap();
+        assert_eq!(gem.name, "Spell Echo");
+    }
+}
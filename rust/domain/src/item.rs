@@ -1,5 +1,6 @@
 pub mod types;
 
+use crate::data::GameVersion;
 use serde::{Deserialize, Serialize};
 use std::default::Default;
 use types::{Category, Hybrid, Influence, League, Mod, Property, Rarity, Sockets, Subcategory};
@@ -27,6 +28,11 @@ pub struct Item {
     pub sockets: Sockets,
     pub properties: Vec<Property>,
     pub note: Option<String>,
+    #[serde(default)]
+    pub game_version: GameVersion,
+    // selected unique variant from pob, e.g. "Pre 3.16.0"
+    #[serde(default)]
+    pub variant: Option<String>,
 }
 
 impl Item {}
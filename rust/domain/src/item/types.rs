@@ -1,12 +1,12 @@
 use itertools::Itertools;
-use rustler::{NifStruct, NifUnitEnum};
+use rustler::{NifMap, NifStruct, NifUnitEnum};
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, convert::TryFrom, ops::Deref, str::FromStr};
 use strum::{AsRefStr, EnumString};
 use thiserror::Error;
 use ts_rs::TS;
 
-use crate::data::{BaseItems, ModValue as DataModValue, MODS};
+use crate::data::{GameVersion, ModValue as DataModValue};
 
 #[derive(Error, Debug)]
 pub enum TypeError {
@@ -59,7 +59,15 @@ lazy_static::lazy_static! {
 
 impl Category {
     pub fn get_from_basetype<T: AsRef<str>>(basetype: T) -> Result<Category, TypeError> {
-        let baseinfo = BaseItems::get_by_name(basetype.as_ref())
+        Category::get_from_versioned_basetype(GameVersion::Poe1, basetype)
+    }
+
+    pub fn get_from_versioned_basetype<T: AsRef<str>>(
+        version: GameVersion,
+        basetype: T,
+    ) -> Result<Category, TypeError> {
+        let baseinfo = version
+            .get_base_by_name(basetype.as_ref())
             .ok_or(TypeError::UnknownCategory(basetype.as_ref().to_string()))?;
         for (k, v) in CATEGORY_MAPPING.deref().iter() {
             if baseinfo.tags.contains(k) {
@@ -158,6 +166,38 @@ lazy_static::lazy_static! {
         hm.insert("Warstaff".to_string(), Subcategory::Weapon);
         hm
     };
+    static ref POE2_SUBCATEGORY_MAPPING: HashMap<String, Subcategory> = {
+        let mut hm = HashMap::new();
+        hm.insert("Boots".to_string(), Subcategory::Boots);
+        hm.insert("Helmet".to_string(), Subcategory::Helmets);
+        hm.insert("Active Skill Gem".to_string(), Subcategory::Gem);
+        hm.insert("Support Skill Gem".to_string(), Subcategory::Gem);
+        hm.insert("Amulet".to_string(), Subcategory::Amulet);
+        hm.insert("Belt".to_string(), Subcategory::Belt);
+        hm.insert("Body Armour".to_string(), Subcategory::BodyArmour);
+        hm.insert("Gloves".to_string(), Subcategory::Gloves);
+        hm.insert("Jewel".to_string(), Subcategory::Jewel);
+        hm.insert("LifeFlask".to_string(), Subcategory::LifeFlask);
+        hm.insert("ManaFlask".to_string(), Subcategory::ManaFlask);
+        // charms took the place of utility flasks
+        hm.insert("UtilityFlask".to_string(), Subcategory::Charm);
+        hm.insert("Ring".to_string(), Subcategory::Ring);
+        hm.insert("Quiver".to_string(), Subcategory::Quiver);
+        hm.insert("Shield".to_string(), Subcategory::Shield);
+        hm.insert("Buckler".to_string(), Subcategory::Shield);
+        hm.insert("Focus".to_string(), Subcategory::Focus);
+        hm.insert("Bow".to_string(), Subcategory::Weapon);
+        hm.insert("Crossbow".to_string(), Subcategory::Weapon);
+        hm.insert("Flail".to_string(), Subcategory::Weapon);
+        hm.insert("One Hand Mace".to_string(), Subcategory::Weapon);
+        hm.insert("Two Hand Mace".to_string(), Subcategory::Weapon);
+        hm.insert("Sceptre".to_string(), Subcategory::Weapon);
+        hm.insert("Spear".to_string(), Subcategory::Weapon);
+        hm.insert("Staff".to_string(), Subcategory::Weapon);
+        hm.insert("Wand".to_string(), Subcategory::Weapon);
+        hm.insert("Warstaff".to_string(), Subcategory::Weapon);
+        hm
+    };
 }
 
 #[derive(
@@ -183,6 +223,8 @@ pub enum Subcategory {
     Shield,
     Quiver,
     Ring,
+    Focus,
+    Charm,
 }
 
 #[derive(Error, Debug)]
@@ -193,11 +235,22 @@ pub enum SubcategoryError {
 
 impl Subcategory {
     pub fn get_from_basetype<T: AsRef<str>>(basetype: T) -> Result<Subcategory, SubcategoryError> {
-        let baseinfo = BaseItems::get_by_name(basetype.as_ref()).ok_or(
+        Subcategory::get_from_versioned_basetype(GameVersion::Poe1, basetype)
+    }
+
+    pub fn get_from_versioned_basetype<T: AsRef<str>>(
+        version: GameVersion,
+        basetype: T,
+    ) -> Result<Subcategory, SubcategoryError> {
+        let baseinfo = version.get_base_by_name(basetype.as_ref()).ok_or(
             SubcategoryError::UnknownSubcategory(basetype.as_ref().to_string()),
         )?;
+        let mapping: &HashMap<String, Subcategory> = match version {
+            GameVersion::Poe1 => &SUBCATEGORY_MAPPING,
+            GameVersion::Poe2 => &POE2_SUBCATEGORY_MAPPING,
+        };
 
-        Ok(SUBCATEGORY_MAPPING
+        Ok(mapping
             .get(&baseinfo.item_class)
             .ok_or(SubcategoryError::UnknownSubcategory(
                 basetype.as_ref().to_string(),
@@ -241,6 +294,20 @@ pub enum ModValue {
     },
 }
 
+impl ModValue {
+    // first value truncated to integer, e.g. lower bound of "Adds 5 to 10 Fire Damage"
+    pub fn first_int(&self) -> Option<i32> {
+        let v = match self {
+            ModValue::Nothing => return None,
+            ModValue::Exact(v) | ModValue::DoubleExact { from: v, .. } => v,
+        };
+        Some(match v {
+            DataModValue::Int(i) => *i,
+            DataModValue::Float(f) => *f as i32,
+        })
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Default, TS)]
 #[ts(export)]
 pub struct Mod {
@@ -256,7 +323,15 @@ pub struct Mod {
 
 impl Mod {
     pub fn try_by_stat(value: &str, typ: ModType) -> Result<Self, ModError> {
-        if let Some(ext) = MODS::get_mod_data(value) {
+        Mod::try_by_versioned_stat(GameVersion::Poe1, value, typ)
+    }
+
+    pub fn try_by_versioned_stat(
+        version: GameVersion,
+        value: &str,
+        typ: ModType,
+    ) -> Result<Self, ModError> {
+        if let Some(ext) = version.get_mod_data(value) {
             return Ok(Mod {
                 text: value.to_string(),
                 type_: typ,
@@ -276,7 +351,16 @@ impl Mod {
     }
 
     pub fn try_by_range_stat(value: &str, range: f32, typ: ModType) -> Result<Self, ModError> {
-        if let Some(ext) = MODS::get_mod_data(value) {
+        Mod::try_by_versioned_range_stat(GameVersion::Poe1, value, range, typ)
+    }
+
+    pub fn try_by_versioned_range_stat(
+        version: GameVersion,
+        value: &str,
+        range: f32,
+        typ: ModType,
+    ) -> Result<Self, ModError> {
+        if let Some(ext) = version.get_mod_data(value) {
             return Ok(Mod {
                 text: value.to_string(),
                 type_: typ,
@@ -310,6 +394,7 @@ pub enum Rarity {
     Magic,
     Rare,
     Unique,
+    Relic,
 }
 
 impl TryFrom<&str> for Rarity {
@@ -319,7 +404,8 @@ impl TryFrom<&str> for Rarity {
         match v.to_lowercase().as_str() {
             "magic" => Ok(Rarity::Normal),
             "rare" => Ok(Rarity::Rare),
-            "unique" | "relic" => Ok(Rarity::Unique),
+            "unique" => Ok(Rarity::Unique),
+            "relic" => Ok(Rarity::Relic),
             "normal" | "" => Ok(Rarity::Normal),
             _ => Err(TypeError::RarityParse(v.to_string())),
         }
@@ -333,6 +419,7 @@ impl From<Rarity> for String {
             Rarity::Rare => "rare",
             Rarity::Magic => "magic",
             Rarity::Unique => "unique",
+            Rarity::Relic => "relic",
         })
         .into()
     }
@@ -367,6 +454,18 @@ impl TryFrom<&str> for Sockets {
     }
 }
 
+// same format as in pob and stash api, e.g. "R-G-B W"
+impl std::fmt::Display for Sockets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let groups = self
+            .groups
+            .iter()
+            .map(|g| g.sockets.iter().map(|s| s.as_ref()).join("-"))
+            .join(" ");
+        write!(f, "{}", groups)
+    }
+}
+
 impl Sockets {
     pub fn max_links(&self) -> usize {
         self.groups
@@ -379,6 +478,24 @@ impl Sockets {
     pub fn colors(&self) -> HashMap<SocketColor, usize> {
         self.groups.iter().flat_map(|s| s.sockets.clone()).counts()
     }
+
+    pub fn satisfies(&self, req: &SocketRequirement) -> bool {
+        let abyssal = self.colors().get(&SocketColor::A).copied().unwrap_or_default();
+        abyssal >= req.abyssal && self.groups.iter().any(|g| g.satisfies(req))
+    }
+
+    // smallest linked group which fits given amount of gems
+    pub fn requirement_for_links(&self, links: usize) -> Option<SocketRequirement> {
+        self.groups
+            .iter()
+            .filter(|g| g.sockets.len() >= links)
+            .min_by_key(|g| g.sockets.len())
+            .map(|g| SocketRequirement {
+                links: g.sockets.len(),
+                colors: g.sockets.iter().cloned().counts(),
+                ..Default::default()
+            })
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
@@ -386,20 +503,70 @@ pub struct SocketGroup {
     sockets: Vec<SocketColor>,
 }
 
-#[derive(Hash, PartialEq, Eq, Clone, Serialize, Deserialize, Debug, Default, EnumString)]
+impl SocketGroup {
+    fn satisfies(&self, req: &SocketRequirement) -> bool {
+        if self.sockets.len() < req.links {
+            return false;
+        }
+        let have = self.sockets.iter().cloned().counts();
+        let count = |c: &SocketColor| have.get(c).copied().unwrap_or_default();
+        // white sockets fit any gem, the rest must match colors exactly
+        let Some(spare_white) = count(&SocketColor::W)
+            .checked_sub(req.colors.get(&SocketColor::W).copied().unwrap_or_default())
+        else {
+            return false;
+        };
+        let missing: usize = req
+            .colors
+            .iter()
+            .filter(|(color, _)| **color != SocketColor::W)
+            .map(|(color, n)| n.saturating_sub(count(color)))
+            .sum();
+        missing <= spare_white
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, NifMap)]
+pub struct SocketRequirement {
+    pub links: usize,
+    pub colors: HashMap<SocketColor, usize>,
+    // abyssal sockets needed for socketed abyss jewels, they don't have to be linked
+    #[serde(default)]
+    pub abyssal: usize,
+}
+
+#[derive(
+    Hash,
+    PartialEq,
+    Eq,
+    Clone,
+    Serialize,
+    Deserialize,
+    Debug,
+    Default,
+    EnumString,
+    AsRefStr,
+    NifUnitEnum,
+)]
 pub enum SocketColor {
     R,
     G,
     B,
     W,
     A,
+    // poe2 rune sockets have no color
+    S,
     #[default]
     NotSupported,
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Mod, ModType, ModValue};
+    use super::{
+        Category, Mod, ModType, ModValue, SocketColor, SocketRequirement, Sockets, Subcategory,
+    };
+    use crate::data::GameVersion;
+    use std::collections::HashMap;
 
     #[test]
     fn mod_parse() {
@@ -425,4 +592,73 @@ mod tests {
         )?;
         Ok(())
     }
+
+    #[test]
+    fn poe2_categories() -> Result<(), anyhow::Error> {
+        let cases = [
+            ("Twig Focus", Category::Armour, Subcategory::Focus),
+            ("Wooden Buckler", Category::Armour, Subcategory::Shield),
+            ("Thawing Charm", Category::Flasks, Subcategory::Charm),
+            ("Makeshift Crossbow", Category::Weapons, Subcategory::Weapon),
+            ("Spell Echo", Category::Gems, Subcategory::Gem),
+        ];
+        for (base, cat, subcat) in cases {
+            assert_eq!(
+                Category::get_from_versioned_basetype(GameVersion::Poe2, base)?,
+                cat
+            );
+            assert_eq!(
+                Subcategory::get_from_versioned_basetype(GameVersion::Poe2, base)?,
+                subcat
+            );
+        }
+        assert!(Category::get_from_basetype("Twig Focus").is_err());
+        assert!(
+            Subcategory::get_from_versioned_basetype(GameVersion::Poe2, "Slink Boots").is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sockets_requirement() -> Result<(), anyhow::Error> {
+        let sockets = Sockets::try_from("B-G-R-B B-W")?;
+        let req = sockets.requirement_for_links(2).unwrap();
+        assert_eq!(req.links, 2);
+        assert_eq!(req.colors.get(&SocketColor::W), Some(&1));
+        assert!(sockets.satisfies(&req));
+
+        let req = sockets.requirement_for_links(3).unwrap();
+        assert_eq!(req.links, 4);
+        assert!(sockets.requirement_for_links(5).is_none());
+
+        let mut colors = HashMap::new();
+        colors.insert(SocketColor::R, 2);
+        let req = SocketRequirement {
+            links: 2,
+            colors,
+            ..Default::default()
+        };
+        assert!(sockets.satisfies(&SocketRequirement {
+            links: 2,
+            colors: [(SocketColor::R, 1), (SocketColor::B, 1)].into(),
+            ..Default::default()
+        }));
+        // second red fits into white socket
+        assert!(Sockets::try_from("R-W")?.satisfies(&req));
+        assert!(!Sockets::try_from("R-G")?.satisfies(&req));
+        assert!(!Sockets::try_from("R-R")?.satisfies(&SocketRequirement {
+            links: 2,
+            colors: [(SocketColor::W, 1)].into(),
+            ..Default::default()
+        }));
+
+        let req = SocketRequirement {
+            abyssal: 2,
+            ..Default::default()
+        };
+        assert!(Sockets::try_from("A A")?.satisfies(&req));
+        assert!(Sockets::try_from("R-A A")?.satisfies(&req));
+        assert!(!Sockets::try_from("A")?.satisfies(&req));
+        Ok(())
+    }
 }
@@ -16,6 +16,7 @@ use public_stash::models::PublicStashData;
 use rustler::{Atom, Encoder, Env, NifResult, NifStruct, SerdeTerm, Term};
 use serde::Serialize;
 use serde_json::{Map, Value};
+use tradeapi::poe1::query::Builder as TradeQueryBuilder;
 use uuid::Uuid;
 
 struct WrapperMap<'a>(&'a Map<String, Value>);
@@ -39,6 +40,13 @@ fn closest_item(
     Ok((atoms::ok(), result))
 }
 
+#[rustler::nif]
+fn trade_search_url(req_item: ItemWithConfig, league: &str) -> NifResult<(Atom, String)> {
+    let builder = TradeQueryBuilder::try_from(&req_item).map_err(RustError::from)?;
+    let url = builder.to_url(league).map_err(RustError::from)?;
+    Ok((atoms::ok(), url.to_string()))
+}
+
 #[rustler::nif]
 fn get_items_from_stash_data(data: &str) -> NifResult<Vec<StoredItem>> {
     let k: PublicStashData = serde_json::from_str(data).unwrap();
@@ -137,7 +145,10 @@ fn get_stored_item_type(item: StoredItem) -> NifResult<(Atom, Atom)> {
         StoredItemInfo::Gem { .. } => atoms::gem(),
         StoredItemInfo::Armor { .. } => atoms::armor(),
         StoredItemInfo::Weapon { .. } => atoms::weapon(),
-        StoredItemInfo::Jewel { .. } => atoms::jewel(),
+        StoredItemInfo::Jewel { .. }
+        | StoredItemInfo::ClusterJewel { .. }
+        | StoredItemInfo::AbyssJewel { .. }
+        | StoredItemInfo::UniqueJewel { .. } => atoms::jewel(),
         StoredItemInfo::Flask { .. } => atoms::flask(),
     };
     Ok((atoms::ok(), atom))
@@ -2,8 +2,28 @@ use crate::{decode_config, encode_config, RustError, SerdeTermJson};
 
 use super::atoms;
 use domain::build_calculation::BuildInfo;
-use pob::{build_import_pob::import_build_from_pob, Pob};
+use pob::{
+    build_import_pob::import_build_from_pob,
+    fetch::{PobFetcher, ReqwestHttp},
+    Pob,
+};
 use rustler::{Atom, Encoder, Env, NifResult, NifStruct, SerdeTerm, Term};
+use utils::DEFAULT_USER_AGENT;
+
+// resolves pastebin, pobb.in and poe.ninja share links to pob code, codes are returned as is
+#[rustler::nif(schedule = "DirtyIo")]
+fn fetch_pob(input: &str) -> NifResult<(Atom, String)> {
+    Ok(fetch_pob_impl(input)?)
+}
+
+fn fetch_pob_impl(input: &str) -> Result<(Atom, String), RustError> {
+    let fetcher = PobFetcher::new(ReqwestHttp::new(DEFAULT_USER_AGENT)?);
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let code = rt.block_on(fetcher.fetch_code(input))?;
+    Ok((atoms::ok(), code))
+}
 
 #[rustler::nif(schedule = "DirtyCpu")]
 fn extract_build_config<'a>(
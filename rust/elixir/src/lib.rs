@@ -33,6 +33,10 @@ pub enum RustError {
     ImportPob(#[from] pob::build_import_pob::ImportPobError),
     #[error("import pob from pastebin: {0}")]
     ImportPastebin(#[from] pob::PobError),
+    #[error("fetch pob: {0}")]
+    FetchPob(#[from] pob::fetch::FetchPobError),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
     #[error("convert u8 to string error: {0}")]
     Utf8(#[from] std::string::FromUtf8Error),
     #[error("convert u8 to str error: {0}")]
@@ -41,6 +45,8 @@ pub enum RustError {
     InvalidUserBuildInfo,
     #[error("invalid item")]
     InvalidItem,
+    #[error("trade query: {0}")]
+    TradeQuery(#[from] tradeapi::poe1::query::BuilderError),
 }
 
 impl From<RustError> for Error {
@@ -73,7 +73,7 @@ pub fn gen_min_max_method(input: TokenStream) -> TokenStream {
     let funcname = format_ident!("set_{}", input.name);
     let name = syn::LitStr::new(&input.name.to_string(), input.name.span());
     let tokens = quote! {
-        fn #funcname(mut self, min: Option<i32>, max: Option<i32>) -> Self {
+        pub fn #funcname(mut self, min: Option<i32>, max: Option<i32>) -> Self {
             let v = json!({
                 "min": min,
                 "max": max,
@@ -92,7 +92,25 @@ pub fn gen_option_method(input: TokenStream) -> TokenStream {
     let funcname = format_ident!("set_{}", input.name);
     let name = syn::LitStr::new(&input.name.to_string(), input.name.span());
     let tokens = quote! {
-        fn #funcname(mut self, val: bool) -> Self {
+        pub fn #funcname(mut self, val: bool) -> Self {
+            let v = json!({
+                "option": val,
+            });
+            let m = self.filters.entry(#name.to_string()).or_default();
+            *m = v;
+            self
+        }
+    };
+    tokens.into()
+}
+
+#[proc_macro]
+pub fn gen_select_method(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as GenSetMethod);
+    let funcname = format_ident!("set_{}", input.name);
+    let name = syn::LitStr::new(&input.name.to_string(), input.name.span());
+    let tokens = quote! {
+        pub fn #funcname(mut self, val: &str) -> Self {
             let v = json!({
                 "option": val,
             });
@@ -1,7 +1,10 @@
 use crate::{ItemSet, Pob, SkillSet};
 
+use std::collections::HashMap;
+
 use domain::{
     build_calculation::{
+        item_config::{ItemConfig, ItemConfigOption},
         stored_item::{StoredItem, StoredItemError},
         BuildInfo, BuildItemsWithConfig, ItemWithConfig,
     },
@@ -27,29 +30,69 @@ pub fn import_build_from_pob<T: AsRef<str>>(
     let doc = pob.as_document()?;
     let itemset = doc.get_itemset(itemset.as_ref())?;
     let skillset = doc
-        .get_skillsets()
-        .iter()
+        .get_skillsets()?
+        .into_iter()
         .find(|s| s.title() == skillset.as_ref())
-        .unwrap()
-        .clone();
+        .ok_or_else(|| crate::PobError::SkillsetNameNotFound(skillset.as_ref().into()))?;
     import(itemset, skillset)
 }
 
 pub fn import_build_from_pob_first_itemset(pob: &Pob) -> Result<BuildInfo, ImportPobError> {
     let doc = pob.as_document()?;
     let itemset = doc.get_first_itemset()?;
-    let skillset = doc.get_skillsets().first().unwrap().clone();
+    let skillset = doc
+        .get_skillsets()?
+        .into_iter()
+        .next()
+        .ok_or(crate::PobError::SkillsetNotFound(0))?;
     import(itemset, skillset)
 }
 
 fn fill(prov_item: &mut Option<ItemWithConfig>, it: &Item) -> Result<(), ImportPobError> {
+    let item = StoredItem::try_from(it.clone())?;
+    // pob names legacy variants after the patch they were changed in
+    let legacy = it.variant.as_ref().is_some_and(|v| v.starts_with("Pre "));
+    let option = legacy.then(|| ItemConfigOption::UniqueRolls {
+        kind: item.unique_kind(),
+        legacy,
+        mods: HashMap::new(),
+    });
     *prov_item = Some(ItemWithConfig {
-        item: StoredItem::try_from(it.clone())?,
-        ..Default::default()
+        item,
+        config: ItemConfig {
+            option,
+            ..Default::default()
+        },
     });
     Ok(())
 }
 
+const SOCKETED_SLOTS: [&str; 7] = [
+    "Helmet",
+    "Body Armour",
+    "Gloves",
+    "Boots",
+    "Weapon 1",
+    "Weapon 2",
+    "Belt",
+];
+
+fn slot_item<'a>(
+    builditems: &'a mut BuildItemsWithConfig,
+    slot: &str,
+) -> Option<&'a mut Option<ItemWithConfig>> {
+    Some(match slot {
+        "Helmet" => &mut builditems.helmet,
+        "Body Armour" => &mut builditems.body,
+        "Gloves" => &mut builditems.gloves,
+        "Boots" => &mut builditems.boots,
+        "Weapon 1" => &mut builditems.weapon1,
+        "Weapon 2" => &mut builditems.weapon2,
+        "Belt" => &mut builditems.belt,
+        _ => return None,
+    })
+}
+
 fn import(itemset: ItemSet, skillset: SkillSet) -> Result<BuildInfo, ImportPobError> {
     let mut builditems = BuildItemsWithConfig::default();
     for it in itemset.items() {
@@ -66,7 +109,7 @@ fn import(itemset: ItemSet, skillset: SkillSet) -> Result<BuildInfo, ImportPobEr
             Subcategory::Belt => fill(&mut builditems.belt, it)?,
             Subcategory::Gloves => fill(&mut builditems.gloves, it)?,
             Subcategory::Boots => fill(&mut builditems.boots, it)?,
-            Subcategory::Shield => fill(&mut builditems.weapon2, it)?,
+            Subcategory::Shield | Subcategory::Focus => fill(&mut builditems.weapon2, it)?,
             Subcategory::Weapon => {
                 if builditems.weapon1.is_none() {
                     fill(&mut builditems.weapon1, it)?
@@ -93,6 +136,24 @@ fn import(itemset: ItemSet, skillset: SkillSet) -> Result<BuildInfo, ImportPobEr
         }
     }
 
+    for group in skillset.groups().iter().filter(|g| g.enabled()) {
+        let slot_item = group.slot().and_then(|s| slot_item(&mut builditems, s));
+        let (Some(Some(ic)), Some(req)) = (slot_item, group.socket_requirement()) else {
+            continue;
+        };
+        // several groups can share an item, the biggest one decides links
+        if ic.config.sockets.as_ref().is_none_or(|s| s.links < req.links) {
+            ic.config.sockets = Some(req);
+        }
+    }
+
+    for slot in SOCKETED_SLOTS {
+        let abyssal = itemset.abyssal_sockets(slot);
+        if let Some(Some(ic)) = slot_item(&mut builditems, slot).filter(|_| abyssal > 0) {
+            ic.config.sockets.get_or_insert_default().abyssal = abyssal;
+        }
+    }
+
     builditems.gems = skillset
         .gems()
         .into_iter()
@@ -111,12 +172,21 @@ fn import(itemset: ItemSet, skillset: SkillSet) -> Result<BuildInfo, ImportPobEr
 
 #[cfg(test)]
 mod tests {
-    use domain::build_calculation::stored_item::ItemInfo;
+    use domain::{
+        build_calculation::{
+            comparison::Comparator, item_config::ItemConfigOption, stored_item::ItemInfo,
+        },
+        item::{
+            types::{Category, Rarity, SocketColor, Subcategory},
+            Item,
+        },
+    };
 
-    use super::import_build_from_pob_first_itemset;
+    use super::{fill, import_build_from_pob_first_itemset};
     use crate::Pob;
 
     const POB: &str = include_str!("pob.xml");
+    const POB_POE2: &str = include_str!("pob_poe2.xml");
 
     #[test]
     fn check_import_items() -> anyhow::Result<()> {
@@ -143,4 +213,95 @@ mod tests {
         println!("{:?}", buildinfo.provided.weapon1);
         Ok(())
     }
+
+    #[test]
+    fn check_import_poe2() -> anyhow::Result<()> {
+        let pob = Pob::new(POB_POE2);
+        let buildinfo = import_build_from_pob_first_itemset(&pob)?;
+        let provided = buildinfo.provided;
+        assert_eq!(provided.weapon1.unwrap().item.basetype, "Withered Wand");
+        assert_eq!(provided.weapon2.unwrap().item.basetype, "Twig Focus");
+        assert_eq!(provided.gloves.unwrap().item.basetype, "Stocky Mitts");
+        assert_eq!(provided.flasks.len(), 1);
+        assert_eq!(provided.gems.len(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn build_search_checks_sockets() -> anyhow::Result<()> {
+        let pob = Pob::new(POB);
+        let buildinfo = import_build_from_pob_first_itemset(&pob)?;
+        let body = buildinfo.provided.body.unwrap();
+        let req = body.config.sockets.as_ref().unwrap();
+        assert_eq!(req.links, 6);
+        assert_eq!(req.colors[&SocketColor::G], 5);
+        assert_eq!(req.colors[&SocketColor::B], 1);
+
+        let candidate = |id: &str, sockets: &str| {
+            let mut it = body.item.clone();
+            it.id = id.to_string();
+            it.sockets = Some(sockets.to_string());
+            it
+        };
+        // provided body armour is 5-linked and can't hold the whole group
+        let found = Comparator::closest_item(
+            &body,
+            vec![
+                body.item.clone(),
+                candidate("five", "G-G-G-G-B G"),
+                candidate("offcolor", "G-G-G-G-B-B"),
+                candidate("six", "G-G-B-G-G-G"),
+            ],
+        );
+        assert_eq!(found.unwrap().id, "six");
+        Ok(())
+    }
+
+    #[test]
+    fn build_search_checks_abyssal_sockets() -> anyhow::Result<()> {
+        let pob = Pob::new(POB);
+        let buildinfo = import_build_from_pob_first_itemset(&pob)?;
+        let belt = buildinfo.provided.belt.unwrap();
+        let req = belt.config.sockets.as_ref().unwrap();
+        assert_eq!(req.abyssal, 1);
+        assert_eq!(req.links, 0);
+
+        let candidate = |id: &str, sockets: Option<&str>| {
+            let mut it = belt.item.clone();
+            it.id = id.to_string();
+            it.sockets = sockets.map(|s| s.to_string());
+            it
+        };
+        let found = Comparator::closest_item(
+            &belt,
+            vec![candidate("none", None), candidate("abyssal", Some("A"))],
+        );
+        assert_eq!(found.unwrap().id, "abyssal");
+        Ok(())
+    }
+
+    #[test]
+    fn import_legacy_variant() -> anyhow::Result<()> {
+        let mut it = Item {
+            name: "Headhunter".to_string(),
+            base_type: "Leather Belt".to_string(),
+            rarity: Rarity::Unique,
+            category: Category::Accessories,
+            subcategories: Subcategory::Belt,
+            variant: Some("Current".to_string()),
+            ..Default::default()
+        };
+        let mut ic = None;
+        fill(&mut ic, &it)?;
+        assert_eq!(ic.unwrap().config.option, None);
+
+        it.variant = Some("Pre 3.16.0".to_string());
+        let mut ic = None;
+        fill(&mut ic, &it)?;
+        assert!(matches!(
+            ic.unwrap().config.option,
+            Some(ItemConfigOption::UniqueRolls { legacy: true, .. })
+        ));
+        Ok(())
+    }
 }
@@ -0,0 +1,261 @@
+use crate::{Pob, PobError};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum FetchPobError {
+    #[error("unsupported pob link: {0}")]
+    UnknownSource(String),
+    #[error("malformed pob link: {0}")]
+    MalformedLink(String),
+    #[error("status code: {0}")]
+    StatusCode(u16),
+    #[error("reqwest error: {0}")]
+    Reqwest(#[from] reqwest::Error),
+    #[error("pob error: {0}")]
+    Pob(#[from] PobError),
+}
+
+#[async_trait::async_trait]
+pub trait HttpGet: Send + Sync {
+    async fn get(&self, url: &str) -> Result<String, FetchPobError>;
+}
+
+pub struct ReqwestHttp {
+    client: reqwest::Client,
+}
+
+impl ReqwestHttp {
+    pub fn new(user_agent: &str) -> Result<ReqwestHttp, FetchPobError> {
+        let client = reqwest::ClientBuilder::new()
+            .user_agent(user_agent)
+            .build()?;
+        Ok(ReqwestHttp { client })
+    }
+}
+
+#[async_trait::async_trait]
+impl HttpGet for ReqwestHttp {
+    async fn get(&self, url: &str) -> Result<String, FetchPobError> {
+        let resp = self.client.get(url).send().await?;
+        let st = resp.status();
+        if !st.is_success() {
+            return Err(FetchPobError::StatusCode(st.as_u16()));
+        }
+        Ok(resp.text().await?)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum PobSource {
+    Code(String),
+    Pastebin(String),
+    Pobbin(String),
+    PoeNinja(String),
+}
+
+impl PobSource {
+    pub fn parse(input: &str) -> Result<PobSource, FetchPobError> {
+        let input = input.trim();
+        let Some((host, path)) = split_link(input) else {
+            return Ok(PobSource::Code(input.to_string()));
+        };
+
+        let segments = path
+            .split(['?', '#'])
+            .next()
+            .unwrap_or_default()
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        let malformed = || FetchPobError::MalformedLink(input.to_string());
+
+        match host {
+            "pastebin.com" => match &segments[..] {
+                ["raw", id] | [id] => Ok(PobSource::Pastebin(id.to_string())),
+                _ => Err(malformed()),
+            },
+            "pobb.in" => match &segments[..] {
+                [id] | [id, "raw"] => Ok(PobSource::Pobbin(id.to_string())),
+                _ => Err(malformed()),
+            },
+            "poe.ninja" => match &segments[..] {
+                ["pob", "raw", id] | ["pob", id] => Ok(PobSource::PoeNinja(id.to_string())),
+                _ => Err(malformed()),
+            },
+            h => Err(FetchPobError::UnknownSource(h.to_string())),
+        }
+    }
+}
+
+// returns host and path for anything that looks like a link, None for raw pob codes
+fn split_link(input: &str) -> Option<(&str, &str)> {
+    let rest = input
+        .strip_prefix("https://")
+        .or_else(|| input.strip_prefix("http://"))
+        .or_else(|| {
+            // links are often pasted without scheme, codes never contain dots
+            input.contains('.').then_some(input)
+        })?;
+    let rest = rest.strip_prefix("www.").unwrap_or(rest);
+    Some(rest.split_once('/').unwrap_or((rest, "")))
+}
+
+#[derive(Debug, Clone)]
+pub struct PobSites {
+    pub pastebin: String,
+    pub pobbin: String,
+    pub poe_ninja: String,
+}
+
+impl Default for PobSites {
+    fn default() -> Self {
+        PobSites {
+            pastebin: "https://pastebin.com".to_string(),
+            pobbin: "https://pobb.in".to_string(),
+            poe_ninja: "https://poe.ninja".to_string(),
+        }
+    }
+}
+
+pub struct PobFetcher<H> {
+    http: H,
+    sites: PobSites,
+}
+
+impl<H: HttpGet> PobFetcher<H> {
+    pub fn new(http: H) -> PobFetcher<H> {
+        PobFetcher {
+            http,
+            sites: PobSites::default(),
+        }
+    }
+
+    pub fn with_sites(mut self, sites: PobSites) -> Self {
+        self.sites = sites;
+        self
+    }
+
+    pub async fn fetch_code(&self, input: &str) -> Result<String, FetchPobError> {
+        let url = match PobSource::parse(input)? {
+            PobSource::Code(code) => return Ok(code),
+            PobSource::Pastebin(id) => format!("{}/raw/{}", self.sites.pastebin, id),
+            PobSource::Pobbin(id) => format!("{}/{}/raw", self.sites.pobbin, id),
+            PobSource::PoeNinja(id) => format!("{}/pob/raw/{}", self.sites.poe_ninja, id),
+        };
+        Ok(self.http.get(&url).await?.trim().to_string())
+    }
+
+    pub async fn fetch(&self, input: &str) -> Result<Pob, FetchPobError> {
+        let code = self.fetch_code(input).await?;
+        Ok(Pob::from_pastebin_data(code)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    const TESTPOB: &str = include_str!("pob.txt");
+
+    #[test]
+    fn parse_sources() -> anyhow::Result<()> {
+        let cases = [
+            (
+                "https://pastebin.com/raw/AbCd1234",
+                PobSource::Pastebin("AbCd1234".into()),
+            ),
+            (
+                "pastebin.com/AbCd1234",
+                PobSource::Pastebin("AbCd1234".into()),
+            ),
+            ("https://pobb.in/xYz_09", PobSource::Pobbin("xYz_09".into())),
+            (
+                "https://pobb.in/xYz_09/raw",
+                PobSource::Pobbin("xYz_09".into()),
+            ),
+            (
+                "https://poe.ninja/pob/1a2b",
+                PobSource::PoeNinja("1a2b".into()),
+            ),
+            (
+                "http://www.poe.ninja/pob/raw/1a2b?x=1",
+                PobSource::PoeNinja("1a2b".into()),
+            ),
+            (
+                "  eNrtnetz27iS6D8f  ",
+                PobSource::Code("eNrtnetz27iS6D8f".into()),
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(PobSource::parse(input)?, expected, "input: {}", input);
+        }
+
+        assert!(matches!(
+            PobSource::parse("https://example.com/abc"),
+            Err(FetchPobError::UnknownSource(_))
+        ));
+        assert!(matches!(
+            PobSource::parse("https://poe.ninja/builds/abc"),
+            Err(FetchPobError::MalformedLink(_))
+        ));
+        Ok(())
+    }
+
+    // serves TESTPOB for raw paths of every supported site, 404 for anything else
+    async fn fixture_server() -> anyhow::Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            while let Ok((mut sock, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 4096];
+                let n = sock.read(&mut buf).await.unwrap_or_default();
+                let req = String::from_utf8_lossy(&buf[..n]);
+                let path = req.split(' ').nth(1).unwrap_or_default();
+                let resp = match path {
+                    "/raw/paste" | "/share/raw" | "/pob/raw/ninja" => format!(
+                        "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        TESTPOB.len(),
+                        TESTPOB
+                    ),
+                    _ => "HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                        .to_string(),
+                };
+                let _ = sock.write_all(resp.as_bytes()).await;
+            }
+        });
+        Ok(format!("http://{}", addr))
+    }
+
+    #[tokio::test]
+    async fn fetch_from_fixture_server() -> anyhow::Result<()> {
+        let base = fixture_server().await?;
+        let fetcher = PobFetcher::new(ReqwestHttp::new("test")?).with_sites(PobSites {
+            pastebin: base.clone(),
+            pobbin: base.clone(),
+            poe_ninja: base,
+        });
+
+        let expected = Pob::from_pastebin_data(TESTPOB.to_owned())?.get_original();
+        for link in [
+            "https://pastebin.com/paste",
+            "https://pobb.in/share",
+            "https://poe.ninja/pob/ninja",
+        ] {
+            let pob = fetcher.fetch(link).await?;
+            assert_eq!(pob.get_original(), expected);
+        }
+
+        let pob = fetcher.fetch(TESTPOB).await?;
+        assert_eq!(pob.get_original(), expected);
+
+        assert!(matches!(
+            fetcher.fetch("https://pobb.in/missing").await,
+            Err(FetchPobError::StatusCode(404))
+        ));
+        Ok(())
+    }
+}
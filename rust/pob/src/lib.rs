@@ -1,5 +1,7 @@
 #[cfg(feature = "parsing")]
 pub mod build_import_pob;
+#[cfg(feature = "fetch")]
+pub mod fetch;
 #[cfg(feature = "parsing")]
 mod parser;
 mod pob;
@@ -1,7 +1,10 @@
 use domain::{
-    data::{BaseItems, BASE_TYPES},
+    data::GameVersion,
     item::{
-        types::{Category, Mod, ModType, Property, Rarity, Sockets, Subcategory, TypeError},
+        types::{
+            Category, Mod, ModType, Property, Rarity, Sockets, Subcategory, SubcategoryError,
+            TypeError,
+        },
         Item,
     },
 };
@@ -12,7 +15,7 @@ use nom::{
         alpha1, alphanumeric1, char, digit1, line_ending, multispace0, not_line_ending,
     },
     combinator::{cut, map, map_res, not},
-    error::{context, ContextError, FromExternalError, ParseError},
+    error::{context, ContextError, ErrorKind, FromExternalError, ParseError},
     multi::{length_count, many0},
     sequence::{delimited, pair, preceded, tuple},
     IResult,
@@ -20,7 +23,6 @@ use nom::{
 
 use std::{
     num::{ParseFloatError, ParseIntError},
-    ops::Deref,
     str::FromStr,
 };
 
@@ -32,6 +34,8 @@ pub enum PobParseError {
     UnknownCategory(String),
     #[error("unknown category type: {0}")]
     CategoryType(#[from] TypeError),
+    #[error("unknown subcategory: {0}")]
+    Subcategory(#[from] SubcategoryError),
     #[error("it is not a range")]
     NotRange,
     #[error("error parsing range: {0}")]
@@ -47,6 +51,8 @@ pub(crate) enum Affix<'a> {
     Range { range: &'a str },
     Crafted,
     Tags,
+    // 1-based indexes of variants which have the mod
+    Variant(Vec<usize>),
 }
 
 impl<'a> Affix<'a> {
@@ -76,6 +82,8 @@ pub(crate) enum ItemValue<'a> {
     Implicits(Vec<(&'a str, Vec<Affix<'a>>)>),
     Quality(i32),
     Sockets(&'a str),
+    Variant(&'a str),
+    SelectedVariant(usize),
     UnknownString(&'a str),
 }
 
@@ -85,63 +93,67 @@ fn rarity<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     context("rarity", preceded(tag("Rarity: "), cut(alpha1)))(i)
 }
 
-fn basetype_map<'a>(name: &'a str, basetype: &'a str) -> Result<ItemValue<'a>, PobParseError> {
-    if BaseItems::contains_name(basetype) {
+fn basetype_map<'a>(
+    name: &'a str,
+    basetype: &'a str,
+    version: GameVersion,
+) -> Result<ItemValue<'a>, PobParseError> {
+    if version.contains_base_name(basetype) {
         return Ok(ItemValue::BaseType {
             name,
             base: basetype,
         });
     }
-    for b in BASE_TYPES.deref() {
-        if basetype.contains(b) {
-            return Ok(ItemValue::BaseType {
-                name,
-                base: b.as_str(),
-            });
-        }
+    if let Some(b) = longest_base_in(basetype, version) {
+        return Ok(ItemValue::BaseType { name, base: b });
     }
     Err(PobParseError::UnknownCategory(basetype.to_string()))
 }
 
-fn name<'a, E>(i: &'a str) -> IResult<&'a str, ItemValue<'a>, E>
+// some basetypes are part of others (poe2 "Ruby" jewel and "Ruby Ring")
+fn longest_base_in(text: &str, version: GameVersion) -> Option<&'static str> {
+    version
+        .base_types()
+        .iter()
+        .filter(|b| text.contains(b.as_str()))
+        .max_by_key(|b| b.len())
+        .map(|b| b.as_str())
+}
+
+fn name<'a, E>(i: &'a str, version: GameVersion) -> IResult<&'a str, ItemValue<'a>, E>
 where
     E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, PobParseError>,
 {
-    context("name", alt((name_normal_rare, name_magic)))(i)
+    context(
+        "name",
+        alt((|i| name_normal_rare(i, version), |i| name_magic(i, version))),
+    )(i)
 }
 
-fn name_normal_rare<'a, E>(i: &'a str) -> IResult<&'a str, ItemValue, E>
+fn name_normal_rare<'a, E>(i: &'a str, version: GameVersion) -> IResult<&'a str, ItemValue, E>
 where
     E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, PobParseError>,
 {
     let (i, name) = context("name_normal_rare", cut(not_line_ending))(i)?;
 
-    if BaseItems::contains_name(name) {
+    if version.contains_base_name(name) {
         Ok((i, ItemValue::BaseType { name, base: name }))
     } else {
         let prs = map_res(preceded(multispace0, cut(not_line_ending)), |basetype| {
-            basetype_map(name, basetype)
+            basetype_map(name, basetype, version)
         });
         context("basetype", prs)(i)
     }
 }
 
-fn name_magic<'a, E>(i: &'a str) -> IResult<&'a str, ItemValue, E>
+fn name_magic<'a, E>(i: &'a str, version: GameVersion) -> IResult<&'a str, ItemValue, E>
 where
     E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, PobParseError>,
 {
     let (i, name) = context("name_magic", cut(not_line_ending))(i)?;
 
-    for b in BASE_TYPES.deref() {
-        if name.contains(b) {
-            return Ok((
-                i,
-                ItemValue::BaseType {
-                    name,
-                    base: b.as_str(),
-                },
-            ));
-        }
+    if let Some(b) = longest_base_in(name, version) {
+        return Ok((i, ItemValue::BaseType { name, base: b }));
     }
     let e = E::from_error_kind(i, nom::error::ErrorKind::AlphaNumeric);
     let e = E::add_context(i, "name_magic2", e);
@@ -163,6 +175,25 @@ fn sockets<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     context("sockets", preceded(tag("Sockets: "), cut(not_line_ending)))(i)
 }
 
+fn variant<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    i: &'a str,
+) -> IResult<&'a str, &'a str, E> {
+    context("variant", preceded(tag("Variant: "), cut(not_line_ending)))(i)
+}
+
+fn selected_variant<'a, E>(i: &'a str) -> IResult<&'a str, usize, E>
+where
+    E: ParseError<&'a str> + FromExternalError<&'a str, PobParseError> + ContextError<&'a str>,
+{
+    context(
+        "selected_variant",
+        map_res(
+            preceded(tag("Selected Variant: "), cut(digit1)),
+            |out: &str| Ok(usize::from_str(out)?),
+        ),
+    )(i)
+}
+
 fn quality<
     'a,
     E: ParseError<&'a str> + FromExternalError<&'a str, PobParseError> + ContextError<&'a str>,
@@ -247,6 +278,17 @@ fn affix_crafted<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     context("affix_crafted", map(tag("{crafted}"), |_| Affix::Crafted))(i)
 }
 
+fn affix_variant<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    i: &'a str,
+) -> IResult<&'a str, Affix<'a>, E> {
+    context(
+        "affix_variant",
+        map(delimited(tag("{variant:"), is_not("}"), char('}')), |v: &str| {
+            Affix::Variant(v.split(',').filter_map(|n| n.parse().ok()).collect())
+        }),
+    )(i)
+}
+
 fn affix_tags<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
 ) -> IResult<&'a str, Affix<'a>, E> {
@@ -263,7 +305,7 @@ fn affix_prefixes<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
 ) -> IResult<&'a str, Vec<Affix<'a>>, E> {
     context(
         "affix_prefixes",
-        many0(alt((affix_range, affix_crafted, affix_tags))),
+        many0(alt((affix_range, affix_crafted, affix_tags, affix_variant))),
     )(i)
 }
 
@@ -299,6 +341,8 @@ fn item_value<
             map(implicits, ItemValue::Implicits),
             map(quality, ItemValue::Quality),
             map(sockets, ItemValue::Sockets),
+            map(variant, ItemValue::Variant),
+            map(selected_variant, ItemValue::SelectedVariant),
             map(|i| affix(i, ModType::Explicit), ItemValue::Affix),
             map(not_line_ending, ItemValue::UnknownString),
         )),
@@ -310,9 +354,10 @@ fn root<
     E: ParseError<&'a str> + FromExternalError<&'a str, PobParseError> + ContextError<&'a str>,
 >(
     i: &'a str,
+    version: GameVersion,
 ) -> IResult<&'a str, Vec<ItemValue>, E> {
     let (i, rarity) = delimited(multispace0, rarity, line_ending)(i)?;
-    let (i, basetype) = delimited(multispace0, name, line_ending)(i)?;
+    let (i, basetype) = delimited(multispace0, |i| name(i, version), line_ending)(i)?;
     let (i, mut vals) = many0(delimited(multispace0, item_value, line_ending))(i)?;
     let (i, end_val) = item_value(i)?;
     let mut values = vec![ItemValue::Rarity(rarity), basetype];
@@ -321,26 +366,40 @@ fn root<
     Ok((i, values))
 }
 
-pub(crate) fn parse_pob_item<'a, E>(i: &'a str) -> IResult<&'a str, ParsedItem, E>
+pub(crate) fn parse_pob_item<'a, E>(
+    i: &'a str,
+    version: GameVersion,
+) -> IResult<&'a str, ParsedItem, E>
 where
     E: ParseError<&'a str> + FromExternalError<&'a str, PobParseError> + ContextError<&'a str>,
 {
-    let (i, items) = root(i)?;
-    let mut item = Item::default();
+    let (rest, values) = root(i, version)?;
+    let item = build_item(values, version)
+        .map_err(|e| nom::Err::Failure(E::from_external_error(i, ErrorKind::MapRes, e)))?;
+    Ok((rest, ParsedItem { item }))
+}
+
+fn build_item(values: Vec<ItemValue>, version: GameVersion) -> Result<Item, PobParseError> {
+    let mut item = Item {
+        game_version: version,
+        ..Default::default()
+    };
     let mut mods = vec![];
+    let mut variants = vec![];
+    let mut selected_variant = None;
 
-    for val in items {
+    for val in values {
         match val {
-            ItemValue::Rarity(r) => item.rarity = Rarity::try_from(r).unwrap(),
+            ItemValue::Rarity(r) => item.rarity = Rarity::try_from(r)?,
             ItemValue::BaseType { base, name } => {
-                item.category = Category::get_from_basetype(base).unwrap();
-                item.subcategories = Subcategory::get_from_basetype(base).unwrap();
+                item.category = Category::get_from_versioned_basetype(version, base)?;
+                item.subcategories = Subcategory::get_from_versioned_basetype(version, base)?;
                 item.name = name.to_string();
                 item.base_type = base.to_string();
             }
             ItemValue::UniqueId(id) => item.id = id.to_string(),
             ItemValue::ItemLevel(il) => item.item_lvl = Some(il),
-            ItemValue::Sockets(s) => item.sockets = Sockets::try_from(s).unwrap(),
+            ItemValue::Sockets(s) => item.sockets = Sockets::try_from(s)?,
             ItemValue::Quality(q) => item.properties.push(Property {
                 name: "Quality".into(),
                 value: Some(format!("+{}%", q)),
@@ -352,31 +411,44 @@ where
                     .map(|im| (im.0, im.1, ModType::Implicit)),
             ),
             ItemValue::Affix(e) => mods.push((e.0, e.2, e.1)),
+            ItemValue::Variant(v) => variants.push(v),
+            ItemValue::SelectedVariant(v) => selected_variant = Some(v),
             _ => {}
         };
     }
 
+    // pob keeps mods of every variant, only selected one is on the item
+    if let Some(selected) = selected_variant {
+        mods.retain(|(_, affixes, _)| {
+            affixes.iter().all(|a| match a {
+                Affix::Variant(v) => v.contains(&selected),
+                _ => true,
+            })
+        });
+        item.variant = variants
+            .get(selected.wrapping_sub(1))
+            .map(|v| v.to_string());
+    }
+
     let mut known_mods = vec![];
     let mut unknown_mods = vec![];
-    mods.into_iter().for_each(|(val, affixes, modtype)| {
-        let m = if let Some(range) = affixes.iter().find(|a| a.is_range()) {
-            let range = range
-                .get_range()
-                .expect("tried to parse range on range affix");
-            Mod::try_by_range_stat(val, range, modtype)
-        } else {
-            Mod::try_by_stat(val, modtype)
+    for (val, affixes, modtype) in mods {
+        let m = match affixes.iter().find(|a| a.is_range()) {
+            Some(range) => {
+                Mod::try_by_versioned_range_stat(version, val, range.get_range()?, modtype)
+            }
+            None => Mod::try_by_versioned_stat(version, val, modtype),
         };
         match m {
             Ok(mm) => known_mods.push(mm),
             Err(_) => unknown_mods.push(val.to_string()),
         }
-    });
+    }
 
     item.mods = known_mods;
     item.unknown_mods = unknown_mods;
 
-    Ok((i, ParsedItem { item }))
+    Ok(item)
 }
 
 #[cfg(test)]
@@ -395,11 +467,19 @@ mod test {
                 Ok(())
             }
         };
+        ($name:ident, $f:ident($($arg:expr),+), $data:expr, $res:expr) => {
+            #[test]
+            fn $name() -> anyhow::Result<()> {
+                let (_, res) = $f::<VerboseError<&str>>($data, $($arg),+)?;
+                assert_eq!(res, $res);
+                Ok(())
+            }
+        };
     }
 
     gen_test!(
         name_check,
-        name,
+        name(GameVersion::Poe1),
         "Loath Cut\nSmall Cluster Jewel",
         ItemValue::BaseType {
             name: "Loath Cut",
@@ -408,7 +488,7 @@ mod test {
     );
     gen_test!(
         name_check2,
-        name,
+        name(GameVersion::Poe1),
         "Behemot Tutu\nSlink Boots",
         ItemValue::BaseType {
             name: "Behemot Tutu",
@@ -417,7 +497,7 @@ mod test {
     );
     gen_test!(
         name_check3,
-        name,
+        name(GameVersion::Poe1),
         "Divine Life Flask",
         ItemValue::BaseType {
             name: "Divine Life Flask",
@@ -426,7 +506,7 @@ mod test {
     );
     gen_test!(
         name_check4,
-        name,
+        name(GameVersion::Poe1),
         "Iron Commander\nDeath Bow",
         ItemValue::BaseType {
             name: "Iron Commander",
@@ -435,7 +515,7 @@ mod test {
     );
     gen_test!(
         name_custom_check,
-        name,
+        name(GameVersion::Poe1),
         "Lategame Boots\nTwo-Toned Boots (Armour/EnergyShield)",
         ItemValue::BaseType {
             name: "Lategame Boots",
@@ -444,7 +524,7 @@ mod test {
     );
     gen_test!(
         name_check_stygian,
-        name,
+        name(GameVersion::Poe1),
         "Loath Cut\nStygian Vise",
         ItemValue::BaseType {
             name: "Loath Cut",
@@ -453,7 +533,7 @@ mod test {
     );
     gen_test!(
         name_normal_rare_custom,
-        name_normal_rare,
+        name_normal_rare(GameVersion::Poe1),
         "Lategame Boots\nTwo-Toned Boots (Armour/EnergyShield)",
         ItemValue::BaseType {
             name: "Lategame Boots",
@@ -462,7 +542,7 @@ mod test {
     );
     gen_test!(
         name_check_magic,
-        name,
+        name(GameVersion::Poe1),
         "Bubbling Divine Life Flask of Staunching",
         ItemValue::BaseType {
             name: "Bubbling Divine Life Flask of Staunching",
@@ -471,7 +551,7 @@ mod test {
     );
     gen_test!(
         name_magic_check,
-        name_magic,
+        name_magic(GameVersion::Poe1),
         "Bubbling Divine Life Flask of Staunching",
         ItemValue::BaseType {
             name: "Bubbling Divine Life Flask of Staunching",
@@ -480,7 +560,7 @@ mod test {
     );
     gen_test!(
         name_magic_check2,
-        name_magic,
+        name_magic(GameVersion::Poe1),
         "Divine Life Flask of Staunching",
         ItemValue::BaseType {
             name: "Divine Life Flask of Staunching",
@@ -489,7 +569,7 @@ mod test {
     );
     gen_test!(
         name_magic_check3,
-        name_magic,
+        name_magic(GameVersion::Poe1),
         "Bubbling Divine Life Flask",
         ItemValue::BaseType {
             name: "Bubbling Divine Life Flask",
@@ -542,6 +622,35 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn variant_pob_item() -> anyhow::Result<()> {
+        let text = r#"Rarity: UNIQUE
+Thread of Hope
+Crimson Jewel
+Variant: Small Ring
+Variant: Very Large Ring
+Selected Variant: 2
+Crimson Jewel
+Radius: Variable
+Implicits: 0
+{variant:1}Only affects Passives in Small Ring
+{variant:2}Only affects Passives in Very Large Ring
+Passives in Radius can be Allocated without being connected to your tree
+{range:1}-(20-10)% to all Elemental Resistances"#;
+        let (_, item) = parse_pob_item::<VerboseError<&str>>(text, GameVersion::Poe1)?;
+        assert_eq!(item.item.variant.as_deref(), Some("Very Large Ring"));
+        let texts = item
+            .item
+            .mods
+            .iter()
+            .map(|m| m.text.as_str())
+            .chain(item.item.unknown_mods.iter().map(|m| m.as_str()))
+            .collect::<Vec<_>>();
+        assert!(texts.contains(&"Only affects Passives in Very Large Ring"));
+        assert!(!texts.iter().any(|t| t.contains("Small Ring")));
+        Ok(())
+    }
+
     #[test]
     fn implicits_check() -> Result<(), anyhow::Error> {
         let i = "Implicits: 1\nAdds 2 Passive Skills\nAdds 3 Passive Skills";
@@ -571,7 +680,7 @@ Added Small Passive Skills also grant: +5 to Maximum Energy Shield
 Added Small Passive Skills also grant: +5 to Strength
 1 Added Passive Skill is Elegant Form"#;
 
-        let (_, item) = parse_pob_item::<VerboseError<&str>>(item)?;
+        let (_, item) = parse_pob_item::<VerboseError<&str>>(item, GameVersion::Poe1)?;
         assert_eq!(item.item.rarity, Rarity::Rare);
         assert_eq!(item.item.name, "Loath Cut");
         assert_eq!(item.item.base_type, "Small Cluster Jewel");
@@ -626,8 +735,50 @@ Implicits: 0
 You have Tailwind if you have dealt a Critical Strike Recently
 {crafted}24% reduced Effect of Chill and Shock on you
 "#;
-        let (_, item) = parse_pob_item::<VerboseError<&str>>(text)?;
+        let (_, item) = parse_pob_item::<VerboseError<&str>>(text, GameVersion::Poe1)?;
+        assert_eq!(item.item.category, Category::Armour);
+        Ok(())
+    }
+
+    gen_test!(
+        name_magic_poe2_check,
+        name(GameVersion::Poe2),
+        "Lesser Mana Flask of the Verdant",
+        ItemValue::BaseType {
+            name: "Lesser Mana Flask of the Verdant",
+            base: "Lesser Mana Flask",
+        }
+    );
+
+    #[test]
+    fn poe2_pob_item() -> anyhow::Result<()> {
+        let text = r#"Rarity: RARE
+Doom Grip
+Stocky Mitts
+Unique ID: 5d0c9cc6d1e6f2d1a2f1f5b0e3f0a1b2c3d4e5f60718293a4b5c6d7e8f901234
+Item Level: 80
+Quality: 20
+Sockets: S S
+LevelReq: 65
+Implicits: 0
++50 to maximum Life
++30% to Fire Resistance"#;
+        let (_, item) = parse_pob_item::<VerboseError<&str>>(text, GameVersion::Poe2)?;
+        assert_eq!(item.item.game_version, GameVersion::Poe2);
+        assert_eq!(item.item.base_type, "Stocky Mitts");
         assert_eq!(item.item.category, Category::Armour);
+        assert_eq!(item.item.subcategories, Subcategory::Gloves);
+        assert_eq!(item.item.sockets.max_links(), 1);
         Ok(())
     }
+
+    #[test]
+    fn poe2_unknown_base() {
+        let text = r#"Rarity: RARE
+Doom Stride
+Slink Boots
+Implicits: 0
++50 to maximum Life"#;
+        assert!(parse_pob_item::<VerboseError<&str>>(text, GameVersion::Poe2).is_err());
+    }
 }
@@ -2,16 +2,16 @@
 use crate::parser::{parse_pob_item, ParsedItem};
 
 use base64::{decode_config, URL_SAFE};
-use domain::data::BaseItems;
+use domain::data::{BaseItems, GameVersion};
 use domain::item::{
-    types::{Category, Property, Subcategory, TypeError},
+    types::{Category, Property, SocketColor, SocketRequirement, Subcategory, TypeError},
     Item,
 };
 use flate2::read::ZlibDecoder;
 use nom::error::VerboseError;
 use roxmltree::{Document, Node};
 use thiserror::Error;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use std::str::FromStr;
 use std::{collections::HashMap, io::Read};
@@ -26,6 +26,10 @@ pub enum PobError {
     ItemsetNotFound(i32),
     #[error("itemset name not found: {0}")]
     ItemsetNameNotFound(String),
+    #[error("skillset not found: {0}")]
+    SkillsetNotFound(i32),
+    #[error("skillset name not found: {0}")]
+    SkillsetNameNotFound(String),
 
     #[error("type error: {0}")]
     TypeError(#[from] TypeError),
@@ -73,6 +77,7 @@ pub struct ItemSet {
     title: String,
     id: i32,
     items: Vec<Item>,
+    slots: HashMap<String, usize>,
 }
 
 impl ItemSet {
@@ -83,6 +88,7 @@ impl ItemSet {
         let id = i32::from_str(id)?;
         let title = node.attribute("title").map_or("default", |v| v);
         let mut items = vec![];
+        let mut slots = HashMap::new();
 
         for item in node.descendants() {
             let id = item.attribute("itemId").map_or("-1", |v| v);
@@ -90,6 +96,9 @@ impl ItemSet {
             if id == -1 || id == 0 {
                 continue;
             }
+            if let Some(slot) = item.attribute("name") {
+                slots.insert(slot.to_string(), items.len());
+            }
             items.push(items_map.get(&id).unwrap_or(&Item::default()).clone());
         }
 
@@ -97,6 +106,7 @@ impl ItemSet {
             id,
             title: String::from(title),
             items,
+            slots,
         })
     }
 
@@ -104,6 +114,16 @@ impl ItemSet {
         self.items.get(nth)
     }
 
+    pub fn get_slot_item(&self, slot: &str) -> Option<&Item> {
+        self.slots.get(slot).and_then(|idx| self.items.get(*idx))
+    }
+
+    // abyss jewels are slotted as "Belt Abyssal Socket 1" and so on
+    pub fn abyssal_sockets(&self, slot: &str) -> usize {
+        let prefix = format!("{} Abyssal Socket ", slot);
+        self.slots.keys().filter(|s| s.starts_with(&prefix)).count()
+    }
+
     pub fn title(&self) -> &str {
         &self.title
     }
@@ -117,16 +137,98 @@ impl ItemSet {
     }
 }
 
+// poe2 skill gem with supports socketed into it
+#[derive(Clone, Debug)]
+pub struct SkillGem {
+    gem: Item,
+    supports: Vec<Item>,
+    enabled: bool,
+}
+
+impl SkillGem {
+    pub fn gem(&self) -> &Item {
+        &self.gem
+    }
+
+    pub fn supports(&self) -> &Vec<Item> {
+        &self.supports
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SkillGroup {
+    slot: Option<String>,
+    label: String,
+    enabled: bool,
+    main_active_skill: usize,
+    links: usize,
+    // poe1 only, poe2 gems don't take item sockets
+    colors: Option<HashMap<SocketColor, usize>>,
+    active_gems: Vec<Item>,
+    supports: Vec<Item>,
+}
+
+impl SkillGroup {
+    pub fn slot(&self) -> Option<&str> {
+        self.slot.as_deref()
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn active_gems(&self) -> &Vec<Item> {
+        &self.active_gems
+    }
+
+    pub fn supports(&self) -> &Vec<Item> {
+        &self.supports
+    }
+
+    pub fn main_active_gem(&self) -> Option<&Item> {
+        self.active_gems
+            .get(self.main_active_skill)
+            .or(self.active_gems.first())
+    }
+
+    // disabled gems are usually swap options and don't take a socket
+    pub fn links(&self) -> usize {
+        self.links
+    }
+
+    // colors come from gem attribute requirements, gems with unknown color
+    // (e.g. white or newer than gem data) are counted only as links
+    pub fn socket_requirement(&self) -> Option<SocketRequirement> {
+        self.slot.as_ref()?;
+        Some(SocketRequirement {
+            links: self.links,
+            colors: self.colors.clone()?,
+            ..Default::default()
+        })
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct SkillSet {
     title: String,
     id: i32,
     skills: Vec<Item>,
+    groups: Vec<SkillGroup>,
+    skill_gems: Vec<SkillGem>,
 }
 
 impl SkillSet {
     fn try_from(
         node: &Node,
+        version: GameVersion,
         default_gem_quality: Option<i32>,
         default_gem_level: Option<i32>,
     ) -> Result<SkillSet, PobError> {
@@ -141,6 +243,13 @@ impl SkillSet {
                 node.descendants()
                     .filter(|&x| x.tag_name().name() == "Gem")
                     .collect(),
+                version,
+                default_gem_quality,
+                default_gem_level,
+            )?;
+            let groups = SkillSet::parse_groups(
+                node.children().filter(|x| x.has_tag_name("Skill")),
+                version,
                 default_gem_quality,
                 default_gem_level,
             )?;
@@ -149,12 +258,21 @@ impl SkillSet {
                 title: title.to_string(),
                 id,
                 skills,
+                skill_gems: SkillSet::skill_gems_from_groups(version, &groups),
+                groups,
             })
         } else {
             let skills = SkillSet::parse_skills(
                 node.next_siblings()
                     .flat_map(|n| n.descendants().filter(|&x| x.has_tag_name("Gem")))
                     .collect(),
+                version,
+                default_gem_quality,
+                default_gem_level,
+            )?;
+            let groups = SkillSet::parse_groups(
+                node.next_siblings().filter(|x| x.has_tag_name("Skill")),
+                version,
                 default_gem_quality,
                 default_gem_level,
             )?;
@@ -162,39 +280,116 @@ impl SkillSet {
                 title: "default".to_string(),
                 id: 0,
                 skills,
+                skill_gems: SkillSet::skill_gems_from_groups(version, &groups),
+                groups,
             })
         }
     }
 
+    fn parse_groups<'b, 'i: 'b>(
+        nodes: impl Iterator<Item = Node<'b, 'i>>,
+        version: GameVersion,
+        default_gem_quality: Option<i32>,
+        default_gem_level: Option<i32>,
+    ) -> Result<Vec<SkillGroup>, PobError> {
+        let mut groups = vec![];
+        for skill in nodes {
+            let gems = skill
+                .children()
+                .filter(|x| x.has_tag_name("Gem") && x.has_attribute("gemId"))
+                .collect::<Vec<_>>();
+            let enabled = gems
+                .iter()
+                .filter(|g| g.attribute("enabled") != Some("false"))
+                .collect::<Vec<_>>();
+            let links = enabled.len();
+            let colors = (version == GameVersion::Poe1).then(|| {
+                let mut colors = HashMap::new();
+                enabled
+                    .iter()
+                    .filter_map(|g| BaseItems::gem_color(g.attribute("gemId")?))
+                    .for_each(|c| *colors.entry(c).or_default() += 1);
+                colors
+            });
+            let (supports, active): (Vec<_>, Vec<_>) = gems.into_iter().partition(|g| {
+                g.attribute("gemId")
+                    .is_some_and(|id| id.contains("SupportGem"))
+            });
+            let active_gems =
+                SkillSet::parse_skills(active, version, default_gem_quality, default_gem_level)?;
+            let supports =
+                SkillSet::parse_skills(supports, version, default_gem_quality, default_gem_level)?;
+            let main_active_skill = skill
+                .attribute("mainActiveSkill")
+                .and_then(|s| usize::from_str(s).ok())
+                .unwrap_or(1);
+            groups.push(SkillGroup {
+                slot: skill
+                    .attribute("slot")
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.to_string()),
+                label: skill.attribute("label").unwrap_or_default().to_string(),
+                enabled: skill.attribute("enabled") != Some("false"),
+                main_active_skill: main_active_skill.saturating_sub(1),
+                links,
+                colors,
+                active_gems,
+                supports,
+            });
+        }
+        Ok(groups)
+    }
+
+    fn skill_gems_from_groups(version: GameVersion, groups: &[SkillGroup]) -> Vec<SkillGem> {
+        if version != GameVersion::Poe2 {
+            return vec![];
+        }
+        groups
+            .iter()
+            .filter_map(|g| {
+                Some(SkillGem {
+                    gem: g.active_gems.first()?.clone(),
+                    supports: g.supports.clone(),
+                    enabled: g.enabled,
+                })
+            })
+            .collect()
+    }
+
     fn parse_skills(
         nodes: Vec<Node>,
+        version: GameVersion,
         default_gem_quality: Option<i32>,
         default_gem_level: Option<i32>,
     ) -> Result<Vec<Item>, PobError> {
         Ok(nodes
             .into_iter()
             .filter_map(|n| {
-                if !n.has_attribute("gemId") {
-                    return None;
-                }
-
-                let gem_id = n.attribute("gemId").unwrap();
+                let gem_id = n.attribute("gemId")?;
                 let quality = n
                     .attribute("quality")
                     .and_then(|q| i32::from_str(q).ok())
                     .or(default_gem_quality)
-                    .unwrap();
+                    .unwrap_or(0);
                 let level = n
                     .attribute("level")
                     .and_then(|q| i32::from_str(q).ok())
                     .or(default_gem_level)
-                    .unwrap();
-                let info = BaseItems::get_by_id(gem_id).unwrap();
+                    .unwrap_or(1);
+                // gems missing from base items data are skipped instead of failing the whole pob
+                let Some(info) = version.get_base_by_id(gem_id).or_else(|| {
+                    n.attribute("nameSpec")
+                        .and_then(|name| version.get_base_by_name(name))
+                }) else {
+                    warn!("unknown gem {gem_id}, skipping");
+                    return None;
+                };
                 Some(Item {
                     name: info.name.clone(),
                     base_type: info.name,
                     category: Category::Gems,
                     subcategories: Subcategory::Gem,
+                    game_version: version,
                     properties: vec![
                         Property {
                             name: "Level".to_string(),
@@ -220,6 +415,14 @@ impl SkillSet {
     pub fn gems(&self) -> Vec<Item> {
         self.skills.clone()
     }
+
+    pub fn groups(&self) -> &Vec<SkillGroup> {
+        &self.groups
+    }
+
+    pub fn skill_gems(&self) -> &Vec<SkillGem> {
+        &self.skill_gems
+    }
 }
 
 #[derive(Debug)]
@@ -229,7 +432,7 @@ pub struct PobDocument<'a> {
 
 #[cfg(feature = "parsing")]
 impl<'a> PobDocument<'a> {
-    pub fn get_item_sets(&self) -> Vec<ItemSet> {
+    pub fn get_item_sets(&self) -> Result<Vec<ItemSet>, PobError> {
         let mut itemsets = vec![];
         let mut items: HashMap<i32, ParsedItem> = HashMap::new();
         let mut nodes = self.doc.descendants();
@@ -237,16 +440,21 @@ impl<'a> PobDocument<'a> {
             Some(k) => k,
             None => {
                 info!("pob does not have any items");
-                return vec![];
+                return Ok(vec![]);
             }
         };
 
         for item in items_node.descendants() {
             if item.tag_name().name() == "Item" {
-                let id = item.attribute("id").unwrap();
-                let id = i32::from_str(id).unwrap();
-                let (_, itm) = parse_pob_item::<VerboseError<&str>>(item.text().unwrap_or(""))
-                    .expect("can't parse item");
+                let id = item
+                    .attribute("id")
+                    .ok_or(PobError::Parse("can't get id from item".into()))?;
+                let id = i32::from_str(id)?;
+                let (_, itm) = parse_pob_item::<VerboseError<&str>>(
+                    item.text().unwrap_or(""),
+                    self.game_version(),
+                )
+                .map_err(|e| PobError::Parse(format!("can't parse item {}: {}", id, e)))?;
                 items.insert(id, itm);
             }
         }
@@ -266,12 +474,15 @@ impl<'a> PobDocument<'a> {
             }
         }
 
-        itemsets
+        Ok(itemsets)
     }
 
-    pub fn get_skillsets(&self) -> Vec<SkillSet> {
+    pub fn get_skillsets(&self) -> Result<Vec<SkillSet>, PobError> {
+        let version = self.game_version();
         let mut nodes = self.doc.descendants();
-        let skills_node = nodes.find(|&x| x.has_tag_name("Skills")).unwrap();
+        let skills_node = nodes
+            .find(|&x| x.has_tag_name("Skills"))
+            .ok_or(PobError::Parse("pob does not have skills".into()))?;
         let default_gem_quality = skills_node.attribute("defaultGemQuality").and_then(|s| {
             if s == "normalMaximum" {
                 Some(20)
@@ -286,29 +497,29 @@ impl<'a> PobDocument<'a> {
                 i32::from_str(s).ok()
             }
         });
-        let child = skills_node.first_element_child().unwrap();
-        let skillsets = if child.has_tag_name("SkillSet") {
+        let Some(child) = skills_node.first_element_child() else {
+            return Ok(vec![]);
+        };
+        if child.has_tag_name("SkillSet") {
             child
                 .next_siblings()
-                .filter_map(|sk| {
-                    if sk.is_text() {
-                        None
-                    } else {
-                        Some(
-                            SkillSet::try_from(&sk, default_gem_quality, default_gem_level)
-                                .unwrap(),
-                        )
-                    }
+                .filter(|sk| !sk.is_text())
+                .map(|sk| {
+                    SkillSet::try_from(&sk, version, default_gem_quality, default_gem_level)
                 })
                 .collect()
         } else {
-            vec![SkillSet::try_from(&child, default_gem_quality, default_gem_level).unwrap()]
-        };
-        skillsets
+            Ok(vec![SkillSet::try_from(
+                &child,
+                version,
+                default_gem_quality,
+                default_gem_level,
+            )?])
+        }
     }
 
     pub fn get_first_itemset(&self) -> Result<ItemSet, PobError> {
-        let itemsets = self.get_item_sets();
+        let itemsets = self.get_item_sets()?;
 
         itemsets
             .into_iter()
@@ -321,7 +532,7 @@ impl<'a> PobDocument<'a> {
             return self.get_first_itemset();
         }
 
-        let itemsets = self.get_item_sets();
+        let itemsets = self.get_item_sets()?;
 
         itemsets
             .into_iter()
@@ -331,6 +542,14 @@ impl<'a> PobDocument<'a> {
 }
 
 impl<'a> PobDocument<'a> {
+    pub fn game_version(&self) -> GameVersion {
+        if self.doc.root_element().has_tag_name("PathOfBuilding2") {
+            GameVersion::Poe2
+        } else {
+            GameVersion::Poe1
+        }
+    }
+
     pub fn get_itemsets_list(&self) -> Result<Vec<String>, PobError> {
         let mut itemsets = vec![];
         let mut nodes = self.doc.descendants();
@@ -354,8 +573,12 @@ impl<'a> PobDocument<'a> {
 
     pub fn get_skillsets_list(&self) -> Result<Vec<String>, PobError> {
         let mut nodes = self.doc.descendants();
-        let skills_node = nodes.find(|&x| x.has_tag_name("Skills")).unwrap();
-        let child = skills_node.first_element_child().unwrap();
+        let skills_node = nodes
+            .find(|&x| x.has_tag_name("Skills"))
+            .ok_or(PobError::Parse("pob does not have skills".into()))?;
+        let Some(child) = skills_node.first_element_child() else {
+            return Ok(vec![]);
+        };
         let skillsets = if child.has_tag_name("SkillSet") {
             child
                 .next_siblings()
@@ -382,16 +605,21 @@ mod tests {
     const TESTPOB_GEMS: &str = include_str!("pob_gems.txt");
     const TESTPOB_NEW: &str = include_str!("pob_new.txt");
     const TESTPOB_NEW2: &str = include_str!("pob_new2.txt");
+    const TESTPOB_POE2: &str = include_str!("pob_poe2.txt");
 
     use super::Pob;
+    use domain::{
+        data::GameVersion,
+        item::types::{SocketColor, Sockets, Subcategory},
+    };
 
     #[test]
     fn parse_pob() -> Result<(), anyhow::Error> {
         dotenv::dotenv().ok();
         let pob = Pob::from_pastebin_data(TESTPOB.to_owned())?;
         let doc = pob.as_document()?;
-        let _ = doc.get_item_sets();
-        let _ = doc.get_skillsets();
+        doc.get_item_sets()?;
+        doc.get_skillsets()?;
         Ok(())
     }
 
@@ -427,10 +655,10 @@ mod tests {
         let doc = pob.as_document()?;
         let set = doc.get_first_itemset()?;
         println!("first itemset: {:?}", set);
-        let is = doc.get_item_sets();
+        let is = doc.get_item_sets()?;
         assert_eq!(is.len(), 7);
         assert!(!doc.get_itemsets_list()?.is_empty());
-        let sks = doc.get_skillsets();
+        let sks = doc.get_skillsets()?;
         assert_eq!(sks.len(), 6);
         assert!(!doc.get_skillsets_list()?.is_empty());
         for _ in set.items() {}
@@ -442,10 +670,10 @@ mod tests {
         dotenv::dotenv().ok();
         let pob = Pob::from_pastebin_data(TESTPOB_NEW2.to_owned())?;
         let doc = pob.as_document()?;
-        let is = doc.get_item_sets();
+        let is = doc.get_item_sets()?;
         assert_eq!(is.len(), 4);
         assert!(!doc.get_itemsets_list()?.is_empty());
-        let sks = doc.get_skillsets();
+        let sks = doc.get_skillsets()?;
         assert_eq!(sks.len(), 6);
         assert!(!doc.get_skillsets_list()?.is_empty());
         Ok(())
@@ -456,7 +684,7 @@ mod tests {
         dotenv::dotenv().ok();
         let pob = Pob::from_pastebin_data(TESTPOB.to_owned())?;
         let doc = pob.as_document()?;
-        let itemsets = doc.get_item_sets();
+        let itemsets = doc.get_item_sets()?;
         assert_eq!(itemsets.len(), 3);
         assert_eq!(itemsets[0].title(), "default");
         assert_eq!(itemsets[0].id(), 1);
@@ -471,7 +699,7 @@ mod tests {
     fn check_skillsets_default() -> Result<(), anyhow::Error> {
         let pob = Pob::from_pastebin_data(TESTPOB.to_owned())?;
         let doc = pob.as_document()?;
-        let skillsets = doc.get_skillsets();
+        let skillsets = doc.get_skillsets()?;
         assert_eq!(skillsets.len(), 1);
         // assert_eq!(itemsets[0].title(), "default");
         // assert_eq!(itemsets[0].id(), 1);
@@ -482,10 +710,112 @@ mod tests {
     fn check_skillsets_many() -> Result<(), anyhow::Error> {
         let pob = Pob::from_pastebin_data(TESTPOB_GEMS.to_owned().trim().to_string())?;
         let doc = pob.as_document()?;
-        let skillsets = doc.get_skillsets();
+        let skillsets = doc.get_skillsets()?;
         assert_eq!(skillsets.len(), 7);
         // assert_eq!(itemsets[0].title(), "default");
         // assert_eq!(itemsets[0].id(), 1);
         Ok(())
     }
+
+    #[test]
+    fn parse_poe2() -> Result<(), anyhow::Error> {
+        let pob = Pob::from_pastebin_data(TESTPOB_POE2.to_owned())?;
+        let doc = pob.as_document()?;
+        assert_eq!(doc.game_version(), GameVersion::Poe2);
+
+        let set = doc.get_first_itemset()?;
+        let subcategories = set
+            .items()
+            .iter()
+            .map(|it| it.subcategories.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            subcategories,
+            vec![
+                Subcategory::Weapon,
+                Subcategory::Focus,
+                Subcategory::Gloves,
+                Subcategory::Charm
+            ]
+        );
+        assert!(set
+            .items()
+            .iter()
+            .all(|it| it.game_version == GameVersion::Poe2));
+
+        let skillsets = doc.get_skillsets()?;
+        assert_eq!(skillsets.len(), 2);
+        assert_eq!(skillsets[0].gems().len(), 5);
+        let skill_gems = skillsets[0].skill_gems();
+        assert_eq!(skill_gems.len(), 2);
+        assert_eq!(skill_gems[0].gem().name, "Spark");
+        assert!(skill_gems[0].enabled());
+        assert_eq!(
+            skill_gems[0]
+                .supports()
+                .iter()
+                .map(|g| g.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Spell Echo", "Controlled Destruction"]
+        );
+        assert_eq!(skill_gems[1].gem().name, "Flame Wall");
+        assert!(!skill_gems[1].enabled());
+        Ok(())
+    }
+
+    #[test]
+    fn poe1_has_no_skill_gems() -> Result<(), anyhow::Error> {
+        let pob = Pob::from_pastebin_data(TESTPOB.to_owned())?;
+        let doc = pob.as_document()?;
+        assert_eq!(doc.game_version(), GameVersion::Poe1);
+        assert!(doc.get_skillsets()?[0].skill_gems().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn check_skill_groups() -> Result<(), anyhow::Error> {
+        let pob = Pob::new(include_str!("pob.xml"));
+        let doc = pob.as_document()?;
+        let itemset = doc.get_first_itemset()?;
+        let skillset = doc.get_skillsets()?.remove(0);
+        let groups = skillset.groups();
+        let grouped_gems = groups
+            .iter()
+            .map(|g| g.active_gems().len() + g.supports().len())
+            .sum::<usize>();
+        assert_eq!(grouped_gems, skillset.gems().len());
+
+        let body = &groups[0];
+        assert_eq!(body.slot(), Some("Body Armour"));
+        assert!(body.enabled());
+        assert_eq!(body.main_active_gem().unwrap().name, "Toxic Rain");
+        assert_eq!(body.active_gems().len(), 1);
+        assert_eq!(body.supports().len(), 7);
+        assert_eq!(body.links(), 6);
+        // build lists 6 enabled gems but the body armour is only 5-linked
+        let req = body.socket_requirement().unwrap();
+        assert_eq!(req.links, 6);
+        assert_eq!(req.colors[&SocketColor::G], 5);
+        assert_eq!(req.colors[&SocketColor::B], 1);
+        let body_armour = itemset.get_slot_item("Body Armour").unwrap();
+        assert!(!body_armour.sockets.satisfies(&req));
+        assert!(Sockets::try_from("G-G-G-G-G-B")?.satisfies(&req));
+        assert!(!Sockets::try_from("G-G-G-G-B-B")?.satisfies(&req));
+        Ok(())
+    }
+
+    #[test]
+    fn skips_unknown_gems() -> Result<(), anyhow::Error> {
+        let pob = Pob::new(
+            r#"<PathOfBuilding><Skills><Skill slot="Body Armour">
+<Gem gemId="Metadata/Items/Gems/RainOfSpores" nameSpec="Toxic Rain" level="20" quality="20"/>
+<Gem gemId="Metadata/Items/Gems/NotAGem" nameSpec="Not A Gem" level="20"/>
+</Skill></Skills></PathOfBuilding>"#,
+        );
+        let doc = pob.as_document()?;
+        let skillset = doc.get_skillsets()?.remove(0);
+        assert_eq!(skillset.gems().len(), 1);
+        assert_eq!(skillset.gems()[0].name, "Toxic Rain");
+        Ok(())
+    }
 }
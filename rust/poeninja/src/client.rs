@@ -1,16 +1,24 @@
-use crate::limits::{Limits, MultipleLimits};
+use std::time::Duration;
+
 use crate::models::Response;
-use reqwest::cookie::Jar;
-use reqwest::{Method, Request, StatusCode, Url};
 use thiserror::Error;
-use tracing::{debug, error};
+use utils::{
+    reqwest::{cookie::Jar, Request, StatusCode, Url},
+    ClientBuilder, ClientWithMiddleware, Extensions, Limit, LimitHeaders, LimitMiddleware,
+    LimitPolicy,
+};
+
+// poe.ninja doesn't report rate limits, so static rules are registered in shared limiter
+const LIMIT_POLICY: &str = "poe-ninja";
 
 #[derive(Error, Debug)]
 pub enum ClientError {
     #[error("try on next cycle")]
     NextCycle,
     #[error("reqwest error: {0}")]
-    ReqwestError(#[from] reqwest::Error),
+    ReqwestError(#[from] utils::reqwest::Error),
+    #[error("reqwest_middleware error: {0}")]
+    ReqwestMiddleware(#[from] utils::ReqwestMiddlewareError),
     #[error("failed check: {0}")]
     FailedCheck(String),
     #[error("status code: {0}")]
@@ -20,8 +28,7 @@ pub enum ClientError {
 }
 
 pub struct Client {
-    client: reqwest::Client,
-    limiter: MultipleLimits,
+    client: ClientWithMiddleware,
     league: String,
     failed_check: Option<String>,
 }
@@ -34,18 +41,23 @@ impl Client {
             &"https://www.pathofexile.com".parse::<Url>().unwrap(),
         );
 
-        let client = reqwest::ClientBuilder::new()
+        let client = utils::reqwest::ClientBuilder::new()
             .user_agent(user_agent)
             .cookie_store(true)
             .cookie_provider(jar.into())
             .build()
             .expect("can't build http client");
 
-        let limiter = MultipleLimits::parse_header("0:1:60", ",");
+        let limiter = LimitMiddleware::shared();
+        limiter.set_rules(
+            LIMIT_POLICY,
+            LimitHeaders::Client,
+            &[Limit::new(1, Duration::from_secs(1), Duration::ZERO)],
+        );
+        let client = ClientBuilder::new(client).with(limiter).build();
 
         Client {
             client,
-            limiter,
             league: league.to_string(),
             failed_check: None,
         }
@@ -53,8 +65,7 @@ impl Client {
 
     async fn make_limiter_request<T>(
         failed_check: &mut Option<String>,
-        client: &mut reqwest::Client,
-        limiter: &mut MultipleLimits,
+        client: &mut ClientWithMiddleware,
         req: Request,
     ) -> Result<T, ClientError>
     where
@@ -63,9 +74,9 @@ impl Client {
         if failed_check.is_some() {
             return Err(ClientError::FailedCheck(failed_check.clone().unwrap()));
         }
-        limiter.until_ready().await;
-
-        let resp = client.execute(req).await?;
+        let mut extensions = Extensions::new();
+        extensions.insert(LimitPolicy(LIMIT_POLICY.to_string()));
+        let resp = client.execute_with_extensions(req, &mut extensions).await?;
 
         let st = resp.status();
         match st {
@@ -88,7 +99,6 @@ impl Client {
         Self::make_limiter_request(
             &mut self.failed_check,
             &mut self.client,
-            &mut self.limiter,
             req,
         ).await
     }
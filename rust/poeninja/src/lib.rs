@@ -1,5 +1,4 @@
 mod client;
-mod limits;
 pub mod models;
 
 pub use client::{Client, ClientError};
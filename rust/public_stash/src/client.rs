@@ -17,10 +17,16 @@ pub enum Error {
     StatusCode(u16),
     #[error("too many requests")]
     TooManyRequests,
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
 }
 
+const BASE_URL: &str = "https://api.pathofexile.com";
+
 pub struct Client {
     client: ClientWithMiddleware,
+    base_url: String,
+    token: Option<String>,
 }
 
 impl Client {
@@ -31,20 +37,42 @@ impl Client {
             .expect("can't build http client");
 
         let client = ClientBuilder::new(client)
-            .with(LimitMiddleware::default())
+            .with(LimitMiddleware::shared())
             .build();
 
-        Client { client }
+        Client {
+            client,
+            base_url: BASE_URL.to_string(),
+            token: None,
+        }
+    }
+
+    pub fn set_base_url(&mut self, url: &str) {
+        self.base_url = url.trim_end_matches('/').to_string();
+    }
+
+    // oauth token with service:psapi scope
+    pub fn set_token(&mut self, token: &str) {
+        self.token = Some(token.to_string());
     }
 
     pub async fn get_latest_stash<T: AsRef<str>>(&mut self, id: Option<T>) -> Result<PublicStashData, Error> {
+        let body = self.get_latest_stash_raw(id).await?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    // response body as is, e.g. for recording
+    pub async fn get_latest_stash_raw<T: AsRef<str>>(&mut self, id: Option<T>) -> Result<String, Error> {
         let mut req = self
             .client
-            .get("https://api.pathofexile.com/public-stash-tabs");
+            .get(format!("{}/public-stash-tabs", self.base_url));
 
         if let Some(id) = id {
             req = req.query(&[("id", id.as_ref())]);
         }
+        if let Some(token) = &self.token {
+            req = req.bearer_auth(token);
+        }
 
         let req = req.build()?;
 
@@ -59,8 +87,6 @@ impl Client {
             x => return Err(Error::StatusCode(x.as_u16())),
         };
 
-        let body = resp.json::<PublicStashData>().await?;
-
-        Ok(body)
+        Ok(resp.text().await?)
     }
 }
@@ -1,2 +1,6 @@
 pub mod client;
 pub mod models;
+pub mod recorder;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+pub mod tracker;
@@ -1,9 +1,12 @@
-use domain::item::{
-    types::{
-        Category, Influence, Mod, ModError, ModType, Property, Rarity, Sockets, Subcategory,
-        SubcategoryError, TypeError,
+use domain::{
+    data::GameVersion,
+    item::{
+        types::{
+            Category, Influence, Mod, ModError, ModType, Property, Rarity, Sockets, Subcategory,
+            SubcategoryError, TypeError,
+        },
+        Item as DomainItem,
     },
-    Item as DomainItem,
 };
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
@@ -301,6 +304,7 @@ impl From<FrameType> for Rarity {
             1 => Rarity::Magic,
             2 => Rarity::Rare,
             3 => Rarity::Unique,
+            9 => Rarity::Relic,
             _ => Rarity::Normal,
         }
     }
@@ -394,6 +398,8 @@ impl TryFrom<Item> for DomainItem {
             mods,
             unknown_mods,
             note: value.note,
+            game_version: GameVersion::Poe1,
+            variant: None,
         })
     }
 }
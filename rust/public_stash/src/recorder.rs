@@ -0,0 +1,369 @@
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use thiserror::Error;
+use tracing::{info, warn};
+use utils::stream_stashes::extract_next_change_id;
+
+use crate::client::{Client, Error as ClientError};
+
+const CHECKPOINT_FILE: &str = "checkpoint";
+const MAX_RETRIES: u32 = 5;
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Error)]
+pub enum RecorderError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("client error: {0}")]
+    Client(#[from] ClientError),
+    #[error("response without next_change_id for {0}")]
+    NoChangeId(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordFormat {
+    // segment is a directory with `{change_id}.json` files
+    Dir,
+    // segment is a zstd compressed tar with `{change_id}.json` entries
+    TarZstd,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecorderConfig {
+    pub dir: PathBuf,
+    pub format: RecordFormat,
+    // segment is rotated when it reaches either limit
+    pub max_segment_bytes: u64,
+    pub max_segment_age: Duration,
+}
+
+enum SegmentWriter {
+    Dir(PathBuf),
+    TarZstd(tar::Builder<zstd::Encoder<'static, File>>),
+}
+
+struct Segment {
+    writer: SegmentWriter,
+    bytes: u64,
+    started: Instant,
+}
+
+impl Segment {
+    fn write(&mut self, change_id: &str, body: &str) -> Result<(), RecorderError> {
+        let name = format!("{}.json", change_id);
+        match &mut self.writer {
+            SegmentWriter::Dir(dir) => std::fs::write(dir.join(name), body)?,
+            SegmentWriter::TarZstd(builder) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(body.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(
+                    std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs(),
+                );
+                builder.append_data(&mut header, name, body.as_bytes())?;
+            }
+        }
+        self.bytes += body.len() as u64;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), RecorderError> {
+        if let SegmentWriter::TarZstd(builder) = self.writer {
+            builder.into_inner()?.finish()?.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+// writes stash responses into rotated segments and remembers where to continue
+pub struct Recorder {
+    config: RecorderConfig,
+    segment: Option<Segment>,
+    segments: usize,
+    // next change id after last recorded response
+    pending: Option<String>,
+}
+
+impl Recorder {
+    pub fn new(config: RecorderConfig) -> Result<Recorder, RecorderError> {
+        std::fs::create_dir_all(&config.dir)?;
+        let segments = segments(&config.dir)?.len();
+        Ok(Recorder {
+            config,
+            segment: None,
+            segments,
+            pending: None,
+        })
+    }
+
+    // change id to continue from, saved for responses which are fully written
+    pub fn checkpoint(&self) -> Result<Option<String>, RecorderError> {
+        match std::fs::read_to_string(self.config.dir.join(CHECKPOINT_FILE)) {
+            Ok(s) => Ok(Some(s.trim().to_string()).filter(|s| !s.is_empty())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save_checkpoint(&self, change_id: &str) -> Result<(), RecorderError> {
+        let path = self.config.dir.join(CHECKPOINT_FILE);
+        let tmp = path.with_extension("tmp");
+        let mut f = File::create(&tmp)?;
+        f.write_all(change_id.as_bytes())?;
+        f.sync_all()?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    fn open_segment(&mut self, change_id: &str) -> Result<Segment, RecorderError> {
+        let name = format!("{:06}-{}", self.segments, change_id);
+        self.segments += 1;
+        let writer = match self.config.format {
+            RecordFormat::Dir => {
+                let dir = self.config.dir.join(name);
+                std::fs::create_dir_all(&dir)?;
+                SegmentWriter::Dir(dir)
+            }
+            RecordFormat::TarZstd => {
+                let f = File::create(self.config.dir.join(format!("{}.tar.zst", name)))?;
+                SegmentWriter::TarZstd(tar::Builder::new(zstd::Encoder::new(f, 0)?))
+            }
+        };
+        Ok(Segment {
+            writer,
+            bytes: 0,
+            started: Instant::now(),
+        })
+    }
+
+    // records response fetched with `change_id`, returns its next_change_id
+    pub fn write(&mut self, change_id: &str, body: &str) -> Result<String, RecorderError> {
+        let next = extract_next_change_id(body)
+            .ok_or_else(|| RecorderError::NoChangeId(change_id.to_string()))?;
+        let full = self.segment.as_ref().is_some_and(|s| {
+            s.bytes >= self.config.max_segment_bytes
+                || s.started.elapsed() >= self.config.max_segment_age
+        });
+        if full {
+            self.finish()?;
+        }
+        let mut segment = match self.segment.take() {
+            Some(s) => s,
+            None => self.open_segment(change_id)?,
+        };
+        segment.write(change_id, body)?;
+        self.segment = Some(segment);
+
+        // compressed segment is readable only after it's finished
+        match self.config.format {
+            RecordFormat::Dir => self.save_checkpoint(&next)?,
+            RecordFormat::TarZstd => self.pending = Some(next.clone()),
+        }
+        Ok(next)
+    }
+
+    // closes current segment, next write starts a new one
+    pub fn finish(&mut self) -> Result<(), RecorderError> {
+        if let Some(s) = self.segment.take() {
+            s.finish()?;
+        }
+        if let Some(next) = self.pending.take() {
+            self.save_checkpoint(&next)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordOptions {
+    // starts from checkpoint or from the beginning of the river if None
+    pub from: Option<String>,
+    pub max_changes: Option<usize>,
+    // wait before asking again when there are no new changes
+    pub poll_interval: Duration,
+}
+
+// follows next_change_id and records every response, returns number of recorded responses
+pub async fn record(
+    client: &mut Client,
+    recorder: &mut Recorder,
+    options: RecordOptions,
+) -> Result<usize, RecorderError> {
+    let mut change_id = match options.from {
+        Some(id) => Some(id),
+        None => recorder.checkpoint()?,
+    };
+    let mut count = 0;
+    let mut retries = 0;
+    while options.max_changes.is_none_or(|m| count < m) {
+        let body = match client.get_latest_stash_raw(change_id.as_deref()).await {
+            Ok(b) => b,
+            Err(e) if retries < MAX_RETRIES => {
+                warn!("cannot fetch {:?}: {}", change_id, e);
+                tokio::time::sleep(BASE_BACKOFF * 2u32.pow(retries)).await;
+                retries += 1;
+                continue;
+            }
+            Err(e) => {
+                recorder.finish()?;
+                return Err(e.into());
+            }
+        };
+        retries = 0;
+
+        let id = change_id.clone().unwrap_or_else(|| "index".to_string());
+        if extract_next_change_id(&body).as_ref() == Some(&id) {
+            // caught up with the river
+            tokio::time::sleep(options.poll_interval).await;
+            continue;
+        }
+        let next = recorder.write(&id, &body)?;
+        info!("recorded {}, next {}", id, next);
+        change_id = Some(next);
+        count += 1;
+    }
+    recorder.finish()?;
+    Ok(count)
+}
+
+// reads recorded segment names in recording order
+pub fn segments<P: AsRef<Path>>(dir: P) -> Result<Vec<PathBuf>, RecorderError> {
+    let mut segments: Vec<_> = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .is_some_and(|n| !n.to_string_lossy().starts_with(CHECKPOINT_FILE))
+        })
+        .collect();
+    segments.sort();
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Read, path::Path, time::Duration};
+
+    use wiremock::{
+        matchers::{method, path, query_param, query_param_is_missing},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use utils::stream_stashes::{open_stashes, validate_chain};
+
+    use super::{record, segments, RecordFormat, RecordOptions, Recorder, RecorderConfig};
+    use crate::{client::Client, test_utils::page};
+
+    async fn mock_river() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/public-stash-tabs"))
+            .and(query_param_is_missing("id"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(page("1-1")))
+            .mount(&server)
+            .await;
+        for (id, next) in [("1-1", "2-2"), ("2-2", "3-3"), ("3-3", "4-4")] {
+            Mock::given(method("GET"))
+                .and(path("/public-stash-tabs"))
+                .and(query_param("id", id))
+                .respond_with(ResponseTemplate::new(200).set_body_string(page(next)))
+                .mount(&server)
+                .await;
+        }
+        server
+    }
+
+    fn config(dir: &Path, format: RecordFormat) -> RecorderConfig {
+        RecorderConfig {
+            dir: dir.to_path_buf(),
+            format,
+            // every segment holds two responses
+            max_segment_bytes: page("1-1").len() as u64 * 2,
+            max_segment_age: Duration::from_secs(3600),
+        }
+    }
+
+    fn options(max_changes: usize) -> RecordOptions {
+        RecordOptions {
+            from: None,
+            max_changes: Some(max_changes),
+            poll_interval: Duration::from_millis(10),
+        }
+    }
+
+    #[tokio::test]
+    async fn record_dir_and_resume() -> anyhow::Result<()> {
+        let server = mock_river().await;
+        let mut client = Client::new("test");
+        client.set_base_url(&server.uri());
+        let dir = tempfile::tempdir()?;
+        let config = config(dir.path(), RecordFormat::Dir);
+
+        let mut recorder = Recorder::new(config.clone())?;
+        assert_eq!(record(&mut client, &mut recorder, options(3)).await?, 3);
+        assert_eq!(recorder.checkpoint()?.as_deref(), Some("3-3"));
+        let segs = segments(&config.dir)?;
+        assert_eq!(segs.len(), 2);
+        assert!(segs[0].join("index.json").exists());
+        assert!(segs[0].join("1-1.json").exists());
+        assert!(segs[1].join("2-2.json").exists());
+        // rotated segment without index.json is read from its first change id
+        let report = validate_chain(open_stashes(&segs[1])?)?;
+        assert!(report.is_valid());
+        assert_eq!(report.entries, 1);
+
+        // new recorder continues from checkpoint in a new segment
+        let mut recorder = Recorder::new(config.clone())?;
+        assert_eq!(record(&mut client, &mut recorder, options(1)).await?, 1);
+        let segs = segments(&config.dir)?;
+        assert_eq!(segs.len(), 3);
+        assert!(segs[2].ends_with("000002-3-3"));
+        assert_eq!(
+            std::fs::read_to_string(segs[2].join("3-3.json"))?,
+            page("4-4")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn record_tar_zstd() -> anyhow::Result<()> {
+        let server = mock_river().await;
+        let mut client = Client::new("test");
+        client.set_base_url(&server.uri());
+        let dir = tempfile::tempdir()?;
+        let config = config(dir.path(), RecordFormat::TarZstd);
+
+        let mut recorder = Recorder::new(config.clone())?;
+        assert_eq!(record(&mut client, &mut recorder, options(3)).await?, 3);
+        assert_eq!(recorder.checkpoint()?.as_deref(), Some("3-3"));
+
+        let segs = segments(&config.dir)?;
+        assert_eq!(segs.len(), 2);
+        let mut archive = tar::Archive::new(zstd::Decoder::new(std::fs::File::open(&segs[0])?)?);
+        let mut entries = vec![];
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let mut body = String::new();
+            entry.read_to_string(&mut body)?;
+            entries.push((entry.path()?.to_string_lossy().to_string(), body));
+        }
+        assert_eq!(
+            entries,
+            [
+                ("index.json".to_string(), page("1-1")),
+                ("1-1.json".to_string(), page("2-2")),
+            ]
+        );
+        let rotated: Vec<_> = open_stashes(&segs[1])?.collect::<Result<_, _>>()?;
+        assert_eq!(rotated, [("2-2.json".to_string(), page("3-3"))]);
+        Ok(())
+    }
+}
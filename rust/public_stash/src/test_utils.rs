@@ -0,0 +1,4 @@
+// minimal public stash response, enough to follow change id chains
+pub fn page(next: &str) -> String {
+    format!(r#"{{"next_change_id": "{}", "stashes": []}}"#, next)
+}
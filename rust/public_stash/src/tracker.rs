@@ -0,0 +1,466 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::SystemTime,
+};
+
+use domain::{
+    build_calculation::stored_item::{Price, StoredItem},
+    item::types::{Mod, ModType},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Item, PublicStashChange, PublicStashData};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackedItem {
+    pub id: String,
+    pub basetype: String,
+    pub name: String,
+    pub unique: bool,
+    // sorted explicit mod stat ids
+    pub stat_ids: Vec<String>,
+    pub price: Option<Price>,
+    // when item was first seen in stash
+    pub listed_at: SystemTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StashEvent {
+    ItemAdded {
+        stash_id: String,
+        item: TrackedItem,
+    },
+    // item was sold or moved to stash which wasn't seen yet
+    ItemRemoved {
+        stash_id: String,
+        item: TrackedItem,
+        at: SystemTime,
+    },
+    // item appeared in another stash, keeps its listing time
+    ItemMoved {
+        from: String,
+        to: String,
+        item: TrackedItem,
+        at: SystemTime,
+    },
+    PriceChanged {
+        stash_id: String,
+        item_id: String,
+        old: Option<Price>,
+        new: Option<Price>,
+        at: SystemTime,
+    },
+    // stash became empty or private, its items are not reported as removed one by one
+    StashEmptied {
+        stash_id: String,
+        items: Vec<TrackedItem>,
+        at: SystemTime,
+    },
+}
+
+#[derive(Debug, Default)]
+struct StashSnapshot {
+    league: Option<String>,
+    items: HashMap<String, TrackedItem>,
+}
+
+// keeps last known state of every stash and diffs incoming changes against it
+#[derive(Debug, Default)]
+pub struct StashTracker {
+    stashes: HashMap<String, StashSnapshot>,
+    // item id -> stash it was last seen in
+    item_stashes: HashMap<String, String>,
+}
+
+// item note has priority over stash name, same as in stash receiver
+fn item_price(item: &Item, stash_name: Option<&str>) -> Option<Price> {
+    item.note
+        .as_deref()
+        .or(stash_name)
+        .and_then(StoredItem::extract_price)
+}
+
+fn stat_ids(item: &Item) -> Vec<String> {
+    let mut ids: Vec<_> = item
+        .explicit_mods
+        .iter()
+        .flatten()
+        .filter_map(|m| Mod::try_by_stat(m, ModType::Explicit).ok())
+        .map(|m| m.stat_id)
+        .collect();
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+impl StashTracker {
+    pub fn new() -> StashTracker {
+        StashTracker::default()
+    }
+
+    // number of tracked non-empty stashes
+    pub fn len(&self) -> usize {
+        self.stashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stashes.is_empty()
+    }
+
+    pub fn items(&self, stash_id: &str) -> impl Iterator<Item = &TrackedItem> {
+        self.stashes
+            .get(stash_id)
+            .into_iter()
+            .flat_map(|s| s.items.values())
+    }
+
+    pub fn league(&self, stash_id: &str) -> Option<&str> {
+        self.stashes.get(stash_id)?.league.as_deref()
+    }
+
+    // removes item from stash it was seen in before and reports it as moved to `stash_id`
+    fn take_moved(
+        &mut self,
+        id: &str,
+        stash_id: &str,
+        at: SystemTime,
+        events: &mut Vec<StashEvent>,
+    ) -> Option<TrackedItem> {
+        let from = self
+            .item_stashes
+            .get(id)
+            .filter(|s| *s != stash_id)?
+            .clone();
+        let snapshot = self.stashes.get_mut(&from)?;
+        let item = snapshot.items.remove(id)?;
+        if snapshot.items.is_empty() {
+            self.stashes.remove(&from);
+        }
+        events.push(StashEvent::ItemMoved {
+            from,
+            to: stash_id.to_string(),
+            item: item.clone(),
+            at,
+        });
+        Some(item)
+    }
+
+    pub fn apply_data(&mut self, data: &PublicStashData, at: SystemTime) -> Vec<StashEvent> {
+        let events = data
+            .stashes
+            .iter()
+            .flat_map(|s| self.apply(s, at))
+            .collect();
+        self.merge_moves(events, at)
+    }
+
+    // item removed from one stash and added to another in same change is moved,
+    // no matter in which order stashes come
+    fn merge_moves(&mut self, events: Vec<StashEvent>, at: SystemTime) -> Vec<StashEvent> {
+        let mut removed = HashMap::new();
+        for event in &events {
+            match event {
+                StashEvent::ItemRemoved { stash_id, item, .. } => {
+                    removed.insert(item.id.clone(), (stash_id.clone(), item.clone()));
+                }
+                StashEvent::StashEmptied {
+                    stash_id, items, ..
+                } => {
+                    for item in items {
+                        removed.insert(item.id.clone(), (stash_id.clone(), item.clone()));
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !events.iter().any(
+            |e| matches!(e, StashEvent::ItemAdded { item, .. } if removed.contains_key(&item.id)),
+        ) {
+            return events;
+        }
+
+        let mut moved = HashSet::new();
+        let mut merged = Vec::with_capacity(events.len());
+        for event in events {
+            let (stash_id, item, from, old) = match event {
+                StashEvent::ItemAdded { stash_id, item } => match removed.remove(&item.id) {
+                    Some((from, old)) => (stash_id, item, from, old),
+                    None => {
+                        merged.push(StashEvent::ItemAdded { stash_id, item });
+                        continue;
+                    }
+                },
+                event => {
+                    merged.push(event);
+                    continue;
+                }
+            };
+            if let Some(tracked) = self
+                .stashes
+                .get_mut(&stash_id)
+                .and_then(|s| s.items.get_mut(&item.id))
+            {
+                tracked.listed_at = old.listed_at;
+            }
+            moved.insert(item.id.clone());
+            let price_changed = old.price != item.price;
+            merged.push(StashEvent::ItemMoved {
+                from,
+                to: stash_id.clone(),
+                item: old.clone(),
+                at,
+            });
+            if price_changed {
+                merged.push(StashEvent::PriceChanged {
+                    stash_id,
+                    item_id: item.id,
+                    old: old.price,
+                    new: item.price,
+                    at,
+                });
+            }
+        }
+
+        merged
+            .into_iter()
+            .filter_map(|event| match event {
+                StashEvent::ItemRemoved { ref item, .. } if moved.contains(&item.id) => None,
+                StashEvent::StashEmptied {
+                    stash_id,
+                    mut items,
+                    at,
+                } => {
+                    items.retain(|i| !moved.contains(&i.id));
+                    (!items.is_empty()).then_some(StashEvent::StashEmptied {
+                        stash_id,
+                        items,
+                        at,
+                    })
+                }
+                event => Some(event),
+            })
+            .collect()
+    }
+
+    pub fn apply(&mut self, change: &PublicStashChange, at: SystemTime) -> Vec<StashEvent> {
+        let stash_id = &change.id;
+        if !change.public || change.items.is_empty() {
+            return match self.stashes.remove(stash_id) {
+                Some(prev) if !prev.items.is_empty() => {
+                    for id in prev.items.keys() {
+                        self.item_stashes.remove(id);
+                    }
+                    let mut items: Vec<_> = prev.items.into_values().collect();
+                    items.sort_by(|a, b| a.id.cmp(&b.id));
+                    vec![StashEvent::StashEmptied {
+                        stash_id: stash_id.clone(),
+                        items,
+                        at,
+                    }]
+                }
+                _ => vec![],
+            };
+        }
+
+        let mut prev = self.stashes.remove(stash_id).unwrap_or_default();
+        let mut events = vec![];
+        let mut items = HashMap::with_capacity(change.items.len());
+        // items without id can't be tracked between changes
+        for item in change.items.iter().filter(|i| i.id.is_some()) {
+            let id = item.id.clone().unwrap_or_default();
+            let price = item_price(item, change.stash.as_deref());
+            let tracked = match prev
+                .items
+                .remove(&id)
+                .or_else(|| self.take_moved(&id, stash_id, at, &mut events))
+            {
+                Some(mut old) => {
+                    if old.price != price {
+                        events.push(StashEvent::PriceChanged {
+                            stash_id: stash_id.clone(),
+                            item_id: id.clone(),
+                            old: old.price.take(),
+                            new: price.clone(),
+                            at,
+                        });
+                        old.price = price;
+                    }
+                    old
+                }
+                None => {
+                    let tracked = TrackedItem {
+                        id: id.clone(),
+                        basetype: item.base_type.clone(),
+                        name: item.name.clone(),
+                        // unique or relic frame
+                        unique: matches!(item.frame_type, Some(3) | Some(9)),
+                        stat_ids: stat_ids(item),
+                        price,
+                        listed_at: at,
+                    };
+                    events.push(StashEvent::ItemAdded {
+                        stash_id: stash_id.clone(),
+                        item: tracked.clone(),
+                    });
+                    tracked
+                }
+            };
+            self.item_stashes.insert(id.clone(), stash_id.clone());
+            items.insert(id, tracked);
+        }
+
+        for id in prev.items.keys() {
+            self.item_stashes.remove(id);
+        }
+        let mut removed: Vec<_> = prev.items.into_values().collect();
+        removed.sort_by(|a, b| a.id.cmp(&b.id));
+        events.extend(removed.into_iter().map(|item| StashEvent::ItemRemoved {
+            stash_id: stash_id.clone(),
+            item,
+            at,
+        }));
+
+        self.stashes.insert(
+            stash_id.clone(),
+            StashSnapshot {
+                league: change.league.clone(),
+                items,
+            },
+        );
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use domain::build_calculation::stored_item::Price;
+    use serde_json::json;
+
+    use super::{StashEvent, StashTracker};
+    use crate::models::{PublicStashChange, PublicStashData};
+
+    fn stash(items: &[(&str, Option<&str>)]) -> PublicStashChange {
+        stash_with_id("stash1", items)
+    }
+
+    fn stash_with_id(stash_id: &str, items: &[(&str, Option<&str>)]) -> PublicStashChange {
+        let items: Vec<_> = items
+            .iter()
+            .map(|(id, note)| {
+                json!({
+                    "verified": false, "w": 1, "h": 1, "icon": "",
+                    "name": "", "typeLine": "Coral Ring", "baseType": "Coral Ring",
+                    "identified": true, "id": id, "note": note,
+                })
+            })
+            .collect();
+        serde_json::from_value(json!({
+            "id": stash_id,
+            "public": true,
+            "accountName": "acc",
+            "stash": "~price 1 chaos",
+            "stashType": "PremiumStash",
+            "league": "Standard",
+            "items": items,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn stash_events() {
+        let t0 = SystemTime::UNIX_EPOCH;
+        let t1 = t0 + Duration::from_secs(60);
+        let mut tracker = StashTracker::new();
+
+        let events = tracker.apply(&stash(&[("a", None), ("b", Some("~b/o 5 divine"))]), t0);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            &events[0],
+            StashEvent::ItemAdded { item, .. } if item.price == Some(Price::Chaos(1))
+        ));
+        assert_eq!(tracker.league("stash1"), Some("Standard"));
+
+        let events = tracker.apply(&stash(&[("b", Some("~b/o 4 divine")), ("c", None)]), t1);
+        assert_eq!(events.len(), 3);
+        assert!(matches!(
+            &events[0],
+            StashEvent::PriceChanged { item_id, old: Some(Price::Divine(5)), new: Some(Price::Divine(4)), .. }
+                if item_id == "b"
+        ));
+        assert!(matches!(&events[1], StashEvent::ItemAdded { item, .. } if item.id == "c"));
+        assert!(matches!(
+            &events[2],
+            StashEvent::ItemRemoved { item, at, .. } if item.id == "a" && item.listed_at == t0 && *at == t1
+        ));
+        // price change keeps original listing time
+        assert!(tracker
+            .items("stash1")
+            .any(|i| i.id == "b" && i.listed_at == t0));
+
+        let events = tracker.apply(&stash(&[]), t1);
+        assert!(matches!(
+            &events[..],
+            [StashEvent::StashEmptied { items, .. }] if items.len() == 2
+        ));
+        assert!(tracker.is_empty());
+        assert!(tracker.apply(&stash(&[]), t1).is_empty());
+    }
+
+    #[test]
+    fn moved_items() {
+        let t0 = SystemTime::UNIX_EPOCH;
+        let t1 = t0 + Duration::from_secs(60);
+        let mut tracker = StashTracker::new();
+        tracker.apply(&stash_with_id("stash1", &[("a", None), ("b", None)]), t0);
+
+        // item appears in stash2 before stash1 update is seen
+        let events = tracker.apply(
+            &stash_with_id("stash2", &[("a", Some("~price 2 chaos"))]),
+            t1,
+        );
+        assert!(matches!(
+            &events[..],
+            [
+                StashEvent::ItemMoved { from, to, item, .. },
+                StashEvent::PriceChanged { new: Some(Price::Chaos(2)), .. },
+            ] if from == "stash1" && to == "stash2" && item.listed_at == t0
+        ));
+        // moved item is not reported as removed from stash1
+        let events = tracker.apply(&stash_with_id("stash1", &[("b", None)]), t1);
+        assert!(events.is_empty());
+        assert!(tracker
+            .items("stash2")
+            .any(|i| i.id == "a" && i.listed_at == t0));
+    }
+
+    #[test]
+    fn moves_in_one_change() {
+        let t0 = SystemTime::UNIX_EPOCH;
+        let t1 = t0 + Duration::from_secs(60);
+        let mut tracker = StashTracker::new();
+        tracker.apply(&stash_with_id("stash1", &[("a", None), ("b", None)]), t0);
+        tracker.apply(&stash_with_id("stash2", &[("c", None)]), t0);
+
+        // stash1 update comes first, so "a" is removed before it appears in stash2
+        let data = PublicStashData {
+            next_change_id: String::new(),
+            stashes: vec![
+                stash_with_id("stash1", &[("b", None)]),
+                stash_with_id("stash2", &[]),
+                stash_with_id("stash3", &[("a", None), ("c", Some("~price 3 chaos"))]),
+            ],
+        };
+        let events = tracker.apply_data(&data, t1);
+        assert!(matches!(
+            &events[..],
+            [
+                StashEvent::ItemMoved { from: a_from, item: a, .. },
+                StashEvent::ItemMoved { from: c_from, item: c, .. },
+                StashEvent::PriceChanged { new: Some(Price::Chaos(3)), .. },
+            ] if a_from == "stash1" && a.id == "a" && c_from == "stash2" && c.id == "c"
+        ));
+        assert!(tracker.items("stash3").all(|i| i.listed_at == t0));
+    }
+}
@@ -0,0 +1,462 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use domain::build_calculation::stored_item::{Price, StoredItem};
+use serde::{Deserialize, Serialize};
+
+use crate::{db_enum, mod_value, ItemQuery, StorageError};
+
+// bump when file format changes
+pub const INDEX_VERSION: u32 = 4;
+const META_FILE: &str = "meta.json";
+const ITEMS_FILE: &str = "items.bin";
+const POSTINGS_FILE: &str = "postings.bin";
+
+// position of item in index
+pub type DocId = u32;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IndexedItem {
+    pub id: String,
+    pub basetype: String,
+    pub subcategory: String,
+    pub price: Price,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Posting {
+    pub value: i32,
+    pub doc: DocId,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Meta {
+    version: u32,
+    items: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct Postings {
+    // stat id -> postings sorted by value
+    stats: HashMap<String, Vec<Posting>>,
+    // stat id -> sorted doc ids of mods without parsed value
+    unvalued_stats: HashMap<String, Vec<DocId>>,
+    // facets -> sorted doc ids
+    basetypes: HashMap<String, Vec<DocId>>,
+    subcategories: HashMap<String, Vec<DocId>>,
+    // currency -> postings sorted by value
+    prices: HashMap<String, Vec<Posting>>,
+}
+
+// collects items, last added item with same id wins
+#[derive(Default)]
+pub struct ItemIndexBuilder {
+    items: HashMap<String, StoredItem>,
+}
+
+impl ItemIndexBuilder {
+    pub fn new() -> ItemIndexBuilder {
+        ItemIndexBuilder::default()
+    }
+
+    pub fn add(&mut self, item: StoredItem) {
+        self.items.insert(item.id.clone(), item);
+    }
+
+    pub fn build(self) -> ItemIndex {
+        let mut items: Vec<_> = self.items.into_values().collect();
+        items.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut postings = Postings::default();
+        for (doc, item) in items.iter().enumerate() {
+            let doc = doc as DocId;
+            for m in item.info.mods() {
+                match mod_value(&m.text) {
+                    Some(value) => postings
+                        .stats
+                        .entry(m.stat_id.clone())
+                        .or_default()
+                        .push(Posting { value, doc }),
+                    None => postings
+                        .unvalued_stats
+                        .entry(m.stat_id.clone())
+                        .or_default()
+                        .push(doc),
+                }
+            }
+            postings
+                .basetypes
+                .entry(item.basetype.clone())
+                .or_default()
+                .push(doc);
+            postings
+                .subcategories
+                .entry(db_enum(&item.subcategory))
+                .or_default()
+                .push(doc);
+            if !item.price.is_zero() {
+                let (currency, value) = item.price.parts();
+                postings
+                    .prices
+                    .entry(currency.to_string())
+                    .or_default()
+                    .push(Posting { value, doc });
+            }
+        }
+        postings.stats.values_mut().for_each(|p| p.sort());
+        postings.prices.values_mut().for_each(|p| p.sort());
+        postings.unvalued_stats.values_mut().for_each(|d| d.dedup());
+
+        let items = items
+            .into_iter()
+            .map(|it| IndexedItem {
+                subcategory: db_enum(&it.subcategory),
+                id: it.id,
+                basetype: it.basetype,
+                price: it.price,
+            })
+            .collect();
+        ItemIndex { items, postings }
+    }
+}
+
+pub struct ItemIndex {
+    items: Vec<IndexedItem>,
+    postings: Postings,
+}
+
+fn intersect(a: &[DocId], b: &[DocId]) -> Vec<DocId> {
+    let (mut i, mut j) = (0, 0);
+    let mut res = Vec::with_capacity(a.len().min(b.len()));
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                res.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    res
+}
+
+// postings with value in range, postings are sorted by value
+fn posting_range(postings: &[Posting], min: Option<i32>, max: Option<i32>) -> &[Posting] {
+    let start = min.map_or(0, |min| postings.partition_point(|p| p.value < min));
+    let end = max.map_or(postings.len(), |max| {
+        postings.partition_point(|p| p.value <= max)
+    });
+    &postings[start..end.max(start)]
+}
+
+// one filter of query, its size is upper bound on number of matched items
+enum Candidates<'a> {
+    Docs(&'a [DocId]),
+    Postings(&'a [Posting]),
+    // unbounded stat filter, postings of valued mods and docs of unvalued ones
+    Stat(&'a [Posting], &'a [DocId]),
+}
+
+impl Candidates<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Candidates::Docs(d) => d.len(),
+            Candidates::Postings(p) => p.len(),
+            Candidates::Stat(p, d) => p.len() + d.len(),
+        }
+    }
+
+    fn docs(&self) -> Vec<DocId> {
+        match self {
+            Candidates::Docs(d) => d.to_vec(),
+            Candidates::Postings(p) => {
+                let mut docs: Vec<_> = p.iter().map(|p| p.doc).collect();
+                docs.sort();
+                docs.dedup();
+                docs
+            }
+            Candidates::Stat(p, d) => {
+                let mut docs: Vec<_> = p.iter().map(|p| p.doc).chain(d.iter().copied()).collect();
+                docs.sort();
+                docs.dedup();
+                docs
+            }
+        }
+    }
+}
+
+impl ItemIndex {
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn get(&self, doc: DocId) -> Option<&IndexedItem> {
+        self.items.get(doc as usize)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, dir: P) -> Result<(), StorageError> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        let mut w = BufWriter::new(File::create(dir.join(ITEMS_FILE))?);
+        bincode::serialize_into(&mut w, &self.items)?;
+        w.flush()?;
+        let mut w = BufWriter::new(File::create(dir.join(POSTINGS_FILE))?);
+        bincode::serialize_into(&mut w, &self.postings)?;
+        w.flush()?;
+        // meta is written last, so partially written index can't be opened
+        let meta = Meta {
+            version: INDEX_VERSION,
+            items: self.items.len(),
+        };
+        serde_json::to_writer(File::create(dir.join(META_FILE))?, &meta)?;
+        Ok(())
+    }
+
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<ItemIndex, StorageError> {
+        let dir = dir.as_ref();
+        let meta: Meta = serde_json::from_reader(File::open(dir.join(META_FILE))?)?;
+        if meta.version != INDEX_VERSION {
+            return Err(StorageError::IndexVersion(meta.version));
+        }
+        let items: Vec<IndexedItem> =
+            bincode::deserialize_from(BufReader::new(File::open(dir.join(ITEMS_FILE))?))?;
+        if items.len() != meta.items {
+            return Err(StorageError::Decode(format!(
+                "expected {} items, found {}",
+                meta.items,
+                items.len()
+            )));
+        }
+        let postings =
+            bincode::deserialize_from(BufReader::new(File::open(dir.join(POSTINGS_FILE))?))?;
+        Ok(ItemIndex { items, postings })
+    }
+
+    fn candidates(&self, query: &ItemQuery) -> Vec<Candidates<'_>> {
+        let p = &self.postings;
+        let mut candidates = vec![];
+        if let Some(basetype) = &query.basetype {
+            let docs = p.basetypes.get(basetype).map(|d| &d[..]);
+            candidates.push(Candidates::Docs(docs.unwrap_or_default()));
+        }
+        if let Some(subcategory) = &query.subcategory {
+            let docs = p.subcategories.get(&db_enum(subcategory)).map(|d| &d[..]);
+            candidates.push(Candidates::Docs(docs.unwrap_or_default()));
+        }
+        for stat in &query.stats {
+            let postings = p.stats.get(&stat.stat_id).map(|p| &p[..]);
+            if stat.is_bounded() {
+                candidates.push(Candidates::Postings(posting_range(
+                    postings.unwrap_or_default(),
+                    stat.min,
+                    stat.max,
+                )));
+            } else {
+                let docs = p.unvalued_stats.get(&stat.stat_id).map(|d| &d[..]);
+                candidates.push(Candidates::Stat(
+                    postings.unwrap_or_default(),
+                    docs.unwrap_or_default(),
+                ));
+            }
+        }
+        if let Some(range) = &query.price {
+            let postings = p.prices.get(&range.currency).map(|p| &p[..]);
+            candidates.push(Candidates::Postings(posting_range(
+                postings.unwrap_or_default(),
+                range.min,
+                range.max,
+            )));
+        }
+        candidates
+    }
+
+    // number of candidates for each filter of query in order of evaluation
+    pub fn plan(&self, query: &ItemQuery) -> Vec<usize> {
+        let mut sizes: Vec<_> = self.candidates(query).iter().map(|c| c.len()).collect();
+        sizes.sort();
+        sizes
+    }
+
+    // same semantics as `ItemRepository::search`, starts from most selective filter
+    pub fn search(&self, query: &ItemQuery) -> Vec<&IndexedItem> {
+        let mut candidates = self.candidates(query);
+        candidates.sort_by_key(|c| c.len());
+
+        let docs = match candidates.split_first() {
+            Some((first, rest)) => {
+                let mut docs = first.docs();
+                for c in rest {
+                    if docs.is_empty() {
+                        break;
+                    }
+                    docs = intersect(&docs, &c.docs());
+                }
+                docs
+            }
+            None => (0..self.items.len() as DocId).collect(),
+        };
+        docs.into_iter()
+            .take(query.limit.unwrap_or(usize::MAX))
+            .map(|d| &self.items[d as usize])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::{
+        build_calculation::stored_item::{ItemInfo, Mod, Price},
+        item::types::Subcategory,
+    };
+
+    use super::{ItemIndex, ItemIndexBuilder};
+    use crate::{
+        tests::{ring, stat_id},
+        ItemQuery, PriceRange, StatRange,
+    };
+
+    #[test]
+    fn build_search_and_reopen() -> anyhow::Result<()> {
+        let mut builder = ItemIndexBuilder::new();
+        builder.add(ring("1", Price::Chaos(10), &["+80 to maximum Life"]));
+        builder.add(ring(
+            "2",
+            Price::Chaos(50),
+            &["+60 to maximum Life", "+30% to Fire Resistance"],
+        ));
+        builder.add(ring("3", Price::Divine(1), &["+30% to Fire Resistance"]));
+        // replaces first item
+        builder.add(ring("1", Price::Chaos(20), &["+90 to maximum Life"]));
+        let index = builder.build();
+        assert_eq!(index.len(), 3);
+
+        let life = &index.get(0).unwrap();
+        assert_eq!(life.price, Price::Chaos(20));
+        let query = ItemQuery {
+            stats: vec![StatRange::any(stat_id("+1 to maximum Life"))],
+            price: Some(PriceRange {
+                currency: "chaos".to_string(),
+                min: Some(15),
+                max: Some(60),
+            }),
+            ..Default::default()
+        };
+        let ids: Vec<_> = index.search(&query).iter().map(|i| i.id.clone()).collect();
+        assert_eq!(ids, ["1", "2"]);
+
+        let dir = tempfile::tempdir()?;
+        index.save(dir.path())?;
+        let reopened = ItemIndex::open(dir.path())?;
+        let reopened_ids: Vec<_> = reopened
+            .search(&query)
+            .iter()
+            .map(|i| i.id.clone())
+            .collect();
+        assert_eq!(reopened_ids, ids);
+        assert_eq!(
+            reopened
+                .search(&ItemQuery {
+                    subcategory: Some(Subcategory::Ring),
+                    limit: Some(1),
+                    ..Default::default()
+                })
+                .len(),
+            1
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn value_ranges() {
+        let mut builder = ItemIndexBuilder::new();
+        builder.add(ring("1", Price::Chaos(20), &["+90 to maximum Life"]));
+        builder.add(ring(
+            "2",
+            Price::Chaos(50),
+            &["+60 to maximum Life", "+30% to Fire Resistance"],
+        ));
+        builder.add(ring("3", Price::Chaos(5), &["+30% to Fire Resistance"]));
+        builder.add(ring(
+            "4",
+            Price::Chaos(70),
+            &["+85 to maximum Life", "+40% to Fire Resistance"],
+        ));
+        let index = builder.build();
+
+        let query = ItemQuery {
+            stats: vec![
+                StatRange::any(stat_id("+1% to Fire Resistance")),
+                StatRange::new(stat_id("+1 to maximum Life"), Some(80), None),
+            ],
+            ..Default::default()
+        };
+        // life filter is evaluated first
+        assert_eq!(index.plan(&query), [2, 3]);
+        let ids: Vec<_> = index.search(&query).iter().map(|i| i.id.clone()).collect();
+        assert_eq!(ids, ["4"]);
+
+        let query = ItemQuery {
+            stats: vec![StatRange::new(
+                stat_id("+1% to Fire Resistance"),
+                Some(31),
+                Some(50),
+            )],
+            price: Some(PriceRange {
+                currency: "chaos".to_string(),
+                min: None,
+                max: Some(60),
+            }),
+            ..Default::default()
+        };
+        assert!(index.search(&query).is_empty());
+    }
+
+    #[test]
+    fn unparsed_values() {
+        let mut builder = ItemIndexBuilder::new();
+        builder.add(ring("1", Price::Chaos(20), &["+90 to maximum Life"]));
+        let mut item = ring("2", Price::Chaos(5), &[]);
+        if let ItemInfo::Accessory { mods, .. } = &mut item.info {
+            mods.push(Mod {
+                stat_id: stat_id("+1 to maximum Life"),
+                text: "unknown text".to_string(),
+            });
+        }
+        builder.add(item);
+        let index = builder.build();
+
+        let ids = |stat| -> Vec<_> {
+            index
+                .search(&ItemQuery {
+                    stats: vec![stat],
+                    ..Default::default()
+                })
+                .iter()
+                .map(|i| i.id.clone())
+                .collect()
+        };
+        assert_eq!(
+            ids(StatRange::any(stat_id("+1 to maximum Life"))),
+            ["1", "2"]
+        );
+        assert_eq!(
+            ids(StatRange::new(
+                stat_id("+1 to maximum Life"),
+                None,
+                Some(10)
+            )),
+            Vec::<String>::new(),
+            "unparsed value is not indexed as 0"
+        );
+    }
+}
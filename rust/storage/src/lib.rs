@@ -1,2 +1,225 @@
+mod index;
+mod memory;
+mod postgres;
 
+use async_trait::async_trait;
+use domain::{
+    build_calculation::stored_item::StoredItem,
+    item::types::{Mod as DomainMod, ModType, Subcategory},
+};
+use thiserror::Error;
 
+pub use index::{DocId, IndexedItem, ItemIndex, ItemIndexBuilder, Posting, INDEX_VERSION};
+pub use memory::MemoryItemRepository;
+pub use postgres::PgItemRepository;
+
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("decode error: {0}")]
+    Decode(String),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("bincode error: {0}")]
+    Bincode(#[from] bincode::Error),
+    #[error("unsupported index version: {0}")]
+    IndexVersion(u32),
+}
+
+// price filter, currency is "chaos", "divine" or custom currency name
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PriceRange {
+    pub currency: String,
+    pub min: Option<i32>,
+    pub max: Option<i32>,
+}
+
+// mod filter, value is first numeric value of mod
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StatRange {
+    pub stat_id: String,
+    pub min: Option<i32>,
+    pub max: Option<i32>,
+}
+
+impl StatRange {
+    // matches any item with this mod
+    pub fn any<T: Into<String>>(stat_id: T) -> StatRange {
+        StatRange {
+            stat_id: stat_id.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn new<T: Into<String>>(stat_id: T, min: Option<i32>, max: Option<i32>) -> StatRange {
+        StatRange {
+            stat_id: stat_id.into(),
+            min,
+            max,
+        }
+    }
+
+    pub fn is_bounded(&self) -> bool {
+        self.min.is_some() || self.max.is_some()
+    }
+
+    pub fn contains(&self, value: i32) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ItemQuery {
+    pub basetype: Option<String>,
+    pub subcategory: Option<Subcategory>,
+    // item should have all mods
+    pub stats: Vec<StatRange>,
+    pub price: Option<PriceRange>,
+    pub limit: Option<usize>,
+}
+
+impl ItemQuery {
+    pub fn matches(&self, item: &StoredItem) -> bool {
+        if self.basetype.as_ref().is_some_and(|b| *b != item.basetype) {
+            return false;
+        }
+        if self
+            .subcategory
+            .as_ref()
+            .is_some_and(|s| *s != item.subcategory)
+        {
+            return false;
+        }
+        let mods = item.info.mods();
+        if !self.stats.iter().all(|s| {
+            mods.iter().any(|m| {
+                m.stat_id == s.stat_id
+                    && (!s.is_bounded() || mod_value(&m.text).is_some_and(|v| s.contains(v)))
+            })
+        }) {
+            return false;
+        }
+        if let Some(range) = &self.price {
+            let (currency, value) = item.price.parts();
+            if currency != range.currency
+                || range.min.is_some_and(|min| value < min)
+                || range.max.is_some_and(|max| value > max)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// none when mod text can't be parsed, such mods match only unbounded filters
+pub(crate) fn mod_value(text: &str) -> Option<i32> {
+    DomainMod::try_by_stat(text, ModType::Explicit)
+        .ok()
+        .and_then(|m| m.numeric_value.first_int())
+}
+
+// same representation as elixir Ecto.Enum values, e.g. BodyArmour -> body_armour
+pub(crate) fn db_enum<T: AsRef<str>>(v: &T) -> String {
+    let mut s = String::new();
+    for (i, c) in v.as_ref().chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            s.push('_');
+        }
+        s.push(c.to_ascii_lowercase());
+    }
+    s
+}
+
+pub(crate) fn from_db_enum<T: std::str::FromStr>(v: &str) -> Result<T, StorageError> {
+    v.replace('_', "")
+        .parse()
+        .map_err(|_| StorageError::Decode(format!("unknown enum value: {}", v)))
+}
+
+#[async_trait]
+pub trait ItemRepository: Send + Sync {
+    // inserts items or replaces existing ones with same id
+    async fn upsert_stash_items(
+        &self,
+        stash_id: &str,
+        items: &[StoredItem],
+    ) -> Result<(), StorageError>;
+    // removes stash with all its items, returns number of removed items
+    async fn remove_stash(&self, stash_id: &str) -> Result<u64, StorageError>;
+    async fn get_item(&self, id: &str) -> Result<Option<StoredItem>, StorageError>;
+    async fn search(&self, query: &ItemQuery) -> Result<Vec<StoredItem>, StorageError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::{
+        build_calculation::stored_item::{ItemInfo, Mod, Price, StoredItem},
+        item::types::{Category, Mod as DomainMod, ModType, Subcategory},
+    };
+
+    use super::{db_enum, from_db_enum, ItemQuery, StatRange};
+
+    pub(crate) fn ring(id: &str, price: Price, mods: &[&str]) -> StoredItem {
+        StoredItem {
+            id: id.to_string(),
+            basetype: "Coral Ring".to_string(),
+            category: Category::Accessories,
+            subcategory: Subcategory::Ring,
+            info: ItemInfo::Accessory {
+                quality: 0,
+                mods: mods
+                    .iter()
+                    .map(|m| Mod::from(DomainMod::try_by_stat(m, ModType::Explicit).unwrap()))
+                    .collect(),
+            },
+            price,
+            rarity: "rare".to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn stat_id(text: &str) -> String {
+        DomainMod::try_by_stat(text, ModType::Explicit)
+            .unwrap()
+            .stat_id
+    }
+
+    #[test]
+    fn unparsed_mod_values() {
+        let mut item = ring("1", Price::Chaos(1), &["+80 to maximum Life"]);
+        let life = stat_id("+80 to maximum Life");
+        if let ItemInfo::Accessory { mods, .. } = &mut item.info {
+            mods.push(Mod {
+                stat_id: stat_id("+30% to Fire Resistance"),
+                text: "unknown text".to_string(),
+            });
+        }
+        let fire_res = stat_id("+30% to Fire Resistance");
+        let query = |stats| ItemQuery {
+            stats,
+            ..Default::default()
+        };
+
+        assert!(query(vec![StatRange::new(&life, Some(80), Some(80))]).matches(&item));
+        assert!(query(vec![StatRange::any(&fire_res)]).matches(&item));
+        // unparsed value is not treated as 0
+        assert!(!query(vec![StatRange::new(&fire_res, None, Some(10))]).matches(&item));
+    }
+
+    #[test]
+    fn enum_names() -> anyhow::Result<()> {
+        assert_eq!(db_enum(&Subcategory::BodyArmour), "body_armour");
+        assert_eq!(db_enum(&Category::Weapons), "weapons");
+        assert_eq!(
+            from_db_enum::<Subcategory>("utility_flask")?,
+            Subcategory::UtilityFlask
+        );
+        assert_eq!(from_db_enum::<Category>("gems")?, Category::Gems);
+        assert!(from_db_enum::<Category>("unknown").is_err());
+        Ok(())
+    }
+}
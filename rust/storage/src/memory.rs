@@ -0,0 +1,173 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::RwLock,
+};
+
+use async_trait::async_trait;
+use domain::build_calculation::stored_item::StoredItem;
+
+use crate::{ItemQuery, ItemRepository, StorageError};
+
+#[derive(Default)]
+struct MemoryState {
+    items: HashMap<String, StoredItem>,
+    stashes: HashMap<String, HashSet<String>>,
+    // item belongs to one stash, the last one it was seen in
+    item_stashes: HashMap<String, String>,
+}
+
+// keeps everything in memory, used in tests and experiments
+#[derive(Default)]
+pub struct MemoryItemRepository {
+    state: RwLock<MemoryState>,
+}
+
+impl MemoryItemRepository {
+    pub fn new() -> MemoryItemRepository {
+        MemoryItemRepository::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.read().unwrap().items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait]
+impl ItemRepository for MemoryItemRepository {
+    async fn upsert_stash_items(
+        &self,
+        stash_id: &str,
+        items: &[StoredItem],
+    ) -> Result<(), StorageError> {
+        let mut state = self.state.write().unwrap();
+        for item in items {
+            state.items.insert(item.id.clone(), item.clone());
+            if let Some(prev) = state
+                .item_stashes
+                .insert(item.id.clone(), stash_id.to_string())
+                .filter(|prev| prev != stash_id)
+            {
+                if let Some(ids) = state.stashes.get_mut(&prev) {
+                    ids.remove(&item.id);
+                }
+            }
+            state
+                .stashes
+                .entry(stash_id.to_string())
+                .or_default()
+                .insert(item.id.clone());
+        }
+        Ok(())
+    }
+
+    async fn remove_stash(&self, stash_id: &str) -> Result<u64, StorageError> {
+        let mut state = self.state.write().unwrap();
+        let ids = state.stashes.remove(stash_id).unwrap_or_default();
+        let mut removed = 0;
+        for id in ids {
+            state.item_stashes.remove(&id);
+            if state.items.remove(&id).is_some() {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    async fn get_item(&self, id: &str) -> Result<Option<StoredItem>, StorageError> {
+        Ok(self.state.read().unwrap().items.get(id).cloned())
+    }
+
+    async fn search(&self, query: &ItemQuery) -> Result<Vec<StoredItem>, StorageError> {
+        let state = self.state.read().unwrap();
+        let mut items: Vec<_> = state
+            .items
+            .values()
+            .filter(|it| query.matches(it))
+            .cloned()
+            .collect();
+        items.sort_by(|a, b| a.id.cmp(&b.id));
+        if let Some(limit) = query.limit {
+            items.truncate(limit);
+        }
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::{build_calculation::stored_item::Price, item::types::Subcategory};
+
+    use super::MemoryItemRepository;
+    use crate::{
+        tests::{ring, stat_id},
+        ItemQuery, ItemRepository, PriceRange, StatRange,
+    };
+
+    const LIFE: &str = "+80 to maximum Life";
+    const FIRE_RES: &str = "+30% to Fire Resistance";
+
+    #[tokio::test]
+    async fn stash_items() -> anyhow::Result<()> {
+        let repo = MemoryItemRepository::new();
+        repo.upsert_stash_items(
+            "stash1",
+            &[
+                ring("1", Price::Chaos(10), &[LIFE]),
+                ring("2", Price::Chaos(50), &[LIFE, FIRE_RES]),
+                ring("3", Price::Divine(1), &[FIRE_RES]),
+            ],
+        )
+        .await?;
+        repo.upsert_stash_items("stash2", &[ring("4", Price::Chaos(5), &[])])
+            .await?;
+        assert_eq!(repo.len(), 4);
+
+        // upsert replaces item
+        repo.upsert_stash_items("stash1", &[ring("1", Price::Chaos(20), &[LIFE])])
+            .await?;
+        assert_eq!(repo.get_item("1").await?.unwrap().price, Price::Chaos(20));
+
+        let found = repo
+            .search(&ItemQuery {
+                subcategory: Some(Subcategory::Ring),
+                stats: vec![StatRange::any(stat_id(LIFE))],
+                price: Some(PriceRange {
+                    currency: "chaos".to_string(),
+                    min: None,
+                    max: Some(30),
+                }),
+                ..Default::default()
+            })
+            .await?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, "1");
+
+        assert_eq!(repo.remove_stash("stash1").await?, 3);
+        assert!(repo.get_item("2").await?.is_none());
+        assert_eq!(
+            repo.search(&ItemQuery::default()).await?.len(),
+            1,
+            "only stash2 item left"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn moved_item() -> anyhow::Result<()> {
+        let repo = MemoryItemRepository::new();
+        repo.upsert_stash_items("stash1", &[ring("1", Price::Chaos(10), &[LIFE])])
+            .await?;
+        repo.upsert_stash_items("stash2", &[ring("1", Price::Chaos(15), &[LIFE])])
+            .await?;
+
+        assert_eq!(repo.remove_stash("stash1").await?, 0, "item left stash1");
+        assert!(repo.get_item("1").await?.is_some());
+        assert_eq!(repo.remove_stash("stash2").await?, 1);
+        assert!(repo.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,316 @@
+use async_trait::async_trait;
+use domain::build_calculation::stored_item::{ItemInfo, Price, StoredItem};
+use sqlx::{postgres::PgRow, types::Json, PgPool, Postgres, QueryBuilder, Row};
+
+use crate::{db_enum, from_db_enum, ItemQuery, ItemRepository, StorageError};
+
+const ITEM_COLUMNS: &str =
+    "id, basetype, category, subcategory, info, name, price, rarity, sockets, game_version";
+// rows fetched at once when stat value ranges are checked after fetching
+const BOUNDED_SEARCH_PAGE: usize = 500;
+
+// uses `items` and `stashes` tables created by elixir migrations
+pub struct PgItemRepository {
+    pool: PgPool,
+}
+
+impl PgItemRepository {
+    pub fn new(pool: PgPool) -> PgItemRepository {
+        PgItemRepository { pool }
+    }
+
+    pub async fn connect(url: &str) -> Result<PgItemRepository, StorageError> {
+        Ok(PgItemRepository::new(PgPool::connect(url).await?))
+    }
+}
+
+fn item_from_row(row: &PgRow) -> Result<StoredItem, StorageError> {
+    let Json(info): Json<ItemInfo> = row.try_get("info")?;
+    let Json(price): Json<Price> = row.try_get("price")?;
+    Ok(StoredItem {
+        id: row.try_get("id")?,
+        basetype: row.try_get("basetype")?,
+        category: from_db_enum(row.try_get("category")?)?,
+        subcategory: from_db_enum(row.try_get("subcategory")?)?,
+        info,
+        name: row.try_get("name")?,
+        price,
+        rarity: row.try_get("rarity")?,
+        sockets: row.try_get("sockets")?,
+        game_version: from_db_enum(row.try_get("game_version")?)?,
+    })
+}
+
+fn push_conditions(qb: &mut QueryBuilder<'_, Postgres>, query: &ItemQuery) {
+    qb.push(" WHERE TRUE");
+    if let Some(basetype) = &query.basetype {
+        qb.push(" AND basetype = ").push_bind(basetype.clone());
+    }
+    if let Some(subcategory) = &query.subcategory {
+        qb.push(" AND subcategory = ")
+            .push_bind(db_enum(subcategory));
+    }
+    if !query.stats.is_empty() {
+        // values are stored only in mod text, so ranges are checked after fetching.
+        // expression must match GIN index created by elixir migrations
+        let stat_ids: Vec<_> = query.stats.iter().map(|s| s.stat_id.clone()).collect();
+        qb.push(" AND jsonb_path_query_array(info, '$.mods[*].stat_id') @> ")
+            .push_bind(Json(stat_ids))
+            .push("::jsonb");
+    }
+    if let Some(range) = &query.price {
+        // price is stored as {"Chaos": 10}, {"Divine": 1} or {"Custom": ["exalted", 3]}
+        let value = match range.currency.as_str() {
+            "chaos" => "(price->>'Chaos')::int",
+            "divine" => "(price->>'Divine')::int",
+            _ => {
+                qb.push(" AND price->'Custom'->>0 = ")
+                    .push_bind(range.currency.clone());
+                "(price->'Custom'->>1)::int"
+            }
+        };
+        qb.push(format!(" AND {} IS NOT NULL", value));
+        if let Some(min) = range.min {
+            qb.push(format!(" AND {} >= ", value)).push_bind(min);
+        }
+        if let Some(max) = range.max {
+            qb.push(format!(" AND {} <= ", value)).push_bind(max);
+        }
+    }
+}
+
+#[async_trait]
+impl ItemRepository for PgItemRepository {
+    async fn upsert_stash_items(
+        &self,
+        stash_id: &str,
+        items: &[StoredItem],
+    ) -> Result<(), StorageError> {
+        if items.is_empty() {
+            return Ok(());
+        }
+        let mut tx = self.pool.begin().await?;
+
+        let mut qb =
+            QueryBuilder::<Postgres>::new(format!("INSERT INTO items ({}) ", ITEM_COLUMNS));
+        qb.push_values(items, |mut b, it| {
+            b.push_bind(it.id.clone())
+                .push_bind(it.basetype.clone())
+                .push_bind(db_enum(&it.category))
+                .push_bind(db_enum(&it.subcategory))
+                .push_bind(Json(it.info.clone()))
+                .push_bind(it.name.clone())
+                .push_bind(Json(it.price.clone()))
+                .push_bind(it.rarity.clone())
+                .push_bind(it.sockets.clone())
+                .push_bind(db_enum(&it.game_version));
+        });
+        qb.push(
+            " ON CONFLICT (id) DO UPDATE SET basetype = EXCLUDED.basetype, \
+             category = EXCLUDED.category, subcategory = EXCLUDED.subcategory, \
+             info = EXCLUDED.info, name = EXCLUDED.name, price = EXCLUDED.price, \
+             rarity = EXCLUDED.rarity, sockets = EXCLUDED.sockets, \
+             game_version = EXCLUDED.game_version",
+        );
+        qb.build().execute(&mut *tx).await?;
+
+        // item belongs to one stash, links to stashes it was moved from are dropped
+        let ids: Vec<_> = items.iter().map(|it| it.id.clone()).collect();
+        sqlx::query("DELETE FROM stashes WHERE item_id = ANY($1)")
+            .bind(ids)
+            .execute(&mut *tx)
+            .await?;
+        let mut qb = QueryBuilder::<Postgres>::new("INSERT INTO stashes (id, item_id) ");
+        qb.push_values(items, |mut b, it| {
+            b.push_bind(stash_id.to_string()).push_bind(it.id.clone());
+        });
+        qb.build().execute(&mut *tx).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn remove_stash(&self, stash_id: &str) -> Result<u64, StorageError> {
+        let mut tx = self.pool.begin().await?;
+        let removed = sqlx::query(
+            "DELETE FROM items WHERE id IN (SELECT item_id FROM stashes WHERE id = $1)",
+        )
+        .bind(stash_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        sqlx::query("DELETE FROM stashes WHERE id = $1")
+            .bind(stash_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(removed)
+    }
+
+    async fn get_item(&self, id: &str) -> Result<Option<StoredItem>, StorageError> {
+        let row = sqlx::query(&format!("SELECT {} FROM items WHERE id = $1", ITEM_COLUMNS))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(item_from_row).transpose()
+    }
+
+    async fn search(&self, query: &ItemQuery) -> Result<Vec<StoredItem>, StorageError> {
+        let bounded = query.stats.iter().any(|s| s.is_bounded());
+        if !bounded {
+            let mut qb =
+                QueryBuilder::<Postgres>::new(format!("SELECT {} FROM items", ITEM_COLUMNS));
+            push_conditions(&mut qb, query);
+            qb.push(" ORDER BY id");
+            if let Some(limit) = query.limit {
+                qb.push(" LIMIT ").push_bind(limit as i64);
+            }
+            let rows = qb.build().fetch_all(&self.pool).await?;
+            return rows.iter().map(item_from_row).collect();
+        }
+
+        // mod values are not indexed, items with queried stat ids are fetched in pages
+        // and value ranges are checked here until limit is reached
+        let mut found = vec![];
+        let mut after: Option<String> = None;
+        loop {
+            let mut qb =
+                QueryBuilder::<Postgres>::new(format!("SELECT {} FROM items", ITEM_COLUMNS));
+            push_conditions(&mut qb, query);
+            if let Some(id) = &after {
+                qb.push(" AND id > ").push_bind(id.clone());
+            }
+            qb.push(" ORDER BY id LIMIT ")
+                .push_bind(BOUNDED_SEARCH_PAGE as i64);
+            let rows = qb.build().fetch_all(&self.pool).await?;
+            for row in &rows {
+                let item = item_from_row(row)?;
+                if query.matches(&item) {
+                    found.push(item);
+                    if query.limit.is_some_and(|l| found.len() >= l) {
+                        return Ok(found);
+                    }
+                }
+            }
+            match rows.last() {
+                Some(row) if rows.len() == BOUNDED_SEARCH_PAGE => after = Some(row.try_get("id")?),
+                _ => return Ok(found),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::{build_calculation::stored_item::Price, item::types::Subcategory};
+    use sqlx::{postgres::PgPoolOptions, Postgres, QueryBuilder};
+
+    use super::{push_conditions, PgItemRepository};
+    use crate::{
+        tests::{ring, stat_id},
+        ItemQuery, ItemRepository, PriceRange, StatRange,
+    };
+
+    #[test]
+    fn search_sql() {
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT id FROM items");
+        push_conditions(
+            &mut qb,
+            &ItemQuery {
+                subcategory: Some(Subcategory::BodyArmour),
+                stats: vec![StatRange::new("base_maximum_life", Some(80), None)],
+                price: Some(PriceRange {
+                    currency: "exalted".to_string(),
+                    min: Some(1),
+                    max: None,
+                }),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            qb.sql(),
+            "SELECT id FROM items WHERE TRUE AND subcategory = $1 \
+             AND jsonb_path_query_array(info, '$.mods[*].stat_id') @> $2::jsonb AND price->'Custom'->>0 = $3 \
+             AND (price->'Custom'->>1)::int IS NOT NULL AND (price->'Custom'->>1)::int >= $4"
+        );
+    }
+
+    // runs only when STORAGE_TEST_DATABASE_URL is set, tables are temporary
+    // copies of the ones created by elixir migrations
+    #[tokio::test]
+    async fn pg_repository() -> anyhow::Result<()> {
+        let Ok(url) = std::env::var("STORAGE_TEST_DATABASE_URL") else {
+            eprintln!("STORAGE_TEST_DATABASE_URL is not set, skipping");
+            return Ok(());
+        };
+        // temporary tables live in one connection
+        let pool = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&url)
+            .await?;
+        sqlx::raw_sql(
+            "CREATE TEMP TABLE items (id text PRIMARY KEY, basetype text NOT NULL, \
+             category text NOT NULL, subcategory text NOT NULL, info jsonb NOT NULL, \
+             name text NOT NULL, price jsonb NOT NULL, rarity text NOT NULL, sockets text, \
+             game_version text NOT NULL DEFAULT 'poe1'); \
+             CREATE INDEX ON items USING GIN \
+             (jsonb_path_query_array(info, '$.mods[*].stat_id') jsonb_path_ops); \
+             CREATE TEMP TABLE stashes (id text, item_id text NOT NULL);",
+        )
+        .execute(&pool)
+        .await?;
+        let repo = PgItemRepository::new(pool);
+
+        let life = "+80 to maximum Life";
+        let fire_res = "+30% to Fire Resistance";
+        repo.upsert_stash_items(
+            "stash1",
+            &[
+                ring("1", Price::Chaos(10), &[life]),
+                ring("2", Price::Chaos(50), &[life, fire_res]),
+            ],
+        )
+        .await?;
+        repo.upsert_stash_items("stash2", &[ring("3", Price::Divine(1), &[fire_res])])
+            .await?;
+        // item moved to another stash
+        repo.upsert_stash_items("stash2", &[ring("1", Price::Chaos(20), &[life])])
+            .await?;
+        assert_eq!(repo.get_item("1").await?.unwrap().price, Price::Chaos(20));
+
+        let found = repo
+            .search(&ItemQuery {
+                stats: vec![StatRange::any(stat_id(life))],
+                limit: Some(10),
+                ..Default::default()
+            })
+            .await?;
+        let ids: Vec<_> = found.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(ids, ["1", "2"]);
+
+        repo.upsert_stash_items(
+            "stash3",
+            &(10..20)
+                .map(|i| ring(&i.to_string(), Price::Chaos(i), &["+95 to maximum Life"]))
+                .collect::<Vec<_>>(),
+        )
+        .await?;
+        // value ranges are checked after fetching, limit still applies
+        let found = repo
+            .search(&ItemQuery {
+                stats: vec![StatRange::new(stat_id(life), Some(90), None)],
+                limit: Some(3),
+                ..Default::default()
+            })
+            .await?;
+        let ids: Vec<_> = found.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(ids, ["10", "11", "12"]);
+        assert_eq!(repo.remove_stash("stash3").await?, 10);
+
+        assert_eq!(repo.remove_stash("stash1").await?, 1);
+        assert!(repo.get_item("1").await?.is_some());
+        assert_eq!(repo.remove_stash("stash2").await?, 2);
+        assert!(repo.search(&ItemQuery::default()).await?.is_empty());
+        Ok(())
+    }
+}
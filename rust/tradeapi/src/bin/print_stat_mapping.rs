@@ -0,0 +1,19 @@
+use tradeapi::poe1::stat_mapping::StatMapping;
+
+// mapping goes to stdout, unmatched report to stderr.
+// output is committed as tradeapi/dist/stat_mapping.json
+fn main() -> serde_json::Result<()> {
+    let mapping = StatMapping::generate();
+    serde_json::to_writer_pretty(std::io::stdout(), &mapping)?;
+
+    eprintln!(
+        "mapped: {}, unmatched domain: {}, unmatched trade: {}",
+        mapping.entries.len(),
+        mapping.unmatched_domain.len(),
+        mapping.unmatched_trade.len()
+    );
+    for id in &mapping.unmatched_trade {
+        eprintln!("unmatched trade stat: {}", id);
+    }
+    Ok(())
+}
@@ -1,12 +1,16 @@
-use std::time::{Duration, SystemTime};
+use std::time::Duration;
 
-use crate::poe1::models::{ClientFetchResponse, ClientSearchResponse};
+use crate::poe1::exchange::{ExchangeQuery, ExchangeResponse};
+use crate::poe1::models::{ClientFetchEntry, ClientFetchResponse, ClientSearchResponse};
 use crate::poe1::query::Builder;
+use async_stream::try_stream;
+use futures::Stream;
+use sha2::{Digest, Sha256};
 use thiserror::Error;
-use tracing::error;
+use tracing::warn;
 use utils::{
     reqwest::{cookie::Jar, Method, Request, StatusCode, Url},
-    ClientBuilder, ClientWithMiddleware, LimitMiddleware,
+    ClientBuilder, ClientWithMiddleware, Extensions, LimitAccount, LimitMiddleware, LimitPolicy,
 };
 
 #[derive(Error, Debug)]
@@ -25,37 +29,56 @@ pub enum ClientError {
     IncorrectArgs,
 }
 
+const BASE_URL: &str = "https://www.pathofexile.com";
+// trade api allows fetching at most 10 items per request
+const FETCH_BATCH: usize = 10;
+// search returns at most 100 ids, narrower queries are needed for more
+pub const SEARCH_MAX_RESULTS: usize = 100;
+const MAX_RETRIES: u32 = 5;
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+const SEARCH_POLICY: &str = "trade-search-request-limit";
+const FETCH_POLICY: &str = "trade-fetch-request-limit";
+const EXCHANGE_POLICY: &str = "trade-exchange-request-limit";
+
 pub struct Client {
+    base_url: String,
     search_client: ClientWithMiddleware,
     fetch_client: ClientWithMiddleware,
     league: String,
     failed_check: Option<String>,
-    wait_time_on_error: Duration,
-    last_wait: SystemTime,
+    account: LimitAccount,
 }
 
 impl Client {
     pub fn new(user_agent: &str, poesessid: &str, league: &str) -> Client {
         let client = Client::new_client(user_agent, poesessid);
         let search_client = ClientBuilder::new(client)
-            .with(LimitMiddleware::default())
+            .with(LimitMiddleware::shared())
             .build();
 
         let client = Client::new_client(user_agent, poesessid);
         let fetch_client = ClientBuilder::new(client)
-            .with(LimitMiddleware::default())
+            .with(LimitMiddleware::shared())
             .build();
 
         Client {
+            base_url: BASE_URL.to_string(),
             search_client,
             fetch_client,
             league: league.to_string(),
             failed_check: None,
-            wait_time_on_error: Duration::from_secs(5),
-            last_wait: SystemTime::now(),
+            account: Client::limit_account(poesessid),
         }
     }
 
+    // limiter state can be persisted, so don't keep session id itself.
+    // hash must be stable between builds to find persisted state again
+    fn limit_account(poesessid: &str) -> LimitAccount {
+        let hash = Sha256::digest(poesessid.as_bytes());
+        LimitAccount(hash[..8].iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
     fn new_client(user_agent: &str, poesessid: &str) -> utils::reqwest::Client {
         let jar = Jar::default();
         jar.add_cookie_str(
@@ -71,8 +94,85 @@ impl Client {
             .expect("can't build http client")
     }
 
-    pub fn set_wait_time(&mut self, d: Duration) {
-        self.wait_time_on_error = d;
+    pub fn set_base_url(&mut self, url: &str) {
+        self.base_url = url.trim_end_matches('/').to_string();
+    }
+
+    // longer of exponential backoff and limiter's Retry-After or penalty
+    fn backoff_wait(attempt: u32, limiter: Option<Duration>) -> Duration {
+        let exponential = BASE_BACKOFF
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(MAX_BACKOFF);
+        limiter.map_or(exponential, |l| l.max(exponential))
+    }
+
+    async fn backoff(&self, attempt: u32, policy: &str) {
+        let limiter = LimitMiddleware::shared().wait_time(policy, Some(&self.account.0));
+        let wait = Client::backoff_wait(attempt, limiter);
+        warn!("rate limited, waiting {}ms", wait.as_millis());
+        tokio::time::sleep(wait).await;
+    }
+
+    pub async fn get_search_id_with_backoff(
+        &mut self,
+        query: &Builder,
+    ) -> Result<ClientSearchResponse, ClientError> {
+        let mut attempt = 0;
+        loop {
+            match self.get_search_id(query).await {
+                Err(ClientError::TooManyRequestsOrSimilar) if attempt < MAX_RETRIES => {
+                    self.backoff(attempt, SEARCH_POLICY).await;
+                    attempt += 1;
+                }
+                r => return r,
+            }
+        }
+    }
+
+    pub async fn fetch_results_with_backoff(
+        &mut self,
+        ids: Vec<String>,
+        req_id: &str,
+    ) -> Result<ClientFetchResponse, ClientError> {
+        let mut attempt = 0;
+        loop {
+            match self.fetch_results(ids.clone(), req_id).await {
+                Err(ClientError::TooManyRequestsOrSimilar) if attempt < MAX_RETRIES => {
+                    self.backoff(attempt, FETCH_POLICY).await;
+                    attempt += 1;
+                }
+                r => return r,
+            }
+        }
+    }
+
+    // searches and fetches results in batches, yields at most `max_results` items,
+    // which is capped by SEARCH_MAX_RESULTS
+    pub fn search_stream<'a>(
+        &'a mut self,
+        query: &'a Builder,
+        max_results: usize,
+    ) -> impl Stream<Item = Result<ClientFetchEntry, ClientError>> + 'a {
+        try_stream! {
+            let search = self.get_search_id_with_backoff(query).await?;
+            if search.total as usize > search.result.len() {
+                warn!(
+                    "search {} matched {} items, only {} can be fetched",
+                    search.id,
+                    search.total,
+                    search.result.len()
+                );
+            }
+            let ids: Vec<_> = search.result.into_iter().take(max_results).collect();
+            for batch in ids.chunks(FETCH_BATCH) {
+                let resp = self
+                    .fetch_results_with_backoff(batch.to_vec(), &search.id)
+                    .await?;
+                for entry in resp.result {
+                    yield entry;
+                }
+            }
+        }
     }
 
     async fn make_limiter_request<T>(
@@ -80,8 +180,7 @@ impl Client {
         client: &mut ClientWithMiddleware,
         req: Request,
         limit_policy: &str,
-        last_wait: &mut SystemTime,
-        wait_time_on_error: &Duration,
+        account: &LimitAccount,
     ) -> Result<T, ClientError>
     where
         for<'de> T: serde::Deserialize<'de>,
@@ -90,11 +189,10 @@ impl Client {
             return Err(ClientError::FailedCheck(failed_check.clone().unwrap()));
         }
 
-        if last_wait.elapsed().is_err() {
-            return Err(ClientError::TooManyRequestsOrSimilar);
-        }
-
-        let resp = client.execute(req).await?;
+        let mut extensions = Extensions::new();
+        extensions.insert(LimitPolicy(limit_policy.to_string()));
+        extensions.insert(account.clone());
+        let resp = client.execute_with_extensions(req, &mut extensions).await?;
 
         if let Some(l) = resp.headers().get("x-rate-limit-policy") {
             if l != limit_policy {
@@ -107,9 +205,6 @@ impl Client {
         let st = resp.status();
         match st {
             StatusCode::TOO_MANY_REQUESTS => {
-                *last_wait = SystemTime::now()
-                    .checked_add(*wait_time_on_error)
-                    .expect("cannot set timeout for next call");
                 return Err(ClientError::TooManyRequestsOrSimilar);
             }
             x if x.is_success() => {}
@@ -126,8 +221,8 @@ impl Client {
         query: &Builder,
     ) -> Result<ClientSearchResponse, ClientError> {
         let mut req = self.search_client.post(format!(
-            "https://www.pathofexile.com/api/trade/search/{}",
-            self.league
+            "{}/api/trade/search/{}",
+            self.base_url, self.league
         ));
         req = req.json(&query);
 
@@ -136,13 +231,50 @@ impl Client {
             &mut self.failed_check,
             &mut self.search_client,
             req,
-            "trade-search-request-limit",
-            &mut self.last_wait,
-            &self.wait_time_on_error,
+            SEARCH_POLICY,
+            &self.account,
         )
         .await
     }
 
+    pub async fn exchange(
+        &mut self,
+        query: &ExchangeQuery,
+    ) -> Result<ExchangeResponse, ClientError> {
+        let req = self
+            .search_client
+            .post(format!(
+                "{}/api/trade/exchange/{}",
+                self.base_url, self.league
+            ))
+            .json(query)
+            .build()?;
+        Self::make_limiter_request(
+            &mut self.failed_check,
+            &mut self.search_client,
+            req,
+            EXCHANGE_POLICY,
+            &self.account,
+        )
+        .await
+    }
+
+    pub async fn exchange_with_backoff(
+        &mut self,
+        query: &ExchangeQuery,
+    ) -> Result<ExchangeResponse, ClientError> {
+        let mut attempt = 0;
+        loop {
+            match self.exchange(query).await {
+                Err(ClientError::TooManyRequestsOrSimilar) if attempt < MAX_RETRIES => {
+                    self.backoff(attempt, EXCHANGE_POLICY).await;
+                    attempt += 1;
+                }
+                r => return r,
+            }
+        }
+    }
+
     pub async fn fetch_results(
         &mut self,
         ids: Vec<String>,
@@ -165,7 +297,7 @@ impl Client {
             .fetch_client
             .request(
                 Method::GET,
-                format!("https://www.pathofexile.com/api/trade/fetch/{}", v),
+                format!("{}/api/trade/fetch/{}", self.base_url, v),
             )
             .query(&[("query", req_id)]);
 
@@ -175,10 +307,116 @@ impl Client {
             &mut self.failed_check,
             &mut self.fetch_client,
             req,
-            "trade-fetch-request-limit",
-            &mut self.last_wait,
-            &self.wait_time_on_error,
+            FETCH_POLICY,
+            &self.account,
         )
         .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::StreamExt;
+    use serde_json::json;
+    use wiremock::{
+        matchers::{method, path, path_regex},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::Client;
+    use crate::poe1::query::Builder;
+
+    fn entry(id: &str) -> serde_json::Value {
+        json!({
+            "id": id,
+            "listing": {
+                "method": "psapi",
+                "indexed": "2024-08-09T09:24:13Z",
+                "account": { "name": "acc", "realm": "pc" },
+                "price": null
+            },
+            "item": {
+                "verified": true, "w": 1, "h": 1, "icon": "", "id": id,
+                "name": "", "typeLine": "Coral Ring", "baseType": "Coral Ring",
+                "identified": true, "ilvl": 84,
+                "extended": {}
+            }
+        })
+    }
+
+    #[test]
+    fn limit_account_is_stable() {
+        // first 8 bytes of sha256("test")
+        assert_eq!(Client::limit_account("test").0, "9f86d081884c7d65");
+    }
+
+    #[test]
+    fn backoff_respects_limiter() {
+        assert_eq!(Client::backoff_wait(0, None), Duration::from_secs(1));
+        assert_eq!(Client::backoff_wait(3, None), Duration::from_secs(8));
+        assert_eq!(Client::backoff_wait(10, None), Duration::from_secs(60));
+        let retry_after = Some(Duration::from_secs(30));
+        assert_eq!(Client::backoff_wait(1, retry_after), Duration::from_secs(30));
+        assert_eq!(
+            Client::backoff_wait(10, retry_after),
+            Duration::from_secs(60)
+        );
+    }
+
+    #[tokio::test]
+    async fn search_stream_batches_and_retries() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        let ids: Vec<_> = (0..25).map(|i| i.to_string()).collect();
+        Mock::given(method("POST"))
+            .and(path("/api/trade/search/Standard"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "search1",
+                "complexity": 1,
+                "result": ids,
+                "total": 25,
+            })))
+            .mount(&server)
+            .await;
+        // first fetch is rate limited
+        Mock::given(method("GET"))
+            .and(path_regex("^/api/trade/fetch/.*"))
+            .respond_with(ResponseTemplate::new(429))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path_regex("^/api/trade/fetch/.*"))
+            .respond_with(|req: &wiremock::Request| {
+                let ids = req.url.path().rsplit('/').next().unwrap().to_string();
+                let result: Vec<_> = ids.split(',').map(entry).collect();
+                ResponseTemplate::new(200).set_body_json(json!({ "result": result }))
+            })
+            .mount(&server)
+            .await;
+
+        let mut client = Client::new("test", "", "Standard");
+        client.set_base_url(&server.uri());
+        let builder = Builder::new();
+        let items: Vec<_> = client
+            .search_stream(&builder, 15)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<_, _>>()?;
+        assert_eq!(items.len(), 15);
+        assert_eq!(items[14].id, "14");
+
+        let fetches = server
+            .received_requests()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|r| r.url.path().starts_with("/api/trade/fetch/"))
+            .count();
+        // 2 batches + 1 rate limited
+        assert_eq!(fetches, 3);
+        Ok(())
+    }
+}
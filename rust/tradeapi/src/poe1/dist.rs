@@ -17,6 +17,8 @@ struct StatCategory {
 struct Stat {
     id: String,
     text: String,
+    #[serde(rename = "type")]
+    typ: String,
     option: Option<Options>,
 }
 
@@ -44,7 +46,29 @@ lazy_static! {
             .map(|el| (el.text.as_str(), el.id.as_str()))
             .collect()
     };
-    pub static ref STATS_IDS: Vec<&'static str> = STAT_TO_ID.iter().map(|(_, v)| *v).collect();
+    pub static ref STATS_IDS: Vec<&'static str> = STATS
+        .result
+        .iter()
+        .flat_map(|el| &el.entries)
+        .map(|el| el.id.as_str())
+        .collect();
+    pub static ref TYPED_STAT_TO_ID: HashMap<(&'static str, &'static str), &'static str> = {
+        STATS
+            .result
+            .iter()
+            .flat_map(|el| &el.entries)
+            .map(|el| ((el.typ.as_str(), el.text.as_str()), el.id.as_str()))
+            .collect()
+    };
+}
+
+// (type, id, text) of every trade stat
+pub fn trade_stats() -> impl Iterator<Item = (&'static str, &'static str, &'static str)> {
+    STATS
+        .result
+        .iter()
+        .flat_map(|el| &el.entries)
+        .map(|el| (el.typ.as_str(), el.id.as_str(), el.text.as_str()))
 }
 
 #[cfg(test)]
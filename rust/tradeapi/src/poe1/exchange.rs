@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::poe1::{
+    models::AccountInfo,
+    query::{Status, StatusOption},
+};
+
+#[derive(Serialize, Default)]
+struct ExchangeQueryInner {
+    status: Status,
+    have: Vec<String>,
+    want: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    minimum: Option<u32>,
+}
+
+// query for bulk exchange, currencies are trade api ids, e.g. "chaos", "divine"
+#[derive(Serialize)]
+pub struct ExchangeQuery {
+    query: ExchangeQueryInner,
+    sort: HashMap<String, String>,
+    engine: String,
+}
+
+impl Default for ExchangeQuery {
+    fn default() -> Self {
+        ExchangeQuery {
+            query: ExchangeQueryInner::default(),
+            sort: HashMap::from([("have".to_string(), "asc".to_string())]),
+            engine: "new".to_string(),
+        }
+    }
+}
+
+impl ExchangeQuery {
+    pub fn new(have: &[&str], want: &[&str]) -> ExchangeQuery {
+        let mut q = ExchangeQuery::default();
+        q.query.have = have.iter().map(|s| s.to_string()).collect();
+        q.query.want = want.iter().map(|s| s.to_string()).collect();
+        q
+    }
+
+    pub fn set_status(&mut self, status: StatusOption) {
+        self.query.status.option = status;
+    }
+
+    // minimum stock of wanted currency
+    pub fn set_minimum(&mut self, minimum: u32) {
+        self.query.minimum = Some(minimum);
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ExchangeResponse {
+    pub id: String,
+    pub result: HashMap<String, ExchangeEntry>,
+    pub total: i32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ExchangeEntry {
+    pub id: String,
+    pub listing: ExchangeListing,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ExchangeListing {
+    pub indexed: String,
+    pub account: AccountInfo,
+    pub offers: Vec<ExchangeOffer>,
+    pub whisper: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ExchangeOffer {
+    // what the seller wants from us
+    pub exchange: ExchangeSide,
+    // what the seller gives
+    pub item: ExchangeSide,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ExchangeSide {
+    pub currency: String,
+    pub amount: f64,
+    pub stock: Option<u32>,
+    pub whisper: Option<String>,
+}
+
+impl ExchangeOffer {
+    // amount of `have` currency paid for one unit of `want` currency
+    pub fn ratio(&self) -> f64 {
+        self.exchange.amount / self.item.amount
+    }
+}
+
+impl ExchangeResponse {
+    // all offers sorted by ratio, best first
+    pub fn offers(&self) -> Vec<&ExchangeOffer> {
+        let mut offers: Vec<_> = self
+            .result
+            .values()
+            .flat_map(|e| e.listing.offers.iter())
+            .collect();
+        offers.sort_by(|a, b| a.ratio().total_cmp(&b.ratio()));
+        offers
+    }
+
+    // median ratio for given pair, ignoring offers without enough stock
+    pub fn rate(&self, have: &str, want: &str, min_stock: u32) -> Option<f64> {
+        let ratios: Vec<_> = self
+            .offers()
+            .into_iter()
+            .filter(|o| o.exchange.currency == have && o.item.currency == want)
+            .filter(|o| o.item.stock.unwrap_or_default() >= min_stock)
+            .map(|o| o.ratio())
+            .collect();
+        ratios.get(ratios.len() / 2).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{ExchangeQuery, ExchangeResponse};
+
+    #[test]
+    fn query() -> anyhow::Result<()> {
+        let mut q = ExchangeQuery::new(&["chaos"], &["divine"]);
+        q.set_minimum(5);
+        let v = serde_json::to_value(&q)?;
+        assert_eq!(
+            v,
+            json!({
+                "query": {
+                    "status": { "option": "online" },
+                    "have": ["chaos"],
+                    "want": ["divine"],
+                    "minimum": 5,
+                },
+                "sort": { "have": "asc" },
+                "engine": "new",
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn offers() -> anyhow::Result<()> {
+        let offer = |id: &str, chaos: f64, stock: u32| {
+            json!({
+                "id": id,
+                "item": null,
+                "listing": {
+                    "indexed": "2024-08-09T09:24:13Z",
+                    "account": { "name": id, "realm": "pc" },
+                    "whisper": "@{0} Hi, I'd like to buy your {1} for my {2}",
+                    "offers": [{
+                        "exchange": { "currency": "chaos", "amount": chaos, "whisper": "{0} Chaos Orb" },
+                        "item": { "currency": "divine", "amount": 1, "stock": stock, "id": id, "whisper": "{0} Divine Orb" },
+                    }],
+                },
+            })
+        };
+        let resp: ExchangeResponse = serde_json::from_value(json!({
+            "id": "xyz",
+            "complexity": null,
+            "result": {
+                "a": offer("a", 160.0, 10),
+                "b": offer("b", 150.0, 1),
+                "c": offer("c", 155.0, 20),
+            },
+            "total": 3,
+        }))?;
+        let offers = resp.offers();
+        assert_eq!(offers.len(), 3);
+        assert_eq!(offers[0].ratio(), 150.0);
+        assert_eq!(offers[0].item.stock, Some(1));
+        assert_eq!(resp.rate("chaos", "divine", 5), Some(160.0));
+        assert_eq!(resp.rate("chaos", "divine", 1), Some(155.0));
+        assert_eq!(resp.rate("divine", "chaos", 1), None);
+        Ok(())
+    }
+}
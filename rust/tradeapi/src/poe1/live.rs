@@ -0,0 +1,231 @@
+use std::time::Duration;
+
+use async_stream::stream;
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use thiserror::Error;
+use tokio::{net::TcpStream, time::Instant};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{
+        self,
+        client::IntoClientRequest,
+        http::{header::InvalidHeaderValue, HeaderValue},
+        Message,
+    },
+    MaybeTlsStream, WebSocketStream,
+};
+use tracing::warn;
+
+const BASE_URL: &str = "wss://www.pathofexile.com";
+const ORIGIN: &str = "https://www.pathofexile.com";
+const MAX_RECONNECTS: u32 = 5;
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+// connection that was authorized and dropped quickly still counts as a failed attempt
+const MIN_UPTIME: Duration = Duration::from_secs(30);
+
+#[derive(Error, Debug)]
+pub enum LiveSearchError {
+    #[error("websocket error: {0}")]
+    WebSocket(#[from] tungstenite::Error),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid header: {0}")]
+    Header(#[from] InvalidHeaderValue),
+    #[error("live search not authorized, check POESESSID")]
+    Unauthorized,
+    #[error("connection lost after {0} reconnects")]
+    Reconnects(u32),
+}
+
+#[derive(Deserialize)]
+struct LiveMessage {
+    auth: Option<bool>,
+    #[serde(default)]
+    new: Vec<String>,
+}
+
+pub struct LiveSearch {
+    base_url: String,
+    user_agent: String,
+    poesessid: String,
+    league: String,
+}
+
+impl LiveSearch {
+    pub fn new(user_agent: &str, poesessid: &str, league: &str) -> LiveSearch {
+        LiveSearch {
+            base_url: BASE_URL.to_string(),
+            user_agent: user_agent.to_string(),
+            poesessid: poesessid.to_string(),
+            league: league.to_string(),
+        }
+    }
+
+    pub fn set_base_url(&mut self, url: &str) {
+        self.base_url = url.trim_end_matches('/').to_string();
+    }
+
+    async fn connect(
+        &self,
+        search_id: &str,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, LiveSearchError> {
+        let mut req = format!(
+            "{}/api/trade/live/{}/{}",
+            self.base_url, self.league, search_id
+        )
+        .into_client_request()?;
+        let headers = req.headers_mut();
+        headers.insert(
+            "Cookie",
+            HeaderValue::from_str(&format!("POESESSID={}", self.poesessid))?,
+        );
+        headers.insert("User-Agent", HeaderValue::from_str(&self.user_agent)?);
+        headers.insert("Origin", HeaderValue::from_static(ORIGIN));
+        let (ws, _) = connect_async(req).await?;
+        Ok(ws)
+    }
+
+    // yields batches of new result ids which can be passed to `Client::fetch_results`,
+    // reconnects with backoff when connection drops
+    pub fn subscribe<'a>(
+        &'a self,
+        search_id: &'a str,
+    ) -> impl Stream<Item = Result<Vec<String>, LiveSearchError>> + 'a {
+        stream! {
+            let mut attempt = 0;
+            loop {
+                match self.connect(search_id).await {
+                    Ok(mut ws) => {
+                        let connected = Instant::now();
+                        while let Some(msg) = ws.next().await {
+                            let text = match msg {
+                                Ok(Message::Text(text)) => text,
+                                Ok(Message::Close(_)) => break,
+                                Ok(_) => continue,
+                                Err(e) => {
+                                    warn!("live search connection error: {}", e);
+                                    break;
+                                }
+                            };
+                            let msg: LiveMessage = match serde_json::from_str(&text) {
+                                Ok(msg) => msg,
+                                Err(e) => {
+                                    yield Err(e.into());
+                                    continue;
+                                }
+                            };
+                            if msg.auth == Some(false) {
+                                yield Err(LiveSearchError::Unauthorized);
+                                return;
+                            }
+                            if !msg.new.is_empty() {
+                                attempt = 0;
+                                yield Ok(msg.new);
+                            }
+                        }
+                        if connected.elapsed() >= MIN_UPTIME {
+                            attempt = 0;
+                        }
+                    }
+                    Err(e) => warn!("live search connect failed: {}", e),
+                }
+
+                if attempt >= MAX_RECONNECTS {
+                    yield Err(LiveSearchError::Reconnects(attempt));
+                    return;
+                }
+                let wait = BASE_BACKOFF
+                    .saturating_mul(2u32.saturating_pow(attempt))
+                    .min(MAX_BACKOFF);
+                warn!("live search reconnecting in {}ms", wait.as_millis());
+                tokio::time::sleep(wait).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{SinkExt, StreamExt};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::{
+        accept_hdr_async,
+        tungstenite::{
+            handshake::server::{Request, Response},
+            Message,
+        },
+    };
+
+    use super::{LiveSearch, LiveSearchError, MAX_RECONNECTS};
+
+    #[tokio::test]
+    // handshake callback signature is defined by tungstenite
+    #[allow(clippy::result_large_err)]
+    async fn subscribe_and_reconnect() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = tokio::spawn(async move {
+            let messages = [
+                vec![r#"{"auth": true}"#, r#"{"new": ["a", "b"]}"#],
+                vec![r#"{"auth": true}"#, r#"{"new": ["c"]}"#],
+                vec![r#"{"auth": false}"#],
+            ];
+            for msgs in messages {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut ws = accept_hdr_async(stream, |req: &Request, resp: Response| {
+                    assert_eq!(req.uri().path(), "/api/trade/live/Standard/search1");
+                    assert_eq!(req.headers()["Cookie"], "POESESSID=sessid");
+                    Ok(resp)
+                })
+                .await
+                .unwrap();
+                for m in msgs {
+                    ws.send(Message::Text(m.into())).await.unwrap();
+                }
+                ws.close(None).await.unwrap();
+            }
+        });
+
+        let mut live = LiveSearch::new("test", "sessid", "Standard");
+        live.set_base_url(&format!("ws://{}", addr));
+        let results: Vec<_> = live.subscribe("search1").collect().await;
+        server.await?;
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap(), &["a", "b"]);
+        assert_eq!(results[1].as_ref().unwrap(), &["c"]);
+        assert!(matches!(results[2], Err(LiveSearchError::Unauthorized)));
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn auth_without_results_counts_as_attempt() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = tokio::spawn(async move {
+            for _ in 0..=MAX_RECONNECTS {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                ws.send(Message::Text(r#"{"auth": true}"#.into()))
+                    .await
+                    .unwrap();
+                ws.close(None).await.unwrap();
+            }
+        });
+
+        let mut live = LiveSearch::new("test", "sessid", "Standard");
+        live.set_base_url(&format!("ws://{}", addr));
+        let results: Vec<_> = live.subscribe("search1").collect().await;
+        server.await?;
+
+        assert_eq!(results.len(), 1);
+        assert!(matches!(
+            results[0],
+            Err(LiveSearchError::Reconnects(MAX_RECONNECTS))
+        ));
+        Ok(())
+    }
+}
@@ -1,6 +1,10 @@
 mod client;
 mod dist;
+pub mod exchange;
+pub mod live;
 pub mod models;
 pub mod query;
+pub mod stat_mapping;
 
-pub use client::{Client, ClientError};
+pub use client::{Client, ClientError, SEARCH_MAX_RESULTS};
+pub use live::{LiveSearch, LiveSearchError};
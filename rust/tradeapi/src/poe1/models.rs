@@ -1,7 +1,10 @@
-use std::collections::HashMap;
-
+use domain::{
+    build_calculation::stored_item::{StoredItem, StoredItemError},
+    item::Item as DomainItem,
+};
+use public_stash::models::{Item as StashItem, PublicStashError};
 use serde::{Deserialize, Deserializer, Serialize};
-use serde_json::Value;
+use thiserror::Error;
 
 #[derive(Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -24,8 +27,6 @@ pub struct ClientFetchEntry {
     pub id: String,
     pub listing: ClientFetchListing,
     pub item: ClientFetchItem,
-    #[serde(flatten)]
-    pub rest: HashMap<String, Value>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -38,8 +39,6 @@ pub struct ClientFetchListing {
     pub whisper_token: Option<String>,
     pub account: AccountInfo,
     pub price: Option<PriceInfo>,
-    #[serde(flatten)]
-    pub rest: HashMap<String, Value>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -57,6 +56,15 @@ pub struct AccountInfo {
     #[serde(rename = "lastCharacterName")]
     pub last_character_name: Option<String>,
     pub realm: String,
+    pub online: Option<OnlineInfo>,
+    pub language: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "lowercase")]
+pub struct OnlineInfo {
+    pub league: Option<String>,
+    pub status: Option<String>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -68,43 +76,65 @@ pub struct PriceInfo {
     pub currency: String,
 }
 
+impl PriceInfo {
+    // same format as stash notes, e.g. "~b/o 10 chaos"
+    pub fn as_note(&self) -> String {
+        format!("{} {} {}", self.typ, self.amount, self.currency)
+    }
+}
+
+// trade api returns same item as public stash api with additional `extended` info
 #[derive(Deserialize, Serialize, Debug, Clone)]
-#[serde(rename_all = "lowercase")]
 pub struct ClientFetchItem {
-    pub extended: FetchItemExtended,
-    #[serde(rename = "typeLine")]
-    pub type_line: String,
-    #[serde(rename = "baseType")]
-    pub base_type: String,
-    pub ilvl: i32,
-    pub id: String,
-    pub sockets: Option<Vec<SocketGroup>>,
     #[serde(flatten)]
-    pub rest: HashMap<String, Value>,
+    pub item: StashItem,
+    pub extended: FetchItemExtended,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
 #[serde(rename_all = "lowercase")]
 pub struct FetchItemExtended {
     pub dps: Option<f32>,
     pub dps_aug: Option<bool>,
     pub edps: Option<f32>,
-    pub hashes: Option<HashMap<String, Value>>,
-    pub mods: Option<FetchItemMods>,
+    pub edps_aug: Option<bool>,
     pub pdps: Option<f32>,
     pub pdps_aug: Option<bool>,
-    pub text: String,
-    #[serde(flatten)]
-    pub rest: HashMap<String, Value>,
+    pub ar: Option<i32>,
+    pub ar_aug: Option<bool>,
+    pub ev: Option<i32>,
+    pub ev_aug: Option<bool>,
+    pub es: Option<i32>,
+    pub es_aug: Option<bool>,
+    pub hashes: Option<FetchItemHashes>,
+    pub mods: Option<FetchItemMods>,
+    pub text: Option<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "lowercase")]
+pub struct FetchItemHashes {
+    pub explicit: Option<Vec<ModHash>>,
+    pub implicit: Option<Vec<ModHash>>,
+    pub crafted: Option<Vec<ModHash>>,
+    pub fractured: Option<Vec<ModHash>>,
+    pub enchant: Option<Vec<ModHash>>,
+    pub scourge: Option<Vec<ModHash>>,
+}
+
+// ["explicit.stat_3299347043", [0, 1]] where indices point into FetchItemMods of the same type
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ModHash(pub String, pub Option<Vec<Option<usize>>>);
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
 #[serde(rename_all = "lowercase")]
 pub struct FetchItemMods {
     pub explicit: Option<Vec<FetchItemMod>>,
     pub implicit: Option<Vec<FetchItemMod>>,
-    #[serde(flatten)]
-    pub rest: HashMap<String, Value>,
+    pub crafted: Option<Vec<FetchItemMod>>,
+    pub fractured: Option<Vec<FetchItemMod>>,
+    pub enchant: Option<Vec<FetchItemMod>>,
+    pub scourge: Option<Vec<FetchItemMod>>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -124,13 +154,79 @@ pub struct FetchItemModInfo {
     pub min: f32,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
-#[serde(rename_all = "lowercase")]
-pub struct SocketGroup {
-    pub attr: String,
-    pub group: i32,
-    #[serde(rename = "sColour")]
-    pub s_colour: String,
+impl FetchItemExtended {
+    // trade stat id with mods (tier and roll ranges) it came from
+    pub fn mods_by_hash(&self) -> Vec<(&str, Vec<&FetchItemMod>)> {
+        let (Some(hashes), Some(mods)) = (&self.hashes, &self.mods) else {
+            return vec![];
+        };
+        [
+            (&hashes.explicit, &mods.explicit),
+            (&hashes.implicit, &mods.implicit),
+            (&hashes.crafted, &mods.crafted),
+            (&hashes.fractured, &mods.fractured),
+            (&hashes.enchant, &mods.enchant),
+            (&hashes.scourge, &mods.scourge),
+        ]
+        .into_iter()
+        .flat_map(|(h, m)| {
+            let m = m.as_deref().unwrap_or_default();
+            h.iter().flatten().map(move |ModHash(id, idx)| {
+                let mods = idx
+                    .iter()
+                    .flatten()
+                    .flatten()
+                    .filter_map(|i| m.get(*i))
+                    .collect();
+                (id.as_str(), mods)
+            })
+        })
+        .collect()
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum FetchItemError {
+    #[error("item convert: {0}")]
+    Item(#[from] PublicStashError),
+    #[error("stored item convert: {0}")]
+    StoredItem(#[from] StoredItemError),
+}
+
+impl TryFrom<ClientFetchEntry> for DomainItem {
+    type Error = FetchItemError;
+
+    fn try_from(value: ClientFetchEntry) -> Result<Self, Self::Error> {
+        let mut item = value.item.item;
+        if item.note.is_none() {
+            item.note = value.listing.price.map(|p| p.as_note());
+        }
+        Ok(DomainItem::try_from(item)?)
+    }
+}
+
+impl TryFrom<ClientFetchItem> for DomainItem {
+    type Error = FetchItemError;
+
+    fn try_from(value: ClientFetchItem) -> Result<Self, Self::Error> {
+        Ok(DomainItem::try_from(value.item)?)
+    }
+}
+
+impl TryFrom<ClientFetchItem> for StoredItem {
+    type Error = FetchItemError;
+
+    fn try_from(value: ClientFetchItem) -> Result<Self, Self::Error> {
+        Ok(StoredItem::try_from(DomainItem::try_from(value)?)?)
+    }
+}
+
+impl TryFrom<ClientFetchEntry> for StoredItem {
+    type Error = FetchItemError;
+
+    fn try_from(value: ClientFetchEntry) -> Result<Self, Self::Error> {
+        Ok(StoredItem::try_from(DomainItem::try_from(value)?)?)
+    }
 }
 
 #[derive(Deserialize)]
@@ -225,7 +321,12 @@ pub struct StaticItem {
 
 #[cfg(test)]
 mod tests {
-    use super::{ClientFetchResponse, ItemsData, StaticData, StatsData};
+    use domain::{
+        build_calculation::stored_item::{Price, StoredItem},
+        item::Item as DomainItem,
+    };
+
+    use super::{ClientFetchEntry, ClientFetchResponse, ItemsData, StaticData, StatsData};
     static DATA: &str = include_str!("../../dist/items.json");
     static STATS_DATA: &str = include_str!("../../dist/stats.json");
     static STATIC_DATA: &str = include_str!("../../dist/static.json");
@@ -245,4 +346,80 @@ mod tests {
         let _: ClientFetchResponse = serde_json::from_str(TESTCASE1).unwrap();
         let _: ClientFetchResponse = serde_json::from_str(TESTCASE2).unwrap();
     }
+
+    #[test]
+    fn typed_fetch_item() -> anyhow::Result<()> {
+        let resp: ClientFetchResponse = serde_json::from_str(TESTCASE2)?;
+        let entry = &resp.result[0];
+        assert_eq!(entry.listing.account.name, "Khooj");
+        assert!(entry.listing.whisper.is_some());
+
+        let item = &entry.item;
+        assert_eq!(item.item.base_type, "Inscribed Ultimatum");
+        let props = item.item.properties.as_ref().unwrap();
+        assert_eq!(props[2].name, "Requires Sacrifice: {0}");
+        assert_eq!(props[2].values[0][0].value(), "Romira's Banquet");
+
+        let hashes = item.extended.mods_by_hash();
+        assert_eq!(hashes.len(), 8);
+        assert_eq!(hashes[0].0, "ultimatum.umod_12812");
+        assert!(hashes[0].1.is_empty());
+        assert_eq!(hashes[7].0, "explicit.stat_95249895");
+        assert_eq!(hashes[7].1[0].tier, "R2");
+        Ok(())
+    }
+
+    static FETCH_RING: &str = r#"{
+        "id": "1",
+        "listing": {
+            "method": "psapi",
+            "indexed": "2024-08-09T09:24:13Z",
+            "whisper": "@someone hi",
+            "account": { "name": "acc", "realm": "pc" },
+            "price": { "type": "~price", "amount": 10, "currency": "chaos" }
+        },
+        "item": {
+            "verified": true, "w": 1, "h": 1, "icon": "",
+            "league": "Standard", "id": "1",
+            "name": "Storm Loop", "typeLine": "Coral Ring", "baseType": "Coral Ring",
+            "identified": true, "ilvl": 84, "frameType": 2,
+            "requirements": [{ "name": "Level", "values": [["48", 0]], "displayMode": 0 }],
+            "implicitMods": ["+30 to maximum Life"],
+            "explicitMods": ["+80 to maximum Life", "+42% to Fire Resistance"],
+            "extended": {
+                "mods": {
+                    "explicit": [
+                        { "name": "Virile", "tier": "P1", "level": 82,
+                          "magnitudes": [{ "hash": "explicit.stat_3299347043", "min": 80, "max": 89 }] },
+                        { "name": "of the Furnace", "tier": "S2", "level": 72,
+                          "magnitudes": [{ "hash": "explicit.stat_3372524247", "min": 42, "max": 45 }] }
+                    ]
+                },
+                "hashes": {
+                    "explicit": [["explicit.stat_3299347043", [0]], ["explicit.stat_3372524247", [1]]],
+                    "implicit": [["implicit.stat_3299347043", null]]
+                }
+            }
+        }
+    }"#;
+
+    #[test]
+    fn fetch_item_into_stored() -> anyhow::Result<()> {
+        let entry: ClientFetchEntry = serde_json::from_str(FETCH_RING)?;
+        let hashes = entry.item.extended.mods_by_hash();
+        let life = &hashes[0].1[0].magnitudes.as_ref().unwrap()[0];
+        assert_eq!((life.min, life.max), (80.0, 89.0));
+        assert_eq!(hashes[2].0, "implicit.stat_3299347043");
+        assert!(hashes[2].1.is_empty());
+
+        let item = DomainItem::try_from(entry.clone())?;
+        assert_eq!(item.mods.len(), 3);
+        assert_eq!(item.note.as_deref(), Some("~price 10 chaos"));
+
+        let stored = StoredItem::try_from(entry)?;
+        assert_eq!(stored.basetype, "Coral Ring");
+        assert_eq!(stored.info.mods().len(), 3);
+        assert_eq!(stored.price, Price::Chaos(10));
+        Ok(())
+    }
 }
@@ -1,11 +1,16 @@
-use macros::{gen_min_max_method, gen_option_method};
+use macros::{gen_min_max_method, gen_option_method, gen_select_method};
 use serde::Serialize;
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use thiserror::Error;
+use utils::reqwest::Url;
 
 use crate::poe1::dist::{STATS_IDS, STAT_TO_ID};
 
+mod item;
+
+const TRADE_SEARCH_URL: &str = "https://www.pathofexile.com/trade/search";
+
 #[derive(Serialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub struct StatQuery {
@@ -105,6 +110,16 @@ struct Filters {
     req_filters: Option<ReqFilters>,
     #[serde(skip_serializing_if = "Option::is_none")]
     trade_filters: Option<TradeFilters>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    map_filters: Option<MapFilters>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    heist_filters: Option<HeistFilters>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ultimatum_filters: Option<UltimatumFilters>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sanctum_filters: Option<SanctumFilters>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    equipment_filters: Option<EquipmentFilters>,
 }
 
 #[derive(Serialize, Default)]
@@ -260,14 +275,135 @@ impl ReqFilters {
     gen_min_max_method!(str);
     gen_min_max_method!(int);
 
-    fn set_class(mut self, class: &str) -> Self {
-        let v = json!({
-            "option": class,
-        });
-        let m = self.filters.entry(class.to_string()).or_default();
-        *m = v;
-        self
-    }
+    gen_select_method!(class);
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub struct MapFilters {
+    disabled: bool,
+    filters: HashMap<String, Value>,
+}
+
+impl MapFilters {
+    gen_min_max_method!(map_tier);
+    gen_min_max_method!(map_packsize);
+    gen_min_max_method!(map_iiq);
+    gen_min_max_method!(map_iir);
+    gen_option_method!(map_shaped);
+    gen_option_method!(map_elder);
+    gen_option_method!(map_blighted);
+    gen_option_method!(map_uberblighted);
+    gen_select_method!(map_region);
+    gen_select_method!(map_completion_reward);
+    gen_select_method!(map_series);
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub struct HeistFilters {
+    disabled: bool,
+    filters: HashMap<String, Value>,
+}
+
+impl HeistFilters {
+    gen_min_max_method!(heist_wings);
+    gen_min_max_method!(heist_max_wings);
+    gen_min_max_method!(heist_escape_routes);
+    gen_min_max_method!(heist_max_escape_routes);
+    gen_min_max_method!(heist_reward_rooms);
+    gen_min_max_method!(heist_max_reward_rooms);
+    gen_min_max_method!(area_level);
+    gen_min_max_method!(heist_lockpicking);
+    gen_min_max_method!(heist_brute_force);
+    gen_min_max_method!(heist_perception);
+    gen_min_max_method!(heist_demolition);
+    gen_min_max_method!(heist_counter_thaumaturgy);
+    gen_min_max_method!(heist_trap_disarmament);
+    gen_min_max_method!(heist_agility);
+    gen_min_max_method!(heist_deception);
+    gen_min_max_method!(heist_engineering);
+    gen_select_method!(heist_objective_value);
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub struct UltimatumFilters {
+    disabled: bool,
+    filters: HashMap<String, Value>,
+}
+
+impl UltimatumFilters {
+    gen_select_method!(ultimatum_challenge);
+    gen_select_method!(ultimatum_reward);
+    gen_select_method!(ultimatum_input);
+    gen_select_method!(ultimatum_output);
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub struct SanctumFilters {
+    disabled: bool,
+    filters: HashMap<String, Value>,
+}
+
+impl SanctumFilters {
+    gen_min_max_method!(sanctum_resolve);
+    gen_min_max_method!(sanctum_max_resolve);
+    gen_min_max_method!(sanctum_inspiration);
+    gen_min_max_method!(sanctum_gold);
+}
+
+// poe2 trade site merges weapon and armour filters into a single group
+#[derive(Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub struct EquipmentFilters {
+    disabled: bool,
+    filters: HashMap<String, Value>,
+}
+
+impl EquipmentFilters {
+    gen_min_max_method!(damage);
+    gen_min_max_method!(aps);
+    gen_min_max_method!(crit);
+    gen_min_max_method!(dps);
+    gen_min_max_method!(pdps);
+    gen_min_max_method!(edps);
+    gen_min_max_method!(ar);
+    gen_min_max_method!(ev);
+    gen_min_max_method!(es);
+    gen_min_max_method!(block);
+    gen_min_max_method!(spirit);
+    gen_min_max_method!(rune_sockets);
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Currency {
+    // listings priced in either chaos or divine orbs
+    #[serde(rename = "chaos_divine")]
+    ChaosOrDivine,
+    Chaos,
+    Divine,
+    Exalted,
+    Alch,
+    Mirror,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum Indexed {
+    #[serde(rename = "1day")]
+    Day,
+    #[serde(rename = "3days")]
+    ThreeDays,
+    #[serde(rename = "1week")]
+    Week,
+    #[serde(rename = "2weeks")]
+    TwoWeeks,
+    #[serde(rename = "1month")]
+    Month,
+    #[serde(rename = "2months")]
+    TwoMonths,
 }
 
 #[derive(Serialize, Default)]
@@ -293,6 +429,33 @@ impl TradeFilters {
         self.filters.insert("sale_type".to_string(), v);
         self
     }
+
+    pub fn set_price(mut self, min: Option<f32>, max: Option<f32>, cur: Option<Currency>) -> Self {
+        let v = json!({
+            "min": min,
+            "max": max,
+            "option": cur,
+        });
+        self.filters.insert("price".to_string(), v);
+        self
+    }
+
+    // listed time
+    pub fn set_indexed(mut self, indexed: Indexed) -> Self {
+        let v = json!({
+            "option": indexed,
+        });
+        self.filters.insert("indexed".to_string(), v);
+        self
+    }
+
+    pub fn set_collapse(mut self, val: bool) -> Self {
+        let v = json!({
+            "option": val,
+        });
+        self.filters.insert("collapse".to_string(), v);
+        self
+    }
 }
 
 #[derive(Serialize, Default)]
@@ -305,12 +468,14 @@ struct Query {
     #[serde(rename = "type")]
     #[serde(skip_serializing_if = "Option::is_none")]
     typ: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
 }
 
 #[derive(Serialize, Default)]
 #[serde(rename_all = "lowercase")]
-struct Status {
-    option: StatusOption,
+pub(crate) struct Status {
+    pub(crate) option: StatusOption,
 }
 
 #[derive(Serialize, Default)]
@@ -322,20 +487,40 @@ pub enum StatusOption {
     Any,
 }
 
-#[derive(Serialize, Default)]
-#[serde(rename_all = "lowercase")]
-struct SortOptions {
-    price: Sort,
+#[derive(Serialize)]
+#[serde(transparent)]
+struct SortOptions(HashMap<String, Sort>);
+
+impl Default for SortOptions {
+    fn default() -> Self {
+        SortOptions::from(SortBy::Price(Sort::Asc))
+    }
 }
 
-#[derive(Serialize, Default)]
+impl From<SortBy> for SortOptions {
+    fn from(value: SortBy) -> Self {
+        let (k, v) = match value {
+            SortBy::Price(s) => ("price".to_string(), s),
+            SortBy::Stat(id, s) => (format!("stat.{}", id), s),
+        };
+        SortOptions(HashMap::from([(k, v)]))
+    }
+}
+
+#[derive(Serialize, Default, Clone, Copy, Debug, PartialEq)]
 #[serde(rename_all = "lowercase")]
-enum Sort {
+pub enum Sort {
     #[default]
     Asc,
     Desc,
 }
 
+pub enum SortBy {
+    Price(Sort),
+    // trade stat id, e.g. explicit.stat_3299347043
+    Stat(String, Sort),
+}
+
 #[derive(Serialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub struct Builder {
@@ -388,6 +573,10 @@ impl Builder {
         self.query.typ = Some(s.to_string());
     }
 
+    pub fn set_name(&mut self, s: &str) {
+        self.query.name = Some(s.to_string());
+    }
+
     pub fn set_status(&mut self, status: StatusOption) {
         self.query.status.option = status;
     }
@@ -395,6 +584,40 @@ impl Builder {
     pub fn set_trade_filters(&mut self, filters: TradeFilters) {
         self.query.filters.trade_filters = Some(filters);
     }
+
+    pub fn set_map_filters(&mut self, filters: MapFilters) {
+        self.query.filters.map_filters = Some(filters);
+    }
+
+    pub fn set_heist_filters(&mut self, filters: HeistFilters) {
+        self.query.filters.heist_filters = Some(filters);
+    }
+
+    pub fn set_ultimatum_filters(&mut self, filters: UltimatumFilters) {
+        self.query.filters.ultimatum_filters = Some(filters);
+    }
+
+    pub fn set_sanctum_filters(&mut self, filters: SanctumFilters) {
+        self.query.filters.sanctum_filters = Some(filters);
+    }
+
+    pub fn set_equipment_filters(&mut self, filters: EquipmentFilters) {
+        self.query.filters.equipment_filters = Some(filters);
+    }
+
+    pub fn set_sort(&mut self, sort: SortBy) {
+        self.sort = sort.into();
+    }
+
+    pub fn to_url(&self, league: &str) -> Result<Url, BuilderError> {
+        let q = serde_json::to_string(self)?;
+        let mut url = Url::parse(TRADE_SEARCH_URL).expect("valid trade url");
+        url.path_segments_mut()
+            .expect("trade url can be a base")
+            .push(league);
+        url.query_pairs_mut().append_pair("q", &q);
+        Ok(url)
+    }
 }
 
 /*
@@ -607,4 +830,53 @@ mod tests {
             None,
         ));
     }
+
+    #[test]
+    fn currency_names() -> anyhow::Result<()> {
+        assert_eq!(serde_json::to_value(Currency::ChaosOrDivine)?, "chaos_divine");
+        assert_eq!(serde_json::to_value(Currency::Divine)?, "divine");
+        Ok(())
+    }
+
+    #[test]
+    fn filters_and_sort() -> anyhow::Result<()> {
+        let mut query = Builder::new();
+        query.set_req_filters(ReqFilters::default().set_class("scion"));
+        query.set_map_filters(
+            MapFilters::default()
+                .set_map_tier(Some(16), None)
+                .set_map_region("otl"),
+        );
+        query.set_trade_filters(
+            TradeFilters::default()
+                .set_price(None, Some(5.0), Some(Currency::Divine))
+                .set_indexed(Indexed::ThreeDays),
+        );
+        query.set_sort(SortBy::Stat(
+            "explicit.stat_3299347043".to_string(),
+            Sort::Desc,
+        ));
+
+        let v = serde_json::to_value(&query)?;
+        let filters = &v["query"]["filters"];
+        assert_eq!(
+            filters["req_filters"]["filters"]["class"]["option"],
+            "scion"
+        );
+        assert_eq!(filters["map_filters"]["filters"]["map_tier"]["min"], 16);
+        assert_eq!(
+            filters["map_filters"]["filters"]["map_region"]["option"],
+            "otl"
+        );
+        let trade = &filters["trade_filters"]["filters"];
+        assert_eq!(trade["price"]["max"], 5.0);
+        assert_eq!(trade["price"]["option"], "divine");
+        assert_eq!(trade["indexed"]["option"], "3days");
+        assert_eq!(v["sort"]["stat.explicit.stat_3299347043"], "desc");
+        assert_eq!(
+            serde_json::to_value(Builder::new())?["sort"]["price"],
+            "asc"
+        );
+        Ok(())
+    }
 }
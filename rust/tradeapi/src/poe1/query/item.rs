@@ -0,0 +1,338 @@
+use domain::{
+    build_calculation::{
+        item_config::{ItemConfigOption, ModOption, ModStatId, UniqueKind},
+        stored_item::{ItemInfo, StoredItem},
+        ItemWithConfig,
+    },
+    item::types::{SocketColor, SocketRequirement, Subcategory},
+};
+use regex::Regex;
+use tracing::warn;
+
+use super::{
+    Builder, BuilderError, MiscFilters, SocketFilters, StatQuery, StatQueryType, TypeFilters,
+};
+use crate::poe1::{
+    dist::TYPED_STAT_TO_ID,
+    stat_mapping::{MAPPED_TYPES, STAT_MAPPING},
+};
+
+lazy_static::lazy_static! {
+    static ref NUMBER_REGEX: Regex = Regex::new(r"[0-9]+(\.[0-9]+)?").unwrap();
+}
+
+pub(crate) fn trade_stat_id(text: &str) -> Option<&'static str> {
+    let text = NUMBER_REGEX.replace_all(text.lines().next()?, "#");
+    MAPPED_TYPES
+        .iter()
+        .find_map(|typ| TYPED_STAT_TO_ID.get(&(*typ, text.as_ref())).copied())
+}
+
+fn trade_category(subcategory: &Subcategory) -> Option<&'static str> {
+    Some(match subcategory {
+        Subcategory::Helmets => "armour.helmet",
+        Subcategory::Boots => "armour.boots",
+        Subcategory::Gloves => "armour.gloves",
+        Subcategory::BodyArmour => "armour.chest",
+        Subcategory::Shield => "armour.shield",
+        Subcategory::Quiver => "armour.quiver",
+        Subcategory::Gem => "gem",
+        Subcategory::Jewel => "jewel",
+        Subcategory::Amulet => "accessory.amulet",
+        Subcategory::Belt => "accessory.belt",
+        Subcategory::Ring => "accessory.ring",
+        Subcategory::Weapon => "weapon",
+        Subcategory::LifeFlask
+        | Subcategory::ManaFlask
+        | Subcategory::HybridFlask
+        | Subcategory::UtilityFlask => "flask",
+        Subcategory::Empty | Subcategory::Focus | Subcategory::Charm => return None,
+    })
+}
+
+fn mod_text<'a>(item: &'a StoredItem, id: &ModStatId) -> Option<&'a str> {
+    item.info
+        .mods()
+        .iter()
+        .find(|m| &m.stat_id == id as &String)
+        .map(|m| m.text.as_str())
+}
+
+fn add_mod(
+    builder: &mut Builder,
+    stats: StatQuery,
+    item: &StoredItem,
+    id: &ModStatId,
+    option: &ModOption,
+) -> Result<StatQuery, BuilderError> {
+    let (min, max) = match option {
+        ModOption::Exact(v) => (Some(*v), Some(*v)),
+        ModOption::Range(r) => (Some(r.start), Some(r.end)),
+        ModOption::Min(v) => (Some(*v), None),
+        ModOption::Exist => (None, None),
+        ModOption::Ignore => return Ok(stats),
+    };
+
+    let mut trade_ids = STAT_MAPPING.trade_ids(id, None);
+    if trade_ids.is_empty() {
+        trade_ids.extend(mod_text(item, id).and_then(trade_stat_id).map(String::from));
+    }
+    match trade_ids.as_slice() {
+        [] => {
+            warn!("no trade stat for mod: {}", id.as_str());
+            Ok(stats)
+        }
+        [trade_id] => stats.try_add_mod_id(trade_id, min, max, None),
+        // trade has several stats with the same text, any of them matches
+        candidates => {
+            let mut group = StatQuery::new().set_type(StatQueryType::Count(1));
+            for trade_id in candidates {
+                group = group.try_add_mod_id(trade_id, min, max, None)?;
+            }
+            builder.add_stat_group(group);
+            Ok(stats)
+        }
+    }
+}
+
+fn set_unique(
+    builder: &mut Builder,
+    type_filters: &mut Option<TypeFilters>,
+    item: &StoredItem,
+    kind: Option<UniqueKind>,
+) {
+    builder.set_name(&item.name);
+    builder.set_type(&item.basetype);
+    let rarity = match kind {
+        Some(UniqueKind::Relic) => "uniquefoil",
+        _ => "unique",
+    };
+    *type_filters = Some(type_filters.take().unwrap_or_default().set_rarity(rarity));
+}
+
+// colors are counted in the linked group, abyssal sockets have no trade filter
+fn socket_filters(req: &SocketRequirement) -> Option<SocketFilters> {
+    if req.links < 2 {
+        return None;
+    }
+    let color = |c| req.colors.get(&c).copied().filter(|n| *n > 0);
+    Some(SocketFilters::default().set_links(
+        Some(req.links),
+        None,
+        color(SocketColor::R),
+        color(SocketColor::G),
+        color(SocketColor::B),
+        color(SocketColor::W),
+    ))
+}
+
+impl TryFrom<&ItemWithConfig> for Builder {
+    type Error = BuilderError;
+
+    fn try_from(value: &ItemWithConfig) -> Result<Self, Self::Error> {
+        let item = &value.item;
+        let mut builder = Builder::new();
+        let mut stats = StatQuery::new();
+
+        if value.config.basetype {
+            builder.set_type(&item.basetype);
+        }
+        let mut type_filters =
+            trade_category(&item.subcategory).map(|c| TypeFilters::default().set_category(c));
+        if let Some(filters) = value.config.sockets.as_ref().and_then(socket_filters) {
+            builder.set_socket_filters(filters);
+        }
+
+        if let ItemInfo::Gem { level, quality } = item.info {
+            builder.set_type(&item.basetype);
+            builder.set_misc_filters(
+                MiscFilters::default()
+                    .set_gem_level(Some(level as i32), None)
+                    .set_quality(Some(quality as i32), None),
+            );
+        }
+
+        match &value.config.option {
+            Some(ItemConfigOption::Unique) => {
+                set_unique(&mut builder, &mut type_filters, item, None)
+            }
+            // seed, conqueror and radius have no trade stat mapping yet,
+            // they are checked when found items are compared
+            Some(ItemConfigOption::UniqueVariant { mods, .. }) => {
+                set_unique(&mut builder, &mut type_filters, item, None);
+                for id in mods {
+                    stats = add_mod(&mut builder, stats, item, id, &ModOption::Exist)?;
+                }
+            }
+            Some(ItemConfigOption::UniqueRolls { kind, legacy, mods }) => {
+                set_unique(&mut builder, &mut type_filters, item, *kind);
+                for (id, option) in mods {
+                    stats = add_mod(&mut builder, stats, item, id, option)?;
+                }
+                if *legacy {
+                    for m in item.info.mods() {
+                        let id = ModStatId::from(&m.stat_id);
+                        if !mods.contains_key(&id) {
+                            stats = add_mod(&mut builder, stats, item, &id, &ModOption::Exist)?;
+                        }
+                    }
+                }
+            }
+            Some(ItemConfigOption::Mods(mods)) => {
+                for (id, option) in mods {
+                    stats = add_mod(&mut builder, stats, item, id, option)?;
+                }
+            }
+            None => {}
+        }
+
+        if let Some(filters) = type_filters {
+            builder.set_type_filters(filters);
+        }
+        builder.add_stat_group(stats);
+        Ok(builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::{
+        build_calculation::{
+            item_config::{ItemConfig, ItemConfigOption, ModOption, ModStatId},
+            stored_item::{ItemInfo, Mod, StoredItem},
+            ItemWithConfig,
+        },
+        item::types::{Category, SocketColor, SocketRequirement, Subcategory},
+    };
+    use serde_json::Value;
+
+    use super::{trade_stat_id, Builder};
+
+    #[test]
+    fn stat_id_from_text() {
+        assert_eq!(
+            trade_stat_id("+80 to maximum Life"),
+            Some("explicit.stat_3299347043")
+        );
+        assert_eq!(
+            trade_stat_id("12% increased Spell Damage"),
+            Some("explicit.stat_2974417149")
+        );
+        assert_eq!(trade_stat_id("unknown mod"), None);
+    }
+
+    #[test]
+    fn query_from_item() -> anyhow::Result<()> {
+        let stat_id = "base_maximum_life".to_string();
+        let item = ItemWithConfig {
+            item: StoredItem {
+                basetype: "Leather Belt".to_string(),
+                category: Category::Accessories,
+                subcategory: Subcategory::Belt,
+                info: ItemInfo::Accessory {
+                    quality: 0,
+                    mods: vec![Mod {
+                        stat_id: stat_id.clone(),
+                        text: "+80 to maximum Life".to_string(),
+                    }],
+                },
+                ..Default::default()
+            },
+            config: ItemConfig {
+                basetype: true,
+                option: Some(ItemConfigOption::Mods(
+                    [(ModStatId::from(&stat_id), ModOption::Exact(80))].into(),
+                )),
+                sockets: None,
+            },
+        };
+        let builder = Builder::try_from(&item)?;
+        let v: Value = serde_json::to_value(&builder)?;
+        assert_eq!(v["query"]["type"], "Leather Belt");
+        assert_eq!(
+            v["query"]["filters"]["type_filters"]["filters"]["category"]["option"],
+            "accessory.belt"
+        );
+        let filter = &v["query"]["stats"][0]["filters"][0];
+        assert_eq!(filter["id"], "explicit.stat_3299347043");
+        assert_eq!(filter["value"]["min"], 80);
+        assert_eq!(filter["value"]["max"], 80);
+
+        let url = builder.to_url("Mercenaries")?;
+        assert!(url
+            .as_str()
+            .starts_with("https://www.pathofexile.com/trade/search/Mercenaries?q="));
+        Ok(())
+    }
+
+    #[test]
+    fn query_from_unique_and_gem() -> anyhow::Result<()> {
+        let unique = ItemWithConfig {
+            item: StoredItem {
+                name: "Headhunter".to_string(),
+                basetype: "Leather Belt".to_string(),
+                subcategory: Subcategory::Belt,
+                rarity: "unique".to_string(),
+                ..Default::default()
+            },
+            config: ItemConfig {
+                basetype: false,
+                option: Some(ItemConfigOption::Unique),
+                sockets: None,
+            },
+        };
+        let v = serde_json::to_value(Builder::try_from(&unique)?)?;
+        assert_eq!(v["query"]["name"], "Headhunter");
+        assert_eq!(v["query"]["type"], "Leather Belt");
+        let type_filters = &v["query"]["filters"]["type_filters"]["filters"];
+        assert_eq!(type_filters["category"]["option"], "accessory.belt");
+        assert_eq!(type_filters["rarity"]["option"], "unique");
+
+        let gem = ItemWithConfig {
+            item: StoredItem {
+                name: "Fireball".to_string(),
+                basetype: "Fireball".to_string(),
+                subcategory: Subcategory::Gem,
+                info: ItemInfo::Gem {
+                    level: 20,
+                    quality: 20,
+                },
+                ..Default::default()
+            },
+            config: ItemConfig::default(),
+        };
+        let v = serde_json::to_value(Builder::try_from(&gem)?)?;
+        assert_eq!(v["query"]["type"], "Fireball");
+        assert_eq!(
+            v["query"]["filters"]["misc_filters"]["filters"]["gem_level"]["min"],
+            20
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn query_with_sockets() -> anyhow::Result<()> {
+        let item = ItemWithConfig {
+            item: StoredItem {
+                basetype: "Astral Plate".to_string(),
+                subcategory: Subcategory::BodyArmour,
+                ..Default::default()
+            },
+            config: ItemConfig {
+                sockets: Some(SocketRequirement {
+                    links: 6,
+                    colors: [(SocketColor::G, 5), (SocketColor::B, 1)].into(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        };
+        let v = serde_json::to_value(Builder::try_from(&item)?)?;
+        let links = &v["query"]["filters"]["socket_filters"]["filters"]["links"];
+        assert_eq!(links["min"], 6);
+        assert_eq!(links["g"], 5);
+        assert_eq!(links["b"], 1);
+        assert!(links["r"].is_null());
+        Ok(())
+    }
+}
@@ -0,0 +1,325 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io::Read,
+};
+
+use domain::data::MODS;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::poe1::dist::trade_stats;
+
+// bump when normalization or entry format changes
+pub const STAT_MAPPING_VERSION: u32 = 2;
+// order matters: first one is used when type is not specified
+pub const MAPPED_TYPES: &[&str] = &["explicit", "implicit", "crafted", "fractured", "enchant"];
+
+const DOMAIN_NUMBER: &str = r"\+?([0-9\.]+)";
+
+lazy_static! {
+    // generated by print_stat_mapping and committed, so changes show up in review
+    pub static ref STAT_MAPPING: StatMapping =
+        StatMapping::load(&include_bytes!("../../dist/stat_mapping.json")[..]).unwrap();
+}
+
+#[derive(Error, Debug)]
+pub enum StatMappingError {
+    #[error("unsupported mapping version: {0} (expected {STAT_MAPPING_VERSION})")]
+    Version(u32),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct StatMappingEntry {
+    // RePoE stat id, e.g. base_maximum_life
+    pub stat_id: String,
+    // trade ids without type prefix by type, e.g. explicit -> [stat_3299347043].
+    // several trade stats can share one text, all of them are candidates
+    pub trade_ids: BTreeMap<String, Vec<String>>,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct StatMapping {
+    pub version: u32,
+    pub entries: Vec<StatMappingEntry>,
+    pub unmatched_domain: Vec<String>,
+    pub unmatched_trade: Vec<String>,
+    #[serde(skip)]
+    by_stat: HashMap<String, usize>,
+    #[serde(skip)]
+    by_trade: HashMap<String, Vec<usize>>,
+}
+
+fn normalize_domain(key: &str) -> String {
+    key.replace(DOMAIN_NUMBER, "#")
+}
+
+fn normalize_trade(text: &str) -> String {
+    text.replace("+#", "#")
+}
+
+impl StatMapping {
+    pub fn generate() -> StatMapping {
+        StatMapping::from_trade_stats(trade_stats())
+    }
+
+    // trade stats are (type, prefixed id, text)
+    fn from_trade_stats<'a>(
+        stats: impl Iterator<Item = (&'a str, &'a str, &'a str)>,
+    ) -> StatMapping {
+        // (normalized text, type) -> (trade ids, original text)
+        let mut trade: BTreeMap<(String, &str), (BTreeSet<String>, String)> = BTreeMap::new();
+        for (typ, id, text) in stats {
+            if !MAPPED_TYPES.contains(&typ) {
+                continue;
+            }
+            let Some((_, trade_id)) = id.split_once('.') else {
+                continue;
+            };
+            trade
+                .entry((normalize_trade(text), typ))
+                .or_insert_with(|| (BTreeSet::new(), text.to_string()))
+                .0
+                .insert(trade_id.to_string());
+        }
+
+        let mut keys: Vec<_> = MODS.keys().collect();
+        keys.sort();
+
+        let mut entries: BTreeMap<String, StatMappingEntry> = BTreeMap::new();
+        let mut unmatched_domain = BTreeSet::new();
+        let mut used = BTreeSet::new();
+        for key in keys {
+            let normalized = normalize_domain(key);
+            let matched: Vec<_> = MAPPED_TYPES
+                .iter()
+                .filter_map(|typ| {
+                    let k = (normalized.clone(), *typ);
+                    let v = trade.get(&k)?;
+                    used.insert(k);
+                    Some((typ, v))
+                })
+                .collect();
+            for stat_id in MODS[key].ids() {
+                let Some((_, (_, text))) = matched.first() else {
+                    unmatched_domain.insert(stat_id.to_string());
+                    continue;
+                };
+                let e = entries
+                    .entry(stat_id.to_string())
+                    .or_insert_with(|| StatMappingEntry {
+                        stat_id: stat_id.to_string(),
+                        trade_ids: BTreeMap::new(),
+                        text: text.clone(),
+                    });
+                for (typ, (ids, _)) in &matched {
+                    let known = e.trade_ids.entry(typ.to_string()).or_default();
+                    for id in ids {
+                        if !known.contains(id) {
+                            known.push(id.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        let unmatched_domain = unmatched_domain
+            .into_iter()
+            .filter(|id| !entries.contains_key(id))
+            .collect();
+        let unmatched_trade = trade
+            .iter()
+            .filter(|(k, _)| !used.contains(*k))
+            .flat_map(|((_, typ), (ids, _))| ids.iter().map(move |id| format!("{}.{}", typ, id)))
+            .collect();
+
+        StatMapping {
+            version: STAT_MAPPING_VERSION,
+            entries: entries.into_values().collect(),
+            unmatched_domain,
+            unmatched_trade,
+            ..Default::default()
+        }
+        .indexed()
+    }
+
+    pub fn load<R: Read>(r: R) -> Result<StatMapping, StatMappingError> {
+        let mapping: StatMapping = serde_json::from_reader(r)?;
+        if mapping.version != STAT_MAPPING_VERSION {
+            return Err(StatMappingError::Version(mapping.version));
+        }
+        Ok(mapping.indexed())
+    }
+
+    fn indexed(mut self) -> StatMapping {
+        self.by_stat.clear();
+        self.by_trade.clear();
+        for (idx, e) in self.entries.iter().enumerate() {
+            self.by_stat.insert(e.stat_id.clone(), idx);
+            for id in e.trade_ids.values().flatten() {
+                let by_trade = self.by_trade.entry(id.clone()).or_default();
+                if !by_trade.contains(&idx) {
+                    by_trade.push(idx);
+                }
+            }
+        }
+        self
+    }
+
+    // typ is one of MAPPED_TYPES, first available type is used if not specified.
+    // all candidates are returned when several trade stats have the same text
+    pub fn trade_ids(&self, stat_id: &str, typ: Option<&str>) -> Vec<String> {
+        let Some(e) = self.by_stat.get(stat_id).map(|idx| &self.entries[*idx]) else {
+            return vec![];
+        };
+        let found = match typ {
+            Some(typ) => e.trade_ids.get_key_value(typ),
+            None => MAPPED_TYPES.iter().find_map(|t| e.trade_ids.get_key_value(*t)),
+        };
+        found
+            .map(|(typ, ids)| ids.iter().map(|id| format!("{}.{}", typ, id)).collect())
+            .unwrap_or_default()
+    }
+
+    // first candidate of trade_ids
+    pub fn trade_id(&self, stat_id: &str, typ: Option<&str>) -> Option<String> {
+        self.trade_ids(stat_id, typ).into_iter().next()
+    }
+
+    // accepts both prefixed (explicit.stat_...) and bare trade ids
+    pub fn stat_ids(&self, trade_id: &str) -> Vec<&str> {
+        let trade_id = trade_id
+            .split_once('.')
+            .map(|(_, id)| id)
+            .unwrap_or(trade_id);
+        self.by_trade
+            .get(trade_id)
+            .map(|idx| {
+                idx.iter()
+                    .map(|i| self.entries[*i].stat_id.as_str())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::item::types::{Mod, ModType};
+
+    use super::{StatMapping, StatMappingError, STAT_MAPPING, STAT_MAPPING_VERSION};
+
+    #[test]
+    fn generates_from_trade_stats() -> anyhow::Result<()> {
+        let life = Mod::try_by_stat("+80 to maximum Life", ModType::Explicit)?;
+        let fire = Mod::try_by_stat("+40% to Fire Resistance", ModType::Explicit)?;
+        let mapping = StatMapping::from_trade_stats(
+            [
+                ("pseudo", "pseudo.pseudo_total_life", "+# total maximum Life"),
+                ("explicit", "explicit.stat_3299347043", "+# to maximum Life"),
+                ("crafted", "crafted.stat_3299347043", "+# to maximum Life"),
+                ("explicit", "explicit.stat_3372524247", "+#% to Fire Resistance"),
+                ("explicit", "explicit.stat_1", "Trade only stat"),
+                ("explicit", "stat_2", "Unprefixed stat"),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(mapping.version, STAT_MAPPING_VERSION);
+
+        let entry = mapping
+            .entries
+            .iter()
+            .find(|e| e.stat_id == life.stat_id)
+            .unwrap();
+        assert_eq!(entry.text, "+# to maximum Life");
+        assert_eq!(
+            entry.trade_ids.keys().collect::<Vec<_>>(),
+            vec!["crafted", "explicit"]
+        );
+        assert_eq!(
+            mapping.trade_id(&fire.stat_id, None),
+            Some("explicit.stat_3372524247".to_string())
+        );
+
+        assert_eq!(mapping.unmatched_trade, vec!["explicit.stat_1"]);
+        assert!(mapping.stat_ids("pseudo_total_life").is_empty());
+        assert!(mapping.stat_ids("stat_2").is_empty());
+        assert!(!mapping.unmatched_domain.is_empty());
+        assert!(!mapping.unmatched_domain.contains(&life.stat_id));
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_all_candidates() -> anyhow::Result<()> {
+        let life = Mod::try_by_stat("+80 to maximum Life", ModType::Explicit)?;
+        let mapping = StatMapping::from_trade_stats(
+            [
+                ("explicit", "explicit.stat_1", "+# to maximum Life"),
+                ("explicit", "explicit.stat_2", "+# to maximum Life"),
+                ("implicit", "implicit.stat_3", "+# to maximum Life"),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(
+            mapping.trade_ids(&life.stat_id, None),
+            vec!["explicit.stat_1", "explicit.stat_2"]
+        );
+        assert_eq!(
+            mapping.trade_ids(&life.stat_id, Some("implicit")),
+            vec!["implicit.stat_3"]
+        );
+        assert!(mapping.stat_ids("stat_2").contains(&life.stat_id.as_str()));
+        Ok(())
+    }
+
+    #[test]
+    fn normalize() {
+        assert_eq!(
+            super::normalize_domain(r"\+?([0-9\.]+)% to Fire Resistance"),
+            "#% to Fire Resistance"
+        );
+        assert_eq!(
+            super::normalize_trade("+#% to Fire Resistance"),
+            "#% to Fire Resistance"
+        );
+    }
+
+    #[test]
+    fn both_directions() -> anyhow::Result<()> {
+        let life = Mod::try_by_stat("+80 to maximum Life", ModType::Explicit)?;
+        assert_eq!(
+            STAT_MAPPING.trade_id(&life.stat_id, None),
+            Some("explicit.stat_3299347043".to_string())
+        );
+        assert_eq!(
+            STAT_MAPPING.trade_id(&life.stat_id, Some("crafted")),
+            Some("crafted.stat_3299347043".to_string())
+        );
+        assert_eq!(STAT_MAPPING.trade_id(&life.stat_id, Some("enchant")), None);
+        assert!(STAT_MAPPING
+            .stat_ids("implicit.stat_3299347043")
+            .contains(&life.stat_id.as_str()));
+        assert!(!STAT_MAPPING.unmatched_domain.is_empty());
+        assert!(!STAT_MAPPING.unmatched_domain.contains(&life.stat_id));
+        Ok(())
+    }
+
+    #[test]
+    fn load_versioned() -> anyhow::Result<()> {
+        let json = serde_json::to_vec(&*STAT_MAPPING)?;
+        let loaded = StatMapping::load(&json[..])?;
+        assert_eq!(loaded.entries, STAT_MAPPING.entries);
+        assert!(!loaded.stat_ids("stat_3299347043").is_empty());
+
+        let mut v: serde_json::Value = serde_json::from_slice(&json)?;
+        v["version"] = (STAT_MAPPING_VERSION + 1).into();
+        assert!(matches!(
+            StatMapping::load(v.to_string().as_bytes()),
+            Err(StatMappingError::Version(_))
+        ));
+        Ok(())
+    }
+}
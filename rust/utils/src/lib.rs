@@ -1,9 +1,15 @@
 mod limit_middleware;
-pub use limit_middleware::Limits as LimitMiddleware;
+pub use http::Extensions;
+pub use limit_middleware::{
+    FileLimitStore, Limit, LimitAccount, LimitError, LimitHeaders, LimitMetrics, LimitPolicy,
+    LimitSnapshot, LimitStore, LimitStoreError, Limits as LimitMiddleware, LimitsSnapshot,
+    PolicySnapshot,
+};
 pub use reqwest;
 pub use reqwest_middleware::{
     ClientBuilder, ClientWithMiddleware, Error as ReqwestMiddlewareError, Middleware,
 };
 pub mod stream_stashes;
 
-pub static DEFAULT_USER_AGENT: &'static str = "OAuth costmybuild/0.1.0 (contant: bladoff@gmail.com)";
+pub static DEFAULT_USER_AGENT: &str =
+    "OAuth costmybuild/0.1.0 (contant: bladoff@gmail.com)";
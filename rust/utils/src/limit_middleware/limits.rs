@@ -1,134 +1,343 @@
-use std::num::NonZeroU32;
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::num::ParseIntError;
 use std::str::FromStr;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use governor::clock::DefaultClock;
-use governor::state::{InMemoryState, NotKeyed};
-use governor::{Jitter, Quota, RateLimiter};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-#[derive(Debug, PartialEq, Default, Hash, Eq)]
-struct Limit {
+#[derive(Error, Debug, PartialEq)]
+pub enum LimitError {
+    #[error("unknown limit format: {0}")]
+    Format(String),
+    #[error("cannot parse limit value: {0}")]
+    Number(#[from] ParseIntError),
+}
+
+// one rule from `x-rate-limit-*` header, e.g. "8:10:60" (hits:period:penalty).
+// state headers use same format where first value is current hits and last is active penalty
+#[derive(Debug, PartialEq, Default, Hash, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Limit {
     pub hit_count: u32,
     pub watching_time: Duration,
     pub penalty_time: Duration,
 }
 
 impl Limit {
-    fn new(current_hit: u32, watching_time: Duration, penalty_time: Duration) -> Limit {
+    pub fn new(hit_count: u32, watching_time: Duration, penalty_time: Duration) -> Limit {
         Limit {
-            hit_count: current_hit,
+            hit_count,
             watching_time,
             penalty_time,
         }
     }
 
-    fn parse_header(limit: &str) -> Limit {
-        let lms: Vec<&str> = limit.split(':').collect();
+    pub fn parse_header(limit: &str) -> Result<Limit, LimitError> {
+        let lms: Vec<&str> = limit.trim().split(':').collect();
 
         if lms.len() != 3 {
-            panic!("unknown limit format");
+            return Err(LimitError::Format(limit.to_string()));
+        }
+
+        let h = u32::from_str(lms[0])?;
+        let w = u64::from_str(lms[1])?;
+        let p = u64::from_str(lms[2])?;
+
+        Ok(Limit::new(
+            h,
+            Duration::from_secs(w),
+            Duration::from_secs(p),
+        ))
+    }
+
+    pub fn parse_list(limits: &str, sep: &str) -> Result<Vec<Limit>, LimitError> {
+        limits
+            .split(sep)
+            .filter(|l| !l.trim().is_empty())
+            .map(Limit::parse_header)
+            .collect()
+    }
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.hit_count,
+            self.watching_time.as_secs(),
+            self.penalty_time.as_secs()
+        )
+    }
+}
+
+impl From<Limit> for String {
+    fn from(value: Limit) -> Self {
+        value.to_string()
+    }
+}
+
+impl TryFrom<String> for Limit {
+    type Error = LimitError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Limit::parse_header(&value)
+    }
+}
+
+// persisted state of one rule, times are unix timestamps in milliseconds
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct LimitSnapshot {
+    pub limit: Limit,
+    pub hits: Vec<u64>,
+    pub restricted_until: Option<u64>,
+}
+
+// converts between monotonic and wall clock time
+#[derive(Clone, Copy)]
+pub(crate) struct Clock {
+    pub instant: Instant,
+    pub system: SystemTime,
+}
+
+impl Clock {
+    pub fn now() -> Clock {
+        Clock {
+            instant: Instant::now(),
+            system: SystemTime::now(),
         }
+    }
 
-        let h = u32::from_str(lms[0]).expect("cannot parse header value");
-        let w = u64::from_str(lms[1]).expect("cannot parse header value");
-        let p = u64::from_str(lms[2]).expect("cannot parse header value");
+    pub fn to_millis(self, t: Instant) -> u64 {
+        let t = match t.checked_duration_since(self.instant) {
+            Some(d) => self.system + d,
+            None => self.system - self.instant.duration_since(t),
+        };
+        t.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+    }
 
-        Limit::new(h, Duration::from_secs(w), Duration::from_secs(p))
+    // None if time can't be represented, e.g. it's too old
+    pub fn to_instant(self, millis: u64) -> Option<Instant> {
+        let t = UNIX_EPOCH + Duration::from_millis(millis);
+        match t.duration_since(self.system) {
+            Ok(d) => self.instant.checked_add(d),
+            Err(e) => self.instant.checked_sub(e.duration()),
+        }
     }
 }
 
-#[derive(Error, Debug)]
-pub enum MultipleLimitsError {
-    #[error("current limits order does not correspond to initially provided")]
-    LimitMatch,
-    #[error("limits states len does not equal to limits")]
-    LimitsLen,
+#[derive(Debug)]
+struct LimitState {
+    limit: Limit,
+    hits: VecDeque<Instant>,
+    restricted_until: Option<Instant>,
+}
+
+impl LimitState {
+    fn new(limit: Limit) -> LimitState {
+        LimitState {
+            limit,
+            hits: VecDeque::new(),
+            restricted_until: None,
+        }
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while let Some(h) = self.hits.front() {
+            if now.duration_since(*h) >= self.limit.watching_time {
+                self.hits.pop_front();
+            } else {
+                break;
+            }
+        }
+        if self.restricted_until.is_some_and(|r| r <= now) {
+            self.restricted_until = None;
+        }
+    }
+
+    fn restrict(&mut self, until: Instant) {
+        self.restricted_until = Some(self.restricted_until.map_or(until, |r| r.max(until)));
+    }
+
+    fn wait_time(&mut self, now: Instant) -> Option<Duration> {
+        self.prune(now);
+        if let Some(r) = self.restricted_until {
+            return Some(r - now);
+        }
+        if self.limit.hit_count > 0 && self.hits.len() >= self.limit.hit_count as usize {
+            let idx = self.hits.len() - self.limit.hit_count as usize;
+            return Some(self.hits[idx] + self.limit.watching_time - now);
+        }
+        None
+    }
 }
 
+// local sliding window state for all rules of one limit kind (ip, account, client)
 #[derive(Debug, Default)]
 pub struct MultipleLimits {
-    limits: dashmap::DashMap<Limit, RateLimiter<NotKeyed, InMemoryState, DefaultClock>>,
+    limits: Vec<LimitState>,
 }
 
 impl MultipleLimits {
-    pub fn new_with_limit(limits: &str, sep: &str) -> MultipleLimits {
-        let split_limits = limits.split(sep).collect::<Vec<_>>();
-        // todo: vec resize optimization
-        let mut limits = MultipleLimits::default();
-        split_limits.into_iter().for_each(|lm| limits.add_limit(lm));
-        limits
+    // how long to wait before next request is allowed
+    pub fn wait_time(&mut self, now: Instant) -> Option<Duration> {
+        self.limits
+            .iter_mut()
+            .filter_map(|l| l.wait_time(now))
+            .max()
+    }
+
+    pub fn snapshot(&mut self, clock: Clock) -> Vec<LimitSnapshot> {
+        self.limits
+            .iter_mut()
+            .map(|l| {
+                l.prune(clock.instant);
+                LimitSnapshot {
+                    limit: l.limit,
+                    hits: l.hits.iter().map(|h| clock.to_millis(*h)).collect(),
+                    restricted_until: l.restricted_until.map(|r| clock.to_millis(r)),
+                }
+            })
+            .collect()
     }
 
-    fn add_limit(&self, limits: &str) {
-        let new_limits = Limit::parse_header(limits);
-        let hit = NonZeroU32::try_from(new_limits.hit_count)
-            .expect("cannot add limit with zero hit count, probably you provided current limit");
-        // let quota = Quota::new(hit, new_limits.watching_time).unwrap();
-        let quota = Quota::with_period(new_limits.watching_time)
-            .unwrap()
-            .allow_burst(hit);
-
-        let lim = RateLimiter::direct(quota);
-
-        self.limits.insert(new_limits, lim);
-    }
-
-    pub fn is_empty(&self) -> bool {
-        self.limits.is_empty()
-    }
-
-    // pub fn reinit_limits(&self, limits: &str, sep: &str) {
-    //     let split_limits = limits.split(sep).collect::<Vec<_>>();
-    //     // todo: vec resize optimization
-    //     let mut limits = MultipleLimits::default();
-    //     split_limits.into_iter().for_each(|lm| limits.add_limit(lm));
-    //     self.limits.clear();
-    //     self.limits.extend(limits.limits);
-    // }
-
-    // pub async fn adjust_current_states(
-    //     &self,
-    //     states: MultipleLimits,
-    // ) -> Result<(), MultipleLimitsError> {
-    //     if states.limits.len() != self.limits.len() {
-    //         return Err(MultipleLimitsError::LimitsLen);
-    //     }
-    //
-    //     for lm in self.limits.iter() {
-    //         let state_limit = states.limits.get(lm.key()).unwrap();
-    //         if lm.key().watching_time != state_limit.key().watching_time {
-    //             return Err(MultipleLimitsError::LimitMatch);
-    //         }
-    //     }
-    //
-    //     // for (i, lm) in &mut self.limits.iter_mut().enumerate() {
-    //     //     let state_limit = states.limits.get(i).unwrap();
-    //     //     let _ =
-    //     //         lm.1.until_ready_with_jitter(Jitter::new(
-    //     //             Duration::from_secs(1),
-    //     //             state_limit.0.penalty_time,
-    //     //         ))
-    //     //         .await;
-    //     // }
-    //
-    //     Ok(())
-    // }
-
-    pub async fn until_ready(&self) {
-        for lm in &self.limits {
-            lm.value().until_ready().await;
+    pub fn restore(snapshot: &[LimitSnapshot], clock: Clock) -> MultipleLimits {
+        let limits = snapshot
+            .iter()
+            .map(|s| {
+                let mut l = LimitState::new(s.limit);
+                l.hits = s.hits.iter().filter_map(|h| clock.to_instant(*h)).collect();
+                l.restricted_until = s.restricted_until.and_then(|r| clock.to_instant(r));
+                l.prune(clock.instant);
+                l
+            })
+            .collect();
+        MultipleLimits { limits }
+    }
+
+    pub fn hit(&mut self, now: Instant) {
+        for l in &mut self.limits {
+            l.hits.push_back(now);
+        }
+    }
+
+    // reconciles local state with rules and current state reported by server
+    pub fn update(&mut self, rules: &[Limit], states: &[Limit], now: Instant) {
+        let same_rules = rules.len() == self.limits.len()
+            && rules.iter().zip(&self.limits).all(|(r, l)| *r == l.limit);
+        if !same_rules {
+            let mut new_limits: Vec<_> = rules.iter().copied().map(LimitState::new).collect();
+            // keep tracked hits, they still count against new rules
+            if let Some(hits) = self.limits.iter().map(|l| &l.hits).max_by_key(|h| h.len()) {
+                for l in &mut new_limits {
+                    l.hits = hits.clone();
+                }
+            }
+            self.limits = new_limits;
+        }
+
+        for state in states {
+            let Some(l) = self
+                .limits
+                .iter_mut()
+                .find(|l| l.limit.watching_time == state.watching_time)
+            else {
+                continue;
+            };
+            l.prune(now);
+            // server saw more hits than we did, e.g. from another process
+            let missing = (state.hit_count as usize).saturating_sub(l.hits.len());
+            l.hits.extend(std::iter::repeat_n(now, missing));
+            if !state.penalty_time.is_zero() {
+                l.restrict(now + state.penalty_time);
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::time::{Duration, Instant};
+
     use super::*;
 
+    fn limits(rules: &str) -> MultipleLimits {
+        let mut lm = MultipleLimits::default();
+        lm.update(&Limit::parse_list(rules, ",").unwrap(), &[], Instant::now());
+        lm
+    }
+
     #[test]
     fn init_limits() {
-        let lm = MultipleLimits::new_with_limit("5:5:60;20:180:180", ";");
+        let lm = limits("5:5:60,20:180:180");
+        assert_eq!(lm.limits.len(), 2);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(matches!(
+            Limit::parse_header("5:5"),
+            Err(LimitError::Format(_))
+        ));
+        assert!(matches!(
+            Limit::parse_header("a:5:60"),
+            Err(LimitError::Number(_))
+        ));
+        assert!(Limit::parse_list("5:5:60,x", ",").is_err());
+    }
+
+    #[test]
+    fn local_hits() {
+        let now = Instant::now();
+        let mut lm = limits("2:10:60");
+        assert_eq!(lm.wait_time(now), None);
+        lm.hit(now);
+        lm.hit(now + Duration::from_secs(1));
+        assert_eq!(
+            lm.wait_time(now + Duration::from_secs(2)),
+            Some(Duration::from_secs(8))
+        );
+        assert_eq!(lm.wait_time(now + Duration::from_secs(10)), None);
+    }
+
+    #[test]
+    fn server_state() {
+        let now = Instant::now();
+        let rules = Limit::parse_list("3:10:60,10:60:120", ",").unwrap();
+        let mut lm = MultipleLimits::default();
+
+        let states = Limit::parse_list("3:10:0,3:60:0", ",").unwrap();
+        lm.update(&rules, &states, now);
         assert_eq!(lm.limits.len(), 2);
+        assert_eq!(lm.wait_time(now), Some(Duration::from_secs(10)));
+
+        let states = Limit::parse_list("4:10:60,4:60:0", ",").unwrap();
+        lm.update(&rules, &states, now);
+        assert_eq!(lm.wait_time(now), Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn snapshot_roundtrip() {
+        let clock = Clock::now();
+        let mut lm = limits("2:10:60,5:60:120");
+        lm.hit(clock.instant);
+        lm.limits[1].restrict(clock.instant + Duration::from_secs(30));
+
+        let snapshot = lm.snapshot(clock);
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(json.contains(r#""limit":"2:10:60""#));
+        let snapshot: Vec<LimitSnapshot> = serde_json::from_str(&json).unwrap();
+
+        // restored in another "process" 5 seconds later
+        let later = Clock {
+            instant: clock.instant + Duration::from_secs(5),
+            system: clock.system + Duration::from_secs(5),
+        };
+        let mut restored = MultipleLimits::restore(&snapshot, later);
+        assert_eq!(restored.limits[0].hits.len(), 1);
+        let wait = restored.wait_time(later.instant).unwrap();
+        assert!(wait <= Duration::from_secs(25) && wait > Duration::from_secs(24));
     }
 }
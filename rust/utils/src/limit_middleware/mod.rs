@@ -1,12 +1,19 @@
 mod limits;
+mod store;
 
 use core::convert::TryFrom;
 use http::Extensions;
-use limits::MultipleLimits;
-use reqwest::{Request, Response};
+use limits::{Clock, MultipleLimits};
+pub use limits::{Limit, LimitError, LimitSnapshot};
+use reqwest::{header::HeaderMap, Request, Response, StatusCode};
 use reqwest_middleware::{Middleware, Next, Result};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+pub use store::{FileLimitStore, LimitStore, LimitStoreError, LimitsSnapshot, PolicySnapshot};
 use thiserror::Error;
+use tracing::{debug, warn};
 
 #[derive(Error, Debug)]
 pub enum LimitHeadersError {
@@ -14,7 +21,8 @@ pub enum LimitHeadersError {
     Variant,
 }
 
-#[derive(PartialEq, Eq, Hash)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum LimitHeaders {
     Ip,
     Account,
@@ -25,32 +33,315 @@ impl TryFrom<&str> for LimitHeaders {
     type Error = LimitHeadersError;
 
     fn try_from(value: &str) -> core::result::Result<Self, Self::Error> {
-        Ok(match value {
-            "x-rate-limit-account-state" => LimitHeaders::Account,
-            "x-rate-limit-ip-state" => LimitHeaders::Ip,
-            "x-rate-limit-client-state" => LimitHeaders::Client,
+        Ok(match value.to_lowercase().as_str() {
+            "ip" => LimitHeaders::Ip,
+            "account" => LimitHeaders::Account,
+            "client" => LimitHeaders::Client,
             _ => return Err(LimitHeadersError::Variant),
         })
     }
 }
 
+impl LimitHeaders {
+    // name used in `x-rate-limit-rules`
+    pub fn name(&self) -> &'static str {
+        match self {
+            LimitHeaders::Ip => "ip",
+            LimitHeaders::Account => "account",
+            LimitHeaders::Client => "client",
+        }
+    }
+
+    pub fn rules_header(&self) -> &'static str {
+        match self {
+            LimitHeaders::Ip => "x-rate-limit-ip",
+            LimitHeaders::Account => "x-rate-limit-account",
+            LimitHeaders::Client => "x-rate-limit-client",
+        }
+    }
+
+    pub fn state_header(&self) -> &'static str {
+        match self {
+            LimitHeaders::Ip => "x-rate-limit-ip-state",
+            LimitHeaders::Account => "x-rate-limit-account-state",
+            LimitHeaders::Client => "x-rate-limit-client-state",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LimitMetrics {
+    pub requests: u64,
+    // requests delayed by local limiter
+    pub throttled: u64,
+    pub waited: Duration,
+    // 429 responses from server
+    pub rate_limited: u64,
+    pub header_errors: u64,
+}
+
+// rate limit policy name from `x-rate-limit-policy`, attach as request extension
+// when policy is known beforehand, otherwise it's learned from responses per endpoint
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LimitPolicy(pub String);
+
+// identifies account for account-wide limits, e.g. POESESSID or account name
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LimitAccount(pub String);
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+struct LimitKey {
+    policy: String,
+    kind: LimitHeaders,
+    // only set for account limits, ip and client limits are shared.
+    // account limits of requests without LimitAccount are shared too
+    account: Option<String>,
+}
+
 #[derive(Default)]
+struct LimitsState {
+    lm: HashMap<LimitKey, MultipleLimits>,
+    // endpoint -> policy
+    endpoints: HashMap<String, String>,
+    metrics: HashMap<String, LimitMetrics>,
+    retry_until: HashMap<String, Instant>,
+}
+
+impl LimitsState {
+    fn limits<'a>(
+        &'a mut self,
+        policy: &'a str,
+        account: Option<&'a str>,
+    ) -> impl Iterator<Item = &'a mut MultipleLimits> + 'a {
+        self.lm
+            .iter_mut()
+            .filter(move |(k, _)| {
+                k.policy == policy && (k.account.is_none() || k.account.as_deref() == account)
+            })
+            .map(|(_, l)| l)
+    }
+}
+
+// policy-aware limiter, clones share same state so one instance can be used by all clients
+#[derive(Default, Clone)]
 pub struct Limits {
-    lm: dashmap::DashMap<LimitHeaders, limits::MultipleLimits>,
+    state: Arc<Mutex<LimitsState>>,
+}
+
+static SHARED: LazyLock<Limits> = LazyLock::new(Limits::default);
+
+fn endpoint(req: &Request) -> String {
+    let url = req.url();
+    let path = url.path();
+    let path = path.rsplit_once('/').map(|(p, _)| p).unwrap_or(path);
+    format!(
+        "{} {}{}",
+        req.method(),
+        url.host_str().unwrap_or_default(),
+        path
+    )
 }
 
 impl Limits {
-    // pub fn new(init_limits: HashMap<&str, &str>) -> Limits {
-    //     let lm = init_limits
-    //         .into_iter()
-    //         .filter_map(|(k, v)| {
-    //             k.try_into()
-    //                 .ok()
-    //                 .map(|k| (k, MultipleLimits::new_with_limit(v, ",")))
-    //         })
-    //         .collect();
-    //     Limits { lm }
-    // }
+    // process-wide limiter
+    pub fn shared() -> Limits {
+        SHARED.clone()
+    }
+
+    pub fn snapshot(&self) -> LimitsSnapshot {
+        let clock = Clock::now();
+        let mut state = self.state.lock().unwrap();
+        let policies = state
+            .lm
+            .iter_mut()
+            .map(|(k, l)| PolicySnapshot {
+                policy: k.policy.clone(),
+                kind: k.kind,
+                account: k.account.clone(),
+                rules: l.snapshot(clock),
+            })
+            .collect();
+        let retry_until = state
+            .retry_until
+            .iter()
+            .filter(|(_, r)| **r > clock.instant)
+            .map(|(p, r)| (p.clone(), clock.to_millis(*r)))
+            .collect();
+        LimitsSnapshot {
+            policies,
+            retry_until,
+            endpoints: state.endpoints.clone(),
+            ..Default::default()
+        }
+    }
+
+    // replaces state of policies present in snapshot
+    pub fn restore(&self, snapshot: &LimitsSnapshot) {
+        let clock = Clock::now();
+        let mut state = self.state.lock().unwrap();
+        for p in &snapshot.policies {
+            let key = LimitKey {
+                policy: p.policy.clone(),
+                kind: p.kind,
+                account: p.account.clone(),
+            };
+            state
+                .lm
+                .insert(key, MultipleLimits::restore(&p.rules, clock));
+        }
+        for (policy, r) in &snapshot.retry_until {
+            if let Some(r) = clock.to_instant(*r).filter(|r| *r > clock.instant) {
+                state.retry_until.insert(policy.clone(), r);
+            }
+        }
+        state.endpoints.extend(snapshot.endpoints.clone());
+    }
+
+    pub fn save(&self, store: &dyn LimitStore) -> std::result::Result<(), LimitStoreError> {
+        store.save(&self.snapshot())
+    }
+
+    // returns false if store was empty
+    pub fn load(&self, store: &dyn LimitStore) -> std::result::Result<bool, LimitStoreError> {
+        let snapshot = store.load()?;
+        if let Some(snapshot) = &snapshot {
+            self.restore(snapshot);
+        }
+        Ok(snapshot.is_some())
+    }
+
+    pub fn metrics(&self, policy: &str) -> LimitMetrics {
+        let state = self.state.lock().unwrap();
+        state.metrics.get(policy).cloned().unwrap_or_default()
+    }
+
+    // static rules for endpoints which don't report limits, e.g. poe.ninja
+    pub fn set_rules(&self, policy: &str, kind: LimitHeaders, rules: &[Limit]) {
+        let mut state = self.state.lock().unwrap();
+        let key = LimitKey {
+            policy: policy.to_string(),
+            kind,
+            account: None,
+        };
+        state
+            .lm
+            .entry(key)
+            .or_default()
+            .update(rules, &[], Instant::now());
+    }
+
+    // time left until policy accepts requests again
+    pub fn wait_time(&self, policy: &str, account: Option<&str>) -> Option<Duration> {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let retry = state
+            .retry_until
+            .get(policy)
+            .and_then(|r| r.checked_duration_since(now));
+        state
+            .limits(policy, account)
+            .filter_map(|l| l.wait_time(now))
+            .chain(retry)
+            .filter(|w| !w.is_zero())
+            .max()
+    }
+
+    // either reserves a hit and returns None or returns time to wait
+    fn acquire(&self, policy: &str, account: Option<&str>, now: Instant) -> Option<Duration> {
+        let mut state = self.state.lock().unwrap();
+        let retry = state
+            .retry_until
+            .get(policy)
+            .and_then(|r| r.checked_duration_since(now))
+            .filter(|w| !w.is_zero());
+        let wait = state
+            .limits(policy, account)
+            .filter_map(|l| l.wait_time(now))
+            .chain(retry)
+            .max();
+        match wait {
+            Some(wait) => {
+                let m = state.metrics.entry(policy.to_string()).or_default();
+                m.throttled += 1;
+                m.waited += wait;
+            }
+            None => {
+                state.limits(policy, account).for_each(|l| l.hit(now));
+            }
+        }
+        wait
+    }
+
+    fn update(
+        &self,
+        policy: Option<&str>,
+        account: Option<&str>,
+        status: StatusCode,
+        headers: &HeaderMap,
+        now: Instant,
+    ) -> Option<String> {
+        let mut state = self.state.lock().unwrap();
+        let policy = headers
+            .get("x-rate-limit-policy")
+            .and_then(|v| v.to_str().ok())
+            .or(policy)?
+            .to_string();
+        let kinds = headers
+            .get("x-rate-limit-rules")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+        let mut header_errors = 0;
+        for kind in kinds
+            .split(',')
+            .filter_map(|k| LimitHeaders::try_from(k.trim()).ok())
+        {
+            let parse = |name: &str| {
+                let v = headers
+                    .get(name)
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or_default();
+                Limit::parse_list(v, ",")
+            };
+            match (parse(kind.rules_header()), parse(kind.state_header())) {
+                (Ok(rules), Ok(states)) => {
+                    let key = LimitKey {
+                        policy: policy.clone(),
+                        kind,
+                        account: account
+                            .filter(|_| kind == LimitHeaders::Account)
+                            .map(String::from),
+                    };
+                    state
+                        .lm
+                        .entry(key)
+                        .or_default()
+                        .update(&rules, &states, now);
+                }
+                (Err(e), _) | (_, Err(e)) => {
+                    warn!("cannot parse rate limit headers for {:?}: {}", kind, e);
+                    header_errors += 1;
+                }
+            }
+        }
+
+        let retry_after = headers
+            .get("retry-after")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok());
+        if let Some(secs) = retry_after {
+            let until = now + Duration::from_secs(secs);
+            let r = state.retry_until.entry(policy.clone()).or_insert(until);
+            *r = (*r).max(until);
+        }
+
+        let m = state.metrics.entry(policy.clone()).or_default();
+        m.requests += 1;
+        m.header_errors += header_errors;
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            m.rate_limited += 1;
+        }
+        Some(policy)
+    }
 }
 
 #[async_trait::async_trait]
@@ -61,23 +352,191 @@ impl Middleware for Limits {
         extensions: &mut Extensions,
         next: Next<'_>,
     ) -> Result<Response> {
-        for limiter in &self.lm {
-            limiter.value().until_ready().await;
+        let endpoint = endpoint(&req);
+        let policy = extensions
+            .get::<LimitPolicy>()
+            .map(|p| p.0.clone())
+            .or_else(|| self.state.lock().unwrap().endpoints.get(&endpoint).cloned());
+        let account = extensions.get::<LimitAccount>().map(|a| a.0.clone());
+
+        if let Some(policy) = &policy {
+            while let Some(wait) = self.acquire(policy, account.as_deref(), Instant::now()) {
+                debug!("rate limit {}: waiting {}ms", policy, wait.as_millis());
+                tokio::time::sleep(wait).await;
+            }
         }
 
         let resp = next.run(req, extensions).await?;
-
-        if self.lm.is_empty() {
-            for (k, v) in resp.headers().iter() {
-                if let Ok(lh) = k.as_str().try_into() {
-                    self.lm.insert(
-                        lh,
-                        MultipleLimits::new_with_limit(v.to_str().unwrap_or(""), ","),
-                    );
-                }
-            }
+        let learned = self.update(
+            policy.as_deref(),
+            account.as_deref(),
+            resp.status(),
+            resp.headers(),
+            Instant::now(),
+        );
+        if let Some(learned) = learned {
+            self.state
+                .lock()
+                .unwrap()
+                .endpoints
+                .insert(endpoint, learned);
         }
 
         Ok(resp)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use reqwest::{
+        header::{HeaderMap, HeaderValue},
+        StatusCode,
+    };
+
+    use super::{FileLimitStore, Limit, LimitHeaders, Limits};
+
+    fn headers(v: &[(&'static str, &'static str)]) -> HeaderMap {
+        v.iter()
+            .map(|(k, v)| (k.parse().unwrap(), HeaderValue::from_static(v)))
+            .collect()
+    }
+
+    #[test]
+    fn update_from_headers() {
+        let limits = Limits::default();
+        let now = Instant::now();
+        assert_eq!(limits.acquire("search", None, now), None);
+
+        limits.update(
+            None,
+            None,
+            StatusCode::OK,
+            &headers(&[
+                ("x-rate-limit-policy", "search"),
+                ("x-rate-limit-rules", "Ip,Account"),
+                ("x-rate-limit-ip", "2:10:60"),
+                ("x-rate-limit-ip-state", "1:10:0"),
+                ("x-rate-limit-account", "5:10:60"),
+                ("x-rate-limit-account-state", "bad"),
+            ]),
+            now,
+        );
+        assert_eq!(limits.metrics("search").header_errors, 1);
+        assert_eq!(limits.acquire("search", None, now), None);
+        assert_eq!(
+            limits.acquire("search", None, now),
+            Some(Duration::from_secs(10))
+        );
+        // other policies are not affected
+        assert_eq!(limits.acquire("fetch", None, now), None);
+
+        limits.update(
+            Some("search"),
+            None,
+            StatusCode::TOO_MANY_REQUESTS,
+            &headers(&[("retry-after", "30")]),
+            now,
+        );
+        assert_eq!(
+            limits.acquire("search", None, now),
+            Some(Duration::from_secs(30))
+        );
+
+        let metrics = limits.metrics("search");
+        assert_eq!(metrics.requests, 2);
+        assert_eq!(metrics.throttled, 2);
+        assert_eq!(metrics.rate_limited, 1);
+    }
+
+    #[test]
+    fn shared_between_clients() {
+        let limits = Limits::default();
+        let other = limits.clone();
+        let now = Instant::now();
+        let headers = headers(&[
+            ("x-rate-limit-policy", "stash"),
+            ("x-rate-limit-rules", "Account"),
+            ("x-rate-limit-account", "1:10:60"),
+            ("x-rate-limit-account-state", "1:10:0"),
+        ]);
+        limits.update(None, Some("acc1"), StatusCode::OK, &headers, now);
+        assert_eq!(
+            other.acquire("stash", Some("acc1"), now),
+            Some(Duration::from_secs(10))
+        );
+        // account limits are tracked per account
+        assert_eq!(other.acquire("stash", Some("acc2"), now), None);
+
+        other.set_rules(
+            "ninja",
+            LimitHeaders::Client,
+            &[Limit::new(1, Duration::from_secs(1), Duration::ZERO)],
+        );
+        assert_eq!(limits.acquire("ninja", None, now), None);
+        assert!(limits.wait_time("ninja", None).is_some());
+        assert!(other.acquire("ninja", None, now).is_some());
+    }
+
+    #[test]
+    fn account_limits_without_account() {
+        let limits = Limits::default();
+        let now = Instant::now();
+        limits.update(
+            None,
+            None,
+            StatusCode::OK,
+            &headers(&[
+                ("x-rate-limit-policy", "trade"),
+                ("x-rate-limit-rules", "Account"),
+                ("x-rate-limit-account", "1:10:60"),
+                ("x-rate-limit-account-state", "1:10:0"),
+            ]),
+            now,
+        );
+        assert_eq!(
+            limits.acquire("trade", None, now),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(limits.snapshot().policies[0].account, None);
+    }
+
+    #[test]
+    fn persist_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileLimitStore::new(dir.path().join("limits.json"));
+        let limits = Limits::default();
+        assert!(!limits.load(&store).unwrap());
+
+        let now = Instant::now();
+        limits.update(
+            None,
+            Some("acc"),
+            StatusCode::TOO_MANY_REQUESTS,
+            &headers(&[
+                ("x-rate-limit-policy", "stash"),
+                ("x-rate-limit-rules", "Account"),
+                ("x-rate-limit-account", "1:10:60"),
+                ("x-rate-limit-account-state", "2:10:60"),
+                ("retry-after", "60"),
+            ]),
+            now,
+        );
+        limits.save(&store).unwrap();
+
+        let restored = Limits::default();
+        assert!(restored.load(&store).unwrap());
+        // timestamps may differ by a millisecond after clock conversion
+        let policies = restored.snapshot().policies;
+        assert_eq!(policies.len(), 1);
+        assert_eq!(policies[0].account.as_deref(), Some("acc"));
+        assert_eq!(
+            policies[0].rules[0].limit,
+            Limit::parse_header("1:10:60").unwrap()
+        );
+        assert_eq!(policies[0].rules[0].hits.len(), 2);
+        let wait = restored.wait_time("stash", Some("acc")).unwrap();
+        assert!(wait > Duration::from_secs(50));
+    }
+}
@@ -0,0 +1,97 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{limits::LimitSnapshot, LimitHeaders};
+
+// bump when snapshot format changes
+pub const LIMITS_SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Error, Debug)]
+pub enum LimitStoreError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("unsupported snapshot version: {0} (expected {LIMITS_SNAPSHOT_VERSION})")]
+    Version(u32),
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct PolicySnapshot {
+    pub policy: String,
+    pub kind: LimitHeaders,
+    pub account: Option<String>,
+    pub rules: Vec<LimitSnapshot>,
+}
+
+// limiter state which can be saved on shutdown and restored on startup,
+// times are unix timestamps in milliseconds
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct LimitsSnapshot {
+    pub version: u32,
+    pub policies: Vec<PolicySnapshot>,
+    pub retry_until: HashMap<String, u64>,
+    pub endpoints: HashMap<String, String>,
+}
+
+impl Default for LimitsSnapshot {
+    fn default() -> Self {
+        LimitsSnapshot {
+            version: LIMITS_SNAPSHOT_VERSION,
+            policies: vec![],
+            retry_until: HashMap::new(),
+            endpoints: HashMap::new(),
+        }
+    }
+}
+
+pub trait LimitStore: Send + Sync {
+    // None if nothing was saved yet
+    fn load(&self) -> Result<Option<LimitsSnapshot>, LimitStoreError>;
+    fn save(&self, snapshot: &LimitsSnapshot) -> Result<(), LimitStoreError>;
+}
+
+pub struct FileLimitStore {
+    path: PathBuf,
+}
+
+impl FileLimitStore {
+    pub fn new<P: AsRef<Path>>(path: P) -> FileLimitStore {
+        FileLimitStore {
+            path: path.as_ref().into(),
+        }
+    }
+}
+
+impl LimitStore for FileLimitStore {
+    fn load(&self) -> Result<Option<LimitsSnapshot>, LimitStoreError> {
+        let f = match File::open(&self.path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let snapshot: LimitsSnapshot = serde_json::from_reader(BufReader::new(f))?;
+        if snapshot.version != LIMITS_SNAPSHOT_VERSION {
+            return Err(LimitStoreError::Version(snapshot.version));
+        }
+        Ok(Some(snapshot))
+    }
+
+    fn save(&self, snapshot: &LimitsSnapshot) -> Result<(), LimitStoreError> {
+        // write to temporary file first so crash doesn't leave broken state
+        let tmp = self.path.with_extension("tmp");
+        let mut w = BufWriter::new(File::create(&tmp)?);
+        serde_json::to_writer(&mut w, snapshot)?;
+        w.flush()?;
+        drop(w);
+        std::fs::rename(tmp, &self.path)?;
+        Ok(())
+    }
+}
@@ -1,28 +1,90 @@
 use std::{
+    collections::{HashMap, HashSet},
     fs::File,
-    io::{Cursor, Read},
-    os::unix::fs::MetadataExt,
+    io::{BufRead, BufReader, ErrorKind, Read},
     path::{Path, PathBuf},
 };
 
-use compress_tools::uncompress_archive_file;
+use compress_tools::{ArchiveContents, ArchiveIterator};
+use serde::Deserialize;
+use thiserror::Error;
 
-const NEXT_CHANGE_LINE: &str = "\"next_change_id\"";
+#[derive(Error, Debug)]
+pub enum StashesError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("archive error: {0}")]
+    Archive(#[from] compress_tools::Error),
+    #[error("invalid utf-8 in {0}")]
+    Utf8(String),
+    // reading continues from the earliest of later files
+    #[error("change id {expected} is missing, {remaining} later files follow")]
+    Gap { expected: String, remaining: usize },
+    // same as Gap, when entry has no readable next_change_id, e.g. truncated file
+    #[error("{file} has no next change id, {remaining} later files follow")]
+    NoNextChangeId { file: String, remaining: usize },
+    #[error("change id {0} not found")]
+    NotFound(String),
+}
 
-fn extract_next_change_id(s: &str) -> Option<&str> {
-    if let Some(mut idx) = s.find(NEXT_CHANGE_LINE) {
-        idx += NEXT_CHANGE_LINE.len();
-        let first_semi = s[idx..].find("\"").unwrap() + idx;
-        let second_semi = s[first_semi + 1..].find("\"").unwrap() + first_semi + 1;
-        let change_id = &s[first_semi + 1..second_semi];
-        Some(change_id)
-    } else {
-        None
+// (file name, stash json)
+pub type StashesEntry = Result<(String, String), StashesError>;
+
+#[derive(Deserialize)]
+struct NextChangeId {
+    next_change_id: String,
+}
+
+// top level next_change_id, other fields are skipped without allocating.
+// None if json is invalid, e.g. truncated file
+pub fn extract_next_change_id(s: &str) -> Option<String> {
+    serde_json::from_str::<NextChangeId>(s)
+        .ok()
+        .map(|c| c.next_change_id)
+}
+
+// change id of entry named `{change_id}.json`
+pub fn entry_change_id(name: &str) -> &str {
+    name.strip_suffix(".json").unwrap_or(name)
+}
+
+fn counters(id: &str) -> Option<Vec<u64>> {
+    id.split('-').map(|p| p.parse::<u64>().ok()).collect()
+}
+
+// change ids are per shard counters, so later id has no smaller counters
+fn is_later(id: &str, than: &str) -> bool {
+    if id == "index" {
+        return false;
+    }
+    match (counters(id), counters(than)) {
+        (Some(a), Some(b)) if a.len() == b.len() => {
+            a != b && a.iter().zip(&b).all(|(x, y)| x >= y)
+        }
+        _ => true,
     }
 }
 
+// earliest of change ids later than `than` and number of such ids
+fn earliest_later<'a, I>(ids: I, than: &str) -> (Option<&'a String>, usize)
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    let later: Vec<_> = ids.into_iter().filter(|id| is_later(id, than)).collect();
+    let earliest = later.iter().copied().min_by_key(|id| {
+        let sum = counters(id).map(|c| c.iter().sum::<u64>());
+        (sum.is_none(), sum, id.as_str())
+    });
+    (earliest, later.len())
+}
+
 trait StashesSource {
-    fn next(&mut self) -> Option<(String, String)>;
+    fn next(&mut self) -> Option<StashesEntry>;
+
+    // entries are named after change id they were fetched with
+    fn verifiable(&self) -> bool {
+        true
+    }
 }
 
 pub struct StashesIterator {
@@ -30,30 +92,111 @@ pub struct StashesIterator {
 }
 
 impl Iterator for StashesIterator {
-    type Item = (String, String);
+    type Item = StashesEntry;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.src.next()
     }
 }
 
+impl StashesIterator {
+    // false if entry names are derived from previous entry, gaps in such chain can't be detected
+    pub fn is_verifiable(&self) -> bool {
+        self.src.verifiable()
+    }
+
+    // skips entries before given change id, errors if it's never found
+    pub fn resume_from(self, change_id: &str) -> StashesIterator {
+        StashesIterator {
+            src: Box::new(ResumeFrom {
+                src: self.src,
+                change_id: change_id.to_string(),
+                found: false,
+            }),
+        }
+    }
+}
+
+struct ResumeFrom {
+    src: Box<dyn StashesSource>,
+    change_id: String,
+    found: bool,
+}
+
+impl StashesSource for ResumeFrom {
+    fn next(&mut self) -> Option<StashesEntry> {
+        if self.found {
+            return self.src.next();
+        }
+        loop {
+            match self.src.next() {
+                Some(Ok((name, _))) if entry_change_id(&name) != self.change_id => continue,
+                Some(Ok(entry)) => {
+                    self.found = true;
+                    return Some(Ok(entry));
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    self.found = true;
+                    return Some(Err(StashesError::NotFound(self.change_id.clone())));
+                }
+            }
+        }
+    }
+
+    fn verifiable(&self) -> bool {
+        self.src.verifiable()
+    }
+}
+
+// directory with `{change_id}.json` files, follows next_change_id chain starting from index.json,
+// or from the earliest change id if there is no index.json, e.g. in rotated recorder segments
 pub struct DirStashes {
     path: PathBuf,
     next_change_id: Option<String>,
+    // change ids of files not read yet
+    unread: HashSet<String>,
+    // reported after the entry it belongs to
+    pending: Option<StashesError>,
 }
 
 impl DirStashes {
-    pub fn new<P>(path: P) -> Self
+    pub fn new<P>(path: P) -> Result<Self, StashesError>
     where
         P: AsRef<Path>,
     {
-        DirStashes {
-            path: path.as_ref().into(),
-            next_change_id: Some("index".to_string()),
+        let mut stashes = DirStashes::from_change_id(path, "index")?;
+        if !stashes.unread.contains("index") {
+            stashes.next_change_id = earliest_later(&stashes.unread, "index").0.cloned();
+        }
+        Ok(stashes)
+    }
+
+    pub fn from_change_id<P>(path: P, change_id: &str) -> Result<Self, StashesError>
+    where
+        P: AsRef<Path>,
+    {
+        let mut unread = HashSet::new();
+        for entry in std::fs::read_dir(&path)? {
+            let name = entry?.file_name().to_string_lossy().to_string();
+            if name.ends_with(".json") {
+                unread.insert(entry_change_id(&name).to_string());
+            }
         }
+        Ok(DirStashes {
+            path: path.as_ref().into(),
+            next_change_id: Some(change_id.to_string()),
+            unread,
+            pending: None,
+        })
     }
+}
+
+impl IntoIterator for DirStashes {
+    type Item = StashesEntry;
+    type IntoIter = StashesIterator;
 
-    pub fn into_iter(self) -> StashesIterator {
+    fn into_iter(self) -> StashesIterator {
         StashesIterator {
             src: Box::new(self),
         }
@@ -61,51 +204,68 @@ impl DirStashes {
 }
 
 impl StashesSource for DirStashes {
-    fn next(&mut self) -> Option<(String, String)> {
-        if let Some(id) = self.next_change_id.take() {
-            let filename = format!("{}.json", id);
-            let mut f = match File::open(self.path.join(filename.clone())) {
-                Ok(f) => f,
-                Err(_) => return None,
-            };
-            let mut s = String::with_capacity(
-                f.metadata()
-                    .expect(&format!("cannot read file: {}", id))
-                    .size() as usize,
-            );
-            f.read_to_string(&mut s).expect("cannot read to string");
-            let change_id = extract_next_change_id(&s).map(|s| s.to_string());
-            self.next_change_id = change_id;
-            Some((filename, s))
-        } else {
-            None
+    fn next(&mut self) -> Option<StashesEntry> {
+        if let Some(e) = self.pending.take() {
+            return Some(Err(e));
+        }
+        let id = self.next_change_id.take()?;
+        let filename = format!("{}.json", id);
+        let data = match std::fs::read(self.path.join(&filename)) {
+            Ok(d) => d,
+            // end of chain, unless there are later files it doesn't reach
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                let (earliest, remaining) = earliest_later(&self.unread, &id);
+                self.next_change_id = Some(earliest?.clone());
+                return Some(Err(StashesError::Gap {
+                    expected: id,
+                    remaining,
+                }));
+            }
+            Err(e) => return Some(Err(e.into())),
+        };
+        self.unread.remove(&id);
+        let Ok(s) = String::from_utf8(data) else {
+            return Some(Err(StashesError::Utf8(filename)));
+        };
+        self.next_change_id = extract_next_change_id(&s);
+        if self.next_change_id.is_none() {
+            let (earliest, remaining) = earliest_later(&self.unread, &id);
+            if let Some(earliest) = earliest {
+                self.next_change_id = Some(earliest.clone());
+                self.pending = Some(StashesError::NoNextChangeId {
+                    file: filename.clone(),
+                    remaining,
+                });
+            }
         }
+        Some(Ok((filename, s)))
     }
 }
 
+// libarchive supported archive (zip, 7z, tar.*), entries are read sequentially in archive order
 pub struct ArchiveStashes {
-    data: Vec<u8>,
-    next_change_id: Option<String>,
-    buf: Vec<u8>,
+    entries: ArchiveIterator<BufReader<File>>,
+    current: Option<(String, Vec<u8>)>,
 }
 
 impl ArchiveStashes {
-    pub fn new<P>(path: P) -> Self
+    pub fn new<P>(path: P) -> Result<Self, StashesError>
     where
         P: AsRef<Path>,
     {
-        let mut src = File::open(path).expect("cannot open archive");
-        let mut archive = Vec::with_capacity(src.metadata().unwrap().size() as usize);
-        src.read_to_end(&mut archive)
-            .expect("cannot read archive into memory");
-        ArchiveStashes {
-            data: archive,
-            next_change_id: Some("index".to_string()),
-            buf: Vec::with_capacity(10 * 1024 * 1024),
-        }
+        let src = BufReader::new(File::open(path)?);
+        Ok(ArchiveStashes {
+            entries: ArchiveIterator::from_read(src)?,
+            current: None,
+        })
     }
+}
+
+impl IntoIterator for ArchiveStashes {
+    type Item = StashesEntry;
+    type IntoIter = StashesIterator;
 
-    pub fn into_iter(self) -> StashesIterator {
+    fn into_iter(self) -> StashesIterator {
         StashesIterator {
             src: Box::new(self),
         }
@@ -113,31 +273,412 @@ impl ArchiveStashes {
 }
 
 impl StashesSource for ArchiveStashes {
-    fn next(&mut self) -> Option<(String, String)> {
-        if let Some(id) = self.next_change_id.take() {
-            let filename = format!("{}.json", id);
-            self.buf.clear();
-            let mut cur = Cursor::new(&self.data);
-            let bytes = uncompress_archive_file(&mut cur, &mut self.buf, &filename)
-                .expect("cannot read file from archive");
-            let s = unsafe { String::from_utf8_unchecked((&self.buf[..bytes]).to_vec()) };
-            let change_id = extract_next_change_id(&s).map(|s| s.to_string());
-            self.next_change_id = change_id;
-            Some((filename, s))
+    fn next(&mut self) -> Option<StashesEntry> {
+        loop {
+            match self.entries.next()? {
+                ArchiveContents::StartOfEntry(name, _) => {
+                    self.current = name
+                        .ends_with(".json")
+                        .then(|| (file_name(&name), Vec::new()));
+                }
+                ArchiveContents::DataChunk(chunk) => {
+                    if let Some((_, buf)) = &mut self.current {
+                        buf.extend_from_slice(&chunk);
+                    }
+                }
+                ArchiveContents::EndOfEntry => {
+                    if let Some((name, buf)) = self.current.take() {
+                        return Some(match String::from_utf8(buf) {
+                            Ok(s) => Ok((name, s)),
+                            Err(_) => Err(StashesError::Utf8(name)),
+                        });
+                    }
+                }
+                ArchiveContents::Err(e) => return Some(Err(e.into())),
+            }
+        }
+    }
+}
+
+fn file_name(entry: &str) -> String {
+    Path::new(entry)
+        .file_name()
+        .map_or(entry.to_string(), |n| n.to_string_lossy().to_string())
+}
+
+// one stash response per line, optionally compressed with zstd or gzip.
+// lines don't store change id they were fetched with, entries are named after
+// next_change_id of previous line, so missing lines can't be detected
+pub struct JsonlStashes {
+    lines: Box<dyn BufRead>,
+    change_id: String,
+    line: usize,
+}
+
+impl JsonlStashes {
+    pub fn new<P>(path: P) -> Result<Self, StashesError>
+    where
+        P: AsRef<Path>,
+    {
+        let p = path.as_ref();
+        let f = File::open(p)?;
+        let name = p.to_string_lossy();
+        let src: Box<dyn Read> = if name.ends_with(".zst") || name.ends_with(".zstd") {
+            Box::new(zstd::Decoder::new(f)?)
+        } else if name.ends_with(".gz") {
+            Box::new(flate2::read::MultiGzDecoder::new(f))
         } else {
-            None
+            Box::new(f)
+        };
+        Ok(JsonlStashes {
+            lines: Box::new(BufReader::new(src)),
+            change_id: "index".to_string(),
+            line: 0,
+        })
+    }
+}
+
+impl IntoIterator for JsonlStashes {
+    type Item = StashesEntry;
+    type IntoIter = StashesIterator;
+
+    fn into_iter(self) -> StashesIterator {
+        StashesIterator {
+            src: Box::new(self),
+        }
+    }
+}
+
+impl StashesSource for JsonlStashes {
+    fn next(&mut self) -> Option<StashesEntry> {
+        let mut s = String::new();
+        loop {
+            self.line += 1;
+            match self.lines.read_line(&mut s) {
+                Ok(0) => return None,
+                Ok(_) if s.trim().is_empty() => s.clear(),
+                Ok(_) => break,
+                Err(e) if e.kind() == ErrorKind::InvalidData => {
+                    return Some(Err(StashesError::Utf8(format!("line {}", self.line))))
+                }
+                Err(e) => return Some(Err(e.into())),
+            }
         }
+        let s = s.trim_end().to_string();
+        let filename = format!("{}.json", self.change_id);
+        if let Some(id) = extract_next_change_id(&s) {
+            self.change_id = id;
+        }
+        Some(Ok((filename, s)))
+    }
+
+    fn verifiable(&self) -> bool {
+        false
     }
 }
 
-pub fn open_stashes<P>(path: P) -> StashesIterator
+fn is_jsonl(p: &Path) -> bool {
+    let name = p.to_string_lossy();
+    [".jsonl", ".jsonl.gz", ".jsonl.zst", ".jsonl.zstd"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+}
+
+pub fn open_stashes<P>(path: P) -> Result<StashesIterator, StashesError>
+where
+    P: AsRef<Path>,
+{
+    let p = path.as_ref();
+    Ok(if std::fs::metadata(p)?.is_dir() {
+        DirStashes::new(p)?.into_iter()
+    } else if is_jsonl(p) {
+        JsonlStashes::new(p)?.into_iter()
+    } else {
+        ArchiveStashes::new(p)?.into_iter()
+    })
+}
+
+// directories are read starting from given change id, other sources skip entries before it
+pub fn open_stashes_from<P>(path: P, change_id: &str) -> Result<StashesIterator, StashesError>
 where
     P: AsRef<Path>,
 {
     let p = path.as_ref();
-    if std::fs::metadata(p).unwrap().is_dir() {
-        DirStashes::new(p).into_iter()
+    if std::fs::metadata(p)?.is_dir() {
+        Ok(DirStashes::from_change_id(p, change_id)?.into_iter())
     } else {
-        ArchiveStashes::new(p).into_iter()
+        Ok(open_stashes(p)?.resume_from(change_id))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gap {
+    // next_change_id of previous entry
+    pub expected: String,
+    // change id of entry read instead, None if chain ended
+    pub found: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ChainReport {
+    // false if gaps can't be detected, see `StashesIterator::is_verifiable`
+    pub verifiable: bool,
+    pub entries: usize,
+    pub gaps: Vec<Gap>,
+    // change ids which are next for more than one entry
+    pub forks: Vec<String>,
+    // entries without readable next_change_id
+    pub invalid: Vec<String>,
+}
+
+impl ChainReport {
+    pub fn is_valid(&self) -> bool {
+        self.verifiable
+            && self.gaps.is_empty()
+            && self.forks.is_empty()
+            && self.invalid.is_empty()
+    }
+}
+
+// checks that every entry is fetched with next_change_id of previous one,
+// reading continues after gaps so all of them are reported
+pub fn validate_chain(entries: StashesIterator) -> Result<ChainReport, StashesError> {
+    let mut report = ChainReport {
+        verifiable: entries.is_verifiable(),
+        ..Default::default()
+    };
+    let mut expected: Option<String> = None;
+    let mut missing: Option<String> = None;
+    let mut next_ids: HashMap<String, usize> = HashMap::new();
+    for entry in entries {
+        let (name, data) = match entry {
+            Ok(e) => e,
+            Err(StashesError::Gap { expected, .. }) => {
+                missing = Some(expected);
+                continue;
+            }
+            // entry is already counted as invalid
+            Err(StashesError::NoNextChangeId { .. }) => continue,
+            Err(e) => return Err(e),
+        };
+        report.entries += 1;
+        let id = entry_change_id(&name);
+        if let Some(expected) = missing
+            .take()
+            .or(expected.take())
+            .filter(|e| report.verifiable && e != id)
+        {
+            report.gaps.push(Gap {
+                expected,
+                found: Some(id.to_string()),
+            });
+        }
+        match extract_next_change_id(&data) {
+            Some(next) => {
+                *next_ids.entry(next.clone()).or_default() += 1;
+                expected = Some(next);
+            }
+            None => report.invalid.push(id.to_string()),
+        }
+    }
+    if let Some(expected) = missing {
+        report.gaps.push(Gap {
+            expected,
+            found: None,
+        });
+    }
+    report.forks = next_ids
+        .into_iter()
+        .filter(|(_, c)| *c > 1)
+        .map(|(id, _)| id)
+        .collect();
+    report.forks.sort();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::{
+        extract_next_change_id, open_stashes, open_stashes_from, validate_chain, Gap, StashesError,
+    };
+
+    const STASHES: [&str; 2] = [
+        r#"{"next_change_id": "1-1", "stashes": []}"#,
+        r#"{"next_change_id": "2-2", "stashes": []}"#,
+    ];
+
+    fn read_all(path: &std::path::Path) -> Result<Vec<(String, String)>, StashesError> {
+        open_stashes(path)?.collect()
+    }
+
+    #[test]
+    fn read_sources() -> anyhow::Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let dir = tmp.path();
+        let chain = dir.join("chain");
+        std::fs::create_dir_all(&chain)?;
+        std::fs::write(chain.join("index.json"), STASHES[0])?;
+        std::fs::write(chain.join("1-1.json"), STASHES[1])?;
+        let expected = vec![
+            ("index.json".to_string(), STASHES[0].to_string()),
+            ("1-1.json".to_string(), STASHES[1].to_string()),
+        ];
+        assert_eq!(read_all(&chain)?, expected);
+
+        // chain without index starts from the earliest change id
+        let rotated = dir.join("rotated");
+        std::fs::create_dir_all(&rotated)?;
+        std::fs::write(rotated.join("1-1.json"), STASHES[1])?;
+        std::fs::write(rotated.join("0-1.json"), STASHES[0])?;
+        let names: Vec<_> = read_all(&rotated)?.into_iter().map(|(n, _)| n).collect();
+        assert_eq!(names, ["0-1.json", "1-1.json"]);
+
+        let jsonl = STASHES.join("\n") + "\n";
+        let path = dir.join("dump.jsonl.zst");
+        std::fs::write(&path, zstd::encode_all(jsonl.as_bytes(), 0)?)?;
+        assert_eq!(read_all(&path)?, expected);
+
+        let path = dir.join("dump.jsonl.gz");
+        let mut enc = flate2::write::GzEncoder::new(vec![], flate2::Compression::fast());
+        enc.write_all(jsonl.as_bytes())?;
+        std::fs::write(&path, enc.finish()?)?;
+        assert_eq!(read_all(&path)?, expected);
+
+        // nested entries are named by file name, other files are skipped
+        let mut tar = tar::Builder::new(vec![]);
+        for (name, data) in [
+            ("stashes/index.json", STASHES[0]),
+            ("stashes/README", "not stashes"),
+            ("stashes/1-1.json", STASHES[1]),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            tar.append_data(&mut header, name, data.as_bytes())?;
+        }
+        let tar = tar.into_inner()?;
+        let path = dir.join("stashes.tar");
+        std::fs::write(&path, &tar)?;
+        assert_eq!(read_all(&path)?, expected);
+        let path = dir.join("stashes.tar.zst");
+        std::fs::write(&path, zstd::encode_all(&tar[..], 0)?)?;
+        assert_eq!(read_all(&path)?, expected);
+
+        let path = dir.join("broken.jsonl");
+        std::fs::write(&path, b"{\"next_change_id\": \"\xff\"}\n")?;
+        assert!(matches!(read_all(&path), Err(StashesError::Utf8(_))));
+        assert!(open_stashes(dir.join("missing.zip")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn next_change_id() {
+        assert_eq!(extract_next_change_id(STASHES[0]).as_deref(), Some("1-1"));
+        assert_eq!(extract_next_change_id(r#"{"next_change_id": "#), None);
+        assert_eq!(extract_next_change_id("{}"), None);
+        // only top level field counts
+        assert_eq!(
+            extract_next_change_id(
+                r#"{"stashes": [{"stash": "\"next_change_id\": \"x\"", "next_change_id": "y"}], "next_change_id": "3-3"}"#
+            )
+            .as_deref(),
+            Some("3-3")
+        );
+    }
+
+    #[test]
+    fn chain_validation() -> anyhow::Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let dir = tmp.path();
+        std::fs::write(dir.join("index.json"), STASHES[0])?;
+        std::fs::write(dir.join("1-1.json"), STASHES[1])?;
+        // 2-2 and 4-4 are missing
+        std::fs::write(
+            dir.join("3-3.json"),
+            r#"{"next_change_id": "4-4", "stashes": []}"#,
+        )?;
+        std::fs::write(
+            dir.join("5-5.json"),
+            r#"{"next_change_id": "6-6", "stashes": []}"#,
+        )?;
+
+        let report = validate_chain(open_stashes(dir)?)?;
+        assert!(report.verifiable);
+        assert_eq!(report.entries, 4);
+        assert_eq!(
+            report.gaps,
+            [
+                Gap {
+                    expected: "2-2".to_string(),
+                    found: Some("3-3".to_string()),
+                },
+                Gap {
+                    expected: "4-4".to_string(),
+                    found: Some("5-5".to_string()),
+                },
+            ]
+        );
+
+        let mut resumed = open_stashes_from(dir, "3-3")?;
+        assert_eq!(resumed.next().transpose()?.unwrap().0, "3-3.json");
+        assert!(matches!(resumed.next(), Some(Err(StashesError::Gap { .. }))));
+        assert_eq!(resumed.next().transpose()?.unwrap().0, "5-5.json");
+        assert!(resumed.next().is_none());
+
+        let jsonl = [
+            STASHES[0],
+            STASHES[1],
+            r#"{"next_change_id": "2-2", "stashes": []}"#,
+            r#"{"next_change_id": "#,
+        ]
+        .join("\n");
+        let path = dir.join("dump.jsonl");
+        std::fs::write(&path, jsonl)?;
+        let report = validate_chain(open_stashes(&path)?)?;
+        // entry names follow previous line, so only forks and invalid entries are found
+        assert!(!report.verifiable);
+        assert!(report.gaps.is_empty());
+        assert_eq!(report.entries, 4);
+        assert_eq!(report.forks, ["2-2"]);
+        assert_eq!(report.invalid, ["2-2"]);
+        assert!(!report.is_valid());
+
+        let resumed: Vec<_> = open_stashes_from(&path, "1-1")?.collect::<Result<_, _>>()?;
+        assert_eq!(resumed.len(), 3);
+        assert!(matches!(
+            open_stashes_from(&path, "9-9")?.next(),
+            Some(Err(StashesError::NotFound(_)))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn truncated_entry() -> anyhow::Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let dir = tmp.path();
+        std::fs::write(dir.join("1-1.json"), r#"{"next_change_id": "#)?;
+        std::fs::write(
+            dir.join("3-3.json"),
+            r#"{"next_change_id": "4-4", "stashes": []}"#,
+        )?;
+
+        let mut entries = open_stashes_from(dir, "1-1")?;
+        assert_eq!(entries.next().transpose()?.unwrap().0, "1-1.json");
+        assert!(matches!(
+            entries.next(),
+            Some(Err(StashesError::NoNextChangeId { remaining: 1, .. }))
+        ));
+        assert_eq!(entries.next().transpose()?.unwrap().0, "3-3.json");
+        assert!(entries.next().is_none());
+
+        let report = validate_chain(open_stashes(dir)?)?;
+        assert_eq!(report.entries, 2);
+        assert_eq!(report.invalid, ["1-1"]);
+        assert!(report.gaps.is_empty());
+        Ok(())
     }
 }